@@ -1,5 +1,6 @@
 //! Test pattern generation
 
+use std::collections::HashSet;
 use std::iter::zip;
 
 use kdam::{tqdm, BarExt};
@@ -7,9 +8,51 @@ use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
 
 use crate::equiv::{difference, prove};
-use crate::sim::{detects_faults, detects_faults_multi, Fault};
+use crate::sim::{
+    detects_faults, detects_faults_x_multi, pack_patterns, BridgeType, Fault,
+    ParallelFaultSimulator,
+};
+use crate::utils::FanoutView;
 use crate::{Gate, Network, Signal};
 
+mod bist;
+mod compaction;
+mod compactor;
+mod diagnosis;
+mod incremental;
+mod path_delay;
+mod podem;
+mod report;
+mod scan;
+mod sequential;
+mod test_points;
+mod testability;
+mod three_valued;
+mod transition;
+
+pub use bist::{
+    best_bist_seed, bist_coverage, bist_patterns, bist_reseeding, BistCoverage, BistGenerator,
+    BistSource,
+};
+pub use compaction::Compaction;
+use compaction::{exact_cover, greedy_cover, ordered_cover, random_order_cover};
+pub use compactor::{add_compactor, analyze_compaction, CompactionReport, Compactor};
+pub use diagnosis::{diagnose, DiagnosisCandidate};
+pub use incremental::{generate_incremental_comb_test_patterns, IncrementalReport};
+pub use path_delay::{
+    generate_path_delay_test, generate_path_delay_tests, longest_paths, Path, PathDelayResult,
+    PathStatus,
+};
+pub use podem::{podem, PodemResult};
+use podem::{podem_extend, relax_pattern};
+pub use report::{report_comb_test_patterns, AtpgReport, FaultReport, FaultStatus};
+pub use scan::{insert_scan_chains, ScanChains, ScanOrder};
+pub use sequential::generate_seq_test_patterns;
+pub use test_points::{insert_test_points, select_test_points, test_point_coverage, TestPoint};
+pub use testability::{Cop, Scoap, SCOAP_INFINITY};
+use three_valued::find_pattern_detecting_fault_x;
+pub use transition::{generate_transition_test_patterns, LaunchMode, TwoFrameNetwork};
+
 /// Expose flip_flops as inputs for ATPG
 ///
 /// Flip-flop outputs are exposed are primary inputs. Flip-flop inputs, including
@@ -17,6 +60,15 @@ use crate::{Gate, Network, Signal};
 /// The new inputs and outputs are added after the original inputs, and their order
 /// matches the order of the flip flops.
 pub fn expose_dff(aig: &Network) -> Network {
+    expose_dff_unobserved(aig, &[])
+}
+
+/// Expose flip-flops as inputs for ATPG, without observing the inputs of some of them
+///
+/// This is the same as [`expose_dff`], except that the data, enable and reset of the flip-flops
+/// given by gate index do not become outputs. This is used for flip-flops that are not scanned,
+/// such as uninitialized flip-flops with an unknown value.
+pub fn expose_dff_unobserved(aig: &Network, unobserved: &[usize]) -> Network {
     let mut ret = Network::new();
     ret.add_inputs(aig.nb_inputs());
     for i in 0..aig.nb_outputs() {
@@ -26,6 +78,9 @@ pub fn expose_dff(aig: &Network) -> Network {
         if let Gate::Dff([d, en, res]) = aig.gate(i) {
             let new_input = ret.add_input();
             ret.add(Gate::Buf(new_input));
+            if unobserved.contains(&i) {
+                continue;
+            }
             ret.add_output(*d);
             if !en.is_constant() {
                 ret.add_output(*en);
@@ -42,6 +97,54 @@ pub fn expose_dff(aig: &Network) -> Network {
     ret
 }
 
+/// Create a copy of the network with a bridge between two gates
+///
+/// The bridged values are computed from a fault-free copy of the network, so that the result
+/// is acyclic even if the second gate depends on the first one.
+fn bridged_network(aig: &Network, a: usize, b: usize, kind: BridgeType) -> Network {
+    let mut ret = Network::new();
+    ret.add_inputs(aig.nb_inputs());
+    for i in 0..aig.nb_nodes() {
+        ret.add(aig.gate(i).clone());
+    }
+    let sa = Signal::from_var(a as u32);
+    let sb = Signal::from_var(b as u32);
+    let (va, vb) = match kind {
+        BridgeType::WiredAnd => {
+            let x = ret.add(Gate::and(sa, sb));
+            (x, x)
+        }
+        BridgeType::WiredOr => {
+            let x = !ret.add(Gate::and(!sa, !sb));
+            (x, x)
+        }
+        BridgeType::DominantA => (sa, sa),
+        BridgeType::DominantB => (sb, sb),
+    };
+    let offset = ret.nb_nodes() as u32;
+    let t = |s: &Signal| -> Signal {
+        if s.is_var() {
+            Signal::from_var(s.var() + offset) ^ s.is_inverted()
+        } else {
+            *s
+        }
+    };
+    for i in 0..aig.nb_nodes() {
+        if i == a {
+            ret.add(Gate::Buf(va));
+        } else if i == b {
+            ret.add(Gate::Buf(vb));
+        } else {
+            ret.add(aig.gate(i).remap(t));
+        }
+    }
+    for o in 0..aig.nb_outputs() {
+        ret.add_output(t(&aig.output(o)));
+    }
+    ret.check();
+    ret
+}
+
 /// Find a new test pattern for a specific fault using a SAT solver
 ///
 /// Each gate may be in one of two cases:
@@ -49,9 +152,27 @@ pub fn expose_dff(aig: &Network) -> Network {
 ///     * elsewhere, where they don't need to be duplicated
 /// To keep things simpler, we create the full network with/without the fault, and let basic
 /// deduplication handle the rest.
+/// Transition faults require two timesteps, and are never detected by a single pattern:
+/// they are handled on a [`TwoFrameNetwork`].
 fn find_pattern_detecting_fault(aig: &Network, fault: Fault) -> Option<Vec<bool>> {
     assert!(aig.is_comb());
 
+    if let Fault::TransitionFault { .. } = fault {
+        return None;
+    }
+    let fault_aig = faulty_network(aig, fault);
+    let mut diff = difference(aig, &fault_aig, None);
+    diff.make_canonical();
+    diff.cleanup();
+    let ret = prove(&diff);
+    if let Some(pattern) = &ret {
+        assert_eq!(detects_faults(aig, &pattern, &vec![fault]), vec![true]);
+    }
+    ret
+}
+
+/// Create a copy of the network with a stuck-at or bridging fault injected
+fn faulty_network(aig: &Network, fault: Fault) -> Network {
     let mut fault_aig = aig.clone();
     match fault {
         Fault::OutputStuckAtFault { gate, value } => {
@@ -70,16 +191,18 @@ fn find_pattern_detecting_fault(aig: &Network, fault: Fault) -> Option<Vec<bool>
                 );
             fault_aig.replace(gate, g);
         }
+        Fault::TransitionFault { .. } => {
+            // Single timestep: the gate has no previous value to keep
+        }
+        Fault::BridgingFault { a, b, kind } => {
+            fault_aig = bridged_network(aig, a, b, kind);
+        }
+        Fault::LutBitFlipFault { gate, .. } | Fault::DefectFault { gate, .. } => {
+            let lut = fault.faulty_function(aig).unwrap();
+            fault_aig.replace(gate, Gate::lut(aig.gate(gate).dependencies(), lut));
+        }
     };
-
-    let mut diff = difference(aig, &fault_aig);
-    diff.make_canonical();
-    diff.cleanup();
-    let ret = prove(&diff);
-    if let Some(pattern) = &ret {
-        assert_eq!(detects_faults(aig, &pattern, &vec![fault]), vec![true]);
-    }
-    ret
+    fault_aig
 }
 
 /// Generate random patterns with a given number of timesteps
@@ -118,13 +241,50 @@ pub fn generate_random_comb_patterns(
 /// Handling of the actual test pattern generation
 struct TestPatternGenerator<'a> {
     aig: &'a Network,
+    two_frame: Option<&'a TwoFrameNetwork>,
     faults: Vec<Fault>,
+    /// Whether each fault is targeted by deterministic test generation, or only simulated
+    targeted: Vec<bool>,
     patterns: Vec<Vec<bool>>,
-    pattern_detections: Vec<Vec<bool>>,
-    detection: Vec<bool>,
+    /// Distinct patterns of the current set: a duplicate pattern is kept, but detects no fault
+    distinct_patterns: HashSet<Vec<bool>>,
+    /// Faults detected by each pattern, as bitsets
+    pattern_detections: Vec<Vec<u64>>,
+    /// Number of distinct patterns detecting each fault
+    detection: Vec<usize>,
+    /// Number of distinct patterns required to detect each fault
+    n_detect: usize,
     rng: SmallRng,
+    /// Fanout of each gate, for PODEM
+    fanouts: FanoutView,
+    /// Number of backtracks before PODEM gives up on a fault and SAT is used instead
+    podem_backtrack_limit: usize,
+    /// Number of faults resolved by PODEM, as (detected, untestable)
+    podem_resolved: (usize, usize),
+    /// Number of faults resolved by SAT, as (detected, untestable)
+    sat_resolved: (usize, usize),
+    /// Fault simulator, kept for the whole run
+    fault_sim: ParallelFaultSimulator<'a>,
+    /// Compaction algorithm for the final set of patterns
+    compaction: Compaction,
+    /// Whether to extend the deterministic patterns to detect additional faults
+    dynamic_compaction: bool,
+    /// Inputs with an unknown value, for 3-valued fault simulation
+    x_inputs: Vec<usize>,
 }
 
+/// Default number of backtracks before PODEM gives up on a fault and SAT is used instead
+const PODEM_BACKTRACK_LIMIT: usize = 100;
+
+/// Number of additional faults targeted by dynamic compaction for each deterministic pattern
+const DYNAMIC_COMPACTION_TARGETS: usize = 32;
+
+/// Number of backtracks before PODEM gives up on an additional fault during dynamic compaction
+const DYNAMIC_COMPACTION_BACKTRACK_LIMIT: usize = 10;
+
+/// Number of random completions of a test cube tried for each additional detection in N-detect mode
+const N_DETECT_ATTEMPTS: usize = 4;
+
 impl<'a> TestPatternGenerator<'a> {
     pub fn nb_faults(&self) -> usize {
         self.faults.len()
@@ -135,7 +295,17 @@ impl<'a> TestPatternGenerator<'a> {
     }
 
     pub fn nb_detected(&self) -> usize {
-        self.detection.iter().filter(|b| **b).count()
+        self.detection.iter().filter(|d| **d > 0).count()
+    }
+
+    /// Number of detections counted towards the N-detect target, at most N per fault
+    fn nb_useful_detections(&self) -> usize {
+        self.detection.iter().map(|d| (*d).min(self.n_detect)).sum()
+    }
+
+    /// Returns whether the fault is detected by enough patterns
+    fn is_complete(&self, fault_index: usize) -> bool {
+        self.detection[fault_index] >= self.n_detect
     }
 
     /// Initialize the generator from a network and a seed
@@ -144,18 +314,116 @@ impl<'a> TestPatternGenerator<'a> {
         let nb_faults = faults.len();
         TestPatternGenerator {
             aig,
+            two_frame: None,
             faults: faults,
+            targeted: vec![true; nb_faults],
             patterns: Vec::new(),
+            distinct_patterns: HashSet::new(),
             pattern_detections: Vec::new(),
-            detection: vec![false; nb_faults],
+            detection: vec![0; nb_faults],
+            n_detect: 1,
             rng: SmallRng::seed_from_u64(seed),
+            fanouts: FanoutView::new(aig),
+            podem_backtrack_limit: PODEM_BACKTRACK_LIMIT,
+            podem_resolved: (0, 0),
+            sat_resolved: (0, 0),
+            fault_sim: ParallelFaultSimulator::new(aig, 1),
+            compaction: Compaction::Greedy,
+            dynamic_compaction: false,
+            x_inputs: Vec::new(),
         }
     }
 
-    /// Extend a vector of boolean vectors with 64 elements at once
-    fn extend_vec(v: &mut Vec<Vec<bool>>, added: Vec<u64>) {
+    /// Initialize the generator from a two-frame network, to target faults of the original network
+    pub fn from_two_frame(
+        two_frame: &'a TwoFrameNetwork,
+        faults: Vec<Fault>,
+        seed: u64,
+    ) -> TestPatternGenerator<'a> {
+        let mut ret = Self::from(two_frame.network(), faults, seed);
+        ret.two_frame = Some(two_frame);
+        ret
+    }
+
+    /// Analyze which patterns detect the faults
+    fn detects_faults_multi(&mut self, patterns: &Vec<u64>, faults: &Vec<Fault>) -> Vec<u64> {
+        match self.two_frame {
+            Some(t) => t.detects_faults_multi(&mut self.fault_sim, patterns, faults),
+            None if !self.x_inputs.is_empty() => {
+                let nb_threads = self.fault_sim.nb_threads();
+                detects_faults_x_multi(self.aig, patterns, &self.x_inputs, faults, nb_threads)
+            }
+            None => self
+                .fault_sim
+                .detects_faults_multi(std::slice::from_ref(patterns), faults)
+                .pop()
+                .unwrap(),
+        }
+    }
+
+    /// Find a pattern detecting a fault
+    ///
+    /// PODEM is tried first, and a SAT solver is used for the faults it aborts.
+    /// With X sources, only the SAT solver is used, on a 3-valued encoding of the network.
+    fn find_pattern_detecting_fault(&mut self, fault: Fault) -> Option<Vec<bool>> {
+        if self.two_frame.is_none() && self.x_inputs.is_empty() {
+            match podem(self.aig, &self.fanouts, fault, self.podem_backtrack_limit) {
+                PodemResult::Detected(p) => {
+                    self.podem_resolved.0 += 1;
+                    return Some(p);
+                }
+                PodemResult::Untestable => {
+                    self.podem_resolved.1 += 1;
+                    return None;
+                }
+                PodemResult::Aborted => (),
+            }
+        }
+        let ret = match self.two_frame {
+            Some(t) => t.find_pattern_detecting_fault(fault),
+            None if !self.x_inputs.is_empty() => {
+                find_pattern_detecting_fault_x(self.aig, fault, &self.x_inputs)
+            }
+            None => find_pattern_detecting_fault(self.aig, fault),
+        };
+        if ret.is_some() {
+            self.sat_resolved.0 += 1;
+        } else {
+            self.sat_resolved.1 += 1;
+        }
+        ret
+    }
+
+    /// Extend the patterns with 64 patterns at once
+    ///
+    /// Returns the mask of the patterns that were not already in the set.
+    fn extend_patterns(&mut self, added: Vec<u64>) -> u64 {
+        let mut distinct = 0;
+        for i in 0..64 {
+            let pattern: Vec<bool> = added.iter().map(|d| (d >> i) & 1 != 0).collect();
+            if self.distinct_patterns.insert(pattern.clone()) {
+                distinct |= 1 << i;
+            }
+            self.patterns.push(pattern);
+        }
+        distinct
+    }
+
+    /// Add a pattern to the set, and return whether it was not already in the set
+    fn push_pattern(&mut self, pattern: Vec<bool>) -> bool {
+        let distinct = self.distinct_patterns.insert(pattern.clone());
+        self.patterns.push(pattern);
+        distinct
+    }
+
+    /// Extend the detection bitsets with 64 patterns at once, from the detections of each fault
+    fn extend_detections(&mut self, added: Vec<u64>) {
         for i in 0..64 {
-            v.push(added.iter().map(|d| (d >> i) & 1 != 0).collect());
+            let mut det = vec![0u64; self.nb_faults().div_ceil(64)];
+            for (f, d) in added.iter().enumerate() {
+                det[f / 64] |= ((d >> i) & 1) << (f % 64);
+            }
+            self.pattern_detections.push(det);
         }
     }
 
@@ -164,7 +432,7 @@ impl<'a> TestPatternGenerator<'a> {
         let mut faults = Vec::new();
         let mut indices = Vec::new();
         for (i, f) in self.faults.iter().enumerate() {
-            if check_already_detected || !self.detection[i] {
+            if check_already_detected || !self.is_complete(i) {
                 faults.push(*f);
                 indices.push(i);
             }
@@ -173,16 +441,17 @@ impl<'a> TestPatternGenerator<'a> {
     }
 
     /// Add a single pattern to the current set
-    #[allow(dead_code)]
     pub fn add_single_pattern(&mut self, pattern: Vec<bool>, check_already_detected: bool) {
         let (faults, indices) = self.get_faults(check_already_detected);
-        let detected = detects_faults(self.aig, &pattern, &faults);
-        let mut det = vec![false; self.nb_faults()];
+        let multi_pattern = pattern.iter().map(|b| if *b { !0 } else { 0 }).collect();
+        let detected = self.detects_faults_multi(&multi_pattern, &faults);
+        let distinct = self.push_pattern(pattern);
+        let mut det = vec![0u64; self.nb_faults().div_ceil(64)];
         for (i, d) in zip(indices, detected) {
-            self.detection[i] |= d;
-            det[i] = d;
+            let v = (distinct && d != 0) as u64;
+            self.detection[i] += v as usize;
+            det[i / 64] |= v << (i % 64);
         }
-        self.patterns.push(pattern);
         self.pattern_detections.push(det);
     }
 
@@ -206,14 +475,14 @@ impl<'a> TestPatternGenerator<'a> {
     /// Add a new set of patterns to the current set
     pub fn add_patterns(&mut self, patterns: Vec<u64>, check_already_detected: bool) {
         let (faults, indices) = self.get_faults(check_already_detected);
-        let detected = detects_faults_multi(self.aig, &patterns, &faults);
+        let detected = self.detects_faults_multi(&patterns, &faults);
+        let distinct = self.extend_patterns(patterns);
         let mut det = vec![0; self.nb_faults()];
         for (i, d) in zip(indices, detected) {
-            self.detection[i] |= d != 0;
-            det[i] = d;
+            self.detection[i] += (d & distinct).count_ones() as usize;
+            det[i] = d & distinct;
         }
-        Self::extend_vec(&mut self.patterns, patterns);
-        Self::extend_vec(&mut self.pattern_detections, det);
+        self.extend_detections(det);
     }
 
     /// Generate a random pattern and add it to the current set
@@ -224,6 +493,25 @@ impl<'a> TestPatternGenerator<'a> {
         self.add_patterns(pattern, check_already_detected);
     }
 
+    /// Add existing patterns to the current set, and grade them against all faults
+    pub fn add_existing_patterns(&mut self, patterns: &[Vec<bool>]) {
+        let faults = self.faults.clone();
+        let (packed, _) = pack_patterns(patterns, self.aig.nb_inputs());
+        for (chunk, words) in zip(patterns.chunks(64), packed) {
+            let detected = self.detects_faults_multi(&words, &faults);
+            for (b, p) in chunk.iter().enumerate() {
+                let distinct = self.push_pattern(p.clone());
+                let mut det = vec![0u64; self.nb_faults().div_ceil(64)];
+                for (i, d) in detected.iter().enumerate() {
+                    let v = (d >> b) & u64::from(distinct);
+                    self.detection[i] += v as usize;
+                    det[i / 64] |= v << (i % 64);
+                }
+                self.pattern_detections.push(det);
+            }
+        }
+    }
+
     /// Check consistency
     pub fn check(&self) {
         assert_eq!(self.patterns.len(), self.pattern_detections.len());
@@ -231,102 +519,193 @@ impl<'a> TestPatternGenerator<'a> {
             assert_eq!(p.len(), self.aig.nb_inputs());
         }
         for p in &self.pattern_detections {
-            assert_eq!(p.len(), self.nb_faults());
+            assert_eq!(p.len(), self.nb_faults().div_ceil(64));
         }
         assert_eq!(self.detection.len(), self.nb_faults());
     }
 
-    /// Compress the existing patterns to keep as few as possible.
-    /// This is a minimum set cover problem.
-    /// At the moment we solve it with a simple greedy algorithm,
-    /// taking the pattern that detects the most new faults each time.
-    pub fn compress_patterns(&mut self) {
-        let mut progress =
-            tqdm!(total = 2 * self.nb_faults() * self.nb_patterns() + self.nb_detected());
-        progress.set_description("Compression progress");
-        progress
-            .set_bar_format("{desc}{percentage:3.0}%|{animation}| [{elapsed}<{remaining}{postfix}]")
-            .unwrap();
-        progress.set_postfix(format!("patterns=-"));
-        let mut remaining_to_detect = self.nb_detected();
-        let mut it = 0;
-
-        // Which patterns detect a given fault
-        let mut fault_to_patterns = Vec::new();
-        for f in 0..self.nb_faults() {
-            let mut patterns = Vec::new();
-            for p in 0..self.nb_patterns() {
-                if self.pattern_detections[p][f] {
-                    patterns.push(p);
-                }
-                it += 1;
-                if it % 256 == 0 {
-                    progress.update_to(it).unwrap();
-                }
+    /// Remove duplicate patterns, so that N detections are made by distinct patterns
+    fn remove_duplicate_patterns(&mut self) {
+        let mut known = HashSet::new();
+        let mut new_patterns = Vec::new();
+        let mut new_detections = Vec::new();
+        for (p, d) in zip(&self.patterns, &self.pattern_detections) {
+            if known.insert(p) {
+                new_patterns.push(p.clone());
+                new_detections.push(d.clone());
             }
-            fault_to_patterns.push(patterns);
         }
+        self.patterns = new_patterns;
+        self.pattern_detections = new_detections;
+    }
 
-        // Which faults are detected by a given pattern
-        let mut pattern_to_faults = Vec::new();
-        for p in 0..self.nb_patterns() {
-            let mut faults = Vec::new();
-            for f in 0..self.nb_faults() {
-                if self.pattern_detections[p][f] {
-                    faults.push(f);
-                }
-                it += 1;
-                if it % 256 == 0 {
-                    progress.update_to(it).unwrap();
+    /// Simulate all patterns against all detected faults, without fault dropping
+    ///
+    /// Returns the faults detected by each pattern, as bitsets. The faults that are not detected
+    /// yet are detected by no pattern, and are not simulated.
+    fn detections_without_dropping(&mut self) -> Vec<Vec<u64>> {
+        let (faults, indices): (Vec<Fault>, Vec<usize>) = (0..self.nb_faults())
+            .filter(|i| self.detection[*i] > 0)
+            .map(|i| (self.faults[i], i))
+            .unzip();
+        let (packed, masks) = pack_patterns(&self.patterns, self.aig.nb_inputs());
+        let mut ret = Vec::new();
+        for (words, mask) in zip(packed, masks) {
+            let detected = self.detects_faults_multi(&words, &faults);
+            for b in 0..mask.count_ones() {
+                let mut det = vec![0u64; self.nb_faults().div_ceil(64)];
+                for (i, d) in zip(&indices, &detected) {
+                    det[i / 64] |= ((d >> b) & 1) << (i % 64);
                 }
+                ret.push(det);
             }
-            pattern_to_faults.push(faults);
         }
+        ret
+    }
 
-        // How many new faults each pattern detects
-        let mut nb_detected_by_pattern: Vec<_> =
-            pattern_to_faults.iter().map(|v| v.len()).collect();
-        assert_eq!(fault_to_patterns.len(), self.nb_faults());
-        assert_eq!(pattern_to_faults.len(), self.nb_patterns());
-
-        let mut selected_patterns = Vec::new();
-        progress.update_to(it).unwrap();
-        while remaining_to_detect > 0 {
-            // Pick the pattern that detects the most faults
-            let best_pattern = nb_detected_by_pattern
-                .iter()
-                .enumerate()
-                .max_by(|(_, a), (_, b)| a.cmp(b))
-                .map(|(index, _)| index)
-                .unwrap();
-            selected_patterns.push(best_pattern);
-            remaining_to_detect -= nb_detected_by_pattern[best_pattern];
-            progress.set_postfix(format!("patterns={}", selected_patterns.len()));
-            progress
-                .update(nb_detected_by_pattern[best_pattern])
-                .unwrap();
-
-            // Remove the faults detected by the pattern from consideration
-            assert!(nb_detected_by_pattern[best_pattern] > 0);
-            for f in &pattern_to_faults[best_pattern] {
-                for p in &fault_to_patterns[*f] {
-                    nb_detected_by_pattern[*p] -= 1;
-                }
-                // So we don't remove a fault twice
-                fault_to_patterns[*f].clear();
-            }
-            assert_eq!(nb_detected_by_pattern[best_pattern], 0);
+    /// Compress the existing patterns to keep as few as possible.
+    /// This is a minimum set cover problem, solved with the selected compaction algorithm.
+    /// In N-detect mode, each fault keeps up to N detecting patterns.
+    pub fn compress_patterns(&mut self) {
+        if self.n_detect > 1 {
+            self.remove_duplicate_patterns();
         }
-
+        // Pattern-order compaction needs every detection, not only the ones made before dropping
+        if matches!(
+            self.compaction,
+            Compaction::ReverseOrder | Compaction::RandomOrder
+        ) {
+            self.pattern_detections = self.detections_without_dropping();
+        }
+        let selected = match self.compaction {
+            Compaction::Greedy => {
+                greedy_cover(&self.pattern_detections, self.nb_faults(), self.n_detect)
+            }
+            Compaction::ReverseOrder => {
+                let order: Vec<usize> = (0..self.nb_patterns()).rev().collect();
+                ordered_cover(&self.pattern_detections, &order, self.n_detect)
+            }
+            Compaction::RandomOrder => {
+                random_order_cover(&self.pattern_detections, &mut self.rng, self.n_detect)
+            }
+            Compaction::Exact => {
+                let greedy =
+                    greedy_cover(&self.pattern_detections, self.nb_faults(), self.n_detect);
+                exact_cover(
+                    &self.pattern_detections,
+                    self.nb_faults(),
+                    self.n_detect,
+                    greedy,
+                )
+            }
+        };
         let mut new_patterns = Vec::new();
         let mut new_detections = Vec::new();
-        for p in selected_patterns {
+        for p in selected {
             new_patterns.push(self.patterns[p].clone());
             new_detections.push(self.pattern_detections[p].clone());
         }
         self.patterns = new_patterns;
         self.pattern_detections = new_detections;
-        println!();
+    }
+
+    /// Compress the patterns and report the final coverage
+    fn finish(&mut self) {
+        self.check();
+        self.compress_patterns();
+        self.check();
+        println!(
+            "Kept {} patterns, detecting {}/{} faults ({:.2}% coverage)",
+            self.nb_patterns(),
+            self.nb_detected(),
+            self.nb_faults(),
+            100.0 * (self.nb_detected() as f64) / (self.nb_faults() as f64)
+        );
+    }
+
+    /// Extend a deterministic pattern for a fault to detect additional undetected faults
+    ///
+    /// The pattern is relaxed to a test cube, whose unassigned inputs are used by PODEM to
+    /// target the next undetected faults. The remaining inputs keep their original value.
+    fn extend_pattern(&self, fault_index: usize, pattern: Vec<bool>) -> Vec<bool> {
+        if self.two_frame.is_some() || !self.x_inputs.is_empty() {
+            return pattern;
+        }
+        let mut cube = relax_pattern(self.aig, &self.fanouts, self.faults[fault_index], &pattern);
+        let targets = (fault_index + 1..self.nb_faults())
+            .filter(|i| self.detection[*i] == 0 && self.targeted[*i])
+            .take(DYNAMIC_COMPACTION_TARGETS);
+        for i in targets {
+            if cube.iter().all(|b| b.is_some()) {
+                break;
+            }
+            if let Some(c) = podem_extend(
+                self.aig,
+                &self.fanouts,
+                self.faults[i],
+                DYNAMIC_COMPACTION_BACKTRACK_LIMIT,
+                &cube,
+            ) {
+                cube = c;
+            }
+        }
+        zip(cube, pattern).map(|(c, b)| c.unwrap_or(b)).collect()
+    }
+
+    /// Add distinct patterns until the fault is detected N times, or no new pattern is found
+    ///
+    /// The test cube of a detecting pattern is completed with random values, so that the
+    /// additional patterns may sensitize different propagation paths.
+    fn add_n_detect_patterns(&mut self, fault_index: usize) {
+        let Some(base) = (0..self.nb_patterns())
+            .find(|p| {
+                (self.pattern_detections[*p][fault_index / 64] >> (fault_index % 64)) & 1 != 0
+            })
+            .map(|p| self.patterns[p].clone())
+        else {
+            return;
+        };
+        if self.two_frame.is_some() {
+            self.add_random_patterns_from(base, false);
+            return;
+        }
+        let cube = relax_pattern(self.aig, &self.fanouts, self.faults[fault_index], &base);
+        if cube.iter().all(|c| c.is_some()) {
+            // No input can be relaxed, as for faults other than stuck-at: use random variations
+            self.add_random_patterns_from(base, false);
+            return;
+        }
+        let mut attempts = N_DETECT_ATTEMPTS * (self.n_detect - self.detection[fault_index]);
+        while !self.is_complete(fault_index) && attempts > 0 {
+            attempts -= 1;
+            let pattern: Vec<bool> = cube
+                .iter()
+                .map(|c| c.unwrap_or_else(|| self.rng.gen()))
+                .collect();
+            if !self.distinct_patterns.contains(&pattern) {
+                self.add_single_pattern(pattern, false);
+            }
+        }
+    }
+
+    /// Generate a pattern for a fault if it is not detected yet, then additional patterns until it is detected N times
+    ///
+    /// Returns false if no pattern detecting the fault was found.
+    fn target_fault(&mut self, fault_index: usize) -> bool {
+        if self.detection[fault_index] == 0 {
+            let Some(mut pattern) = self.find_pattern_detecting_fault(self.faults[fault_index])
+            else {
+                return false;
+            };
+            if self.dynamic_compaction {
+                pattern = self.extend_pattern(fault_index, pattern);
+            }
+            self.add_random_patterns_from(pattern, false);
+        }
+        if self.detection[fault_index] > 0 && !self.is_complete(fault_index) {
+            self.add_n_detect_patterns(fault_index);
+        }
+        true
     }
 
     pub fn detect_faults(&mut self) {
@@ -335,16 +714,16 @@ impl<'a> TestPatternGenerator<'a> {
         progress
             .set_bar_format("{desc}{percentage:3.0}%|{animation}| [{elapsed}<{remaining}{postfix}]")
             .unwrap();
-        loop {
-            let nb_detected_before = self.nb_detected();
+        // Random patterns are only useful if some faults are not detected by the existing ones
+        let nb_existing = self.nb_patterns();
+        while self.nb_useful_detections() < self.n_detect * self.nb_faults() {
+            let nb_detected_before = self.nb_useful_detections();
             self.add_random_patterns(true);
-            let nb_detected_after = self.nb_detected();
+            let nb_detected_after = self.nb_useful_detections();
             progress.set_postfix(format!("patterns={}, unobservable=-", self.nb_patterns()));
             progress.update_to(self.nb_detected()).unwrap();
-            if nb_detected_after == self.nb_faults() {
-                break;
-            }
-            if ((nb_detected_after - nb_detected_before) as f64) < (0.01 * self.nb_faults() as f64)
+            if ((nb_detected_after - nb_detected_before) as f64)
+                < (0.01 * (self.n_detect * self.nb_faults()) as f64)
             {
                 break;
             }
@@ -352,21 +731,44 @@ impl<'a> TestPatternGenerator<'a> {
         progress
             .write(format!(
                 "Generated {} random patterns, detecting {}/{} faults ({:.2}% coverage)",
-                self.nb_patterns(),
+                self.nb_patterns() - nb_existing,
                 self.nb_detected(),
                 self.nb_faults(),
                 100.0 * (self.nb_detected() as f64) / (self.nb_faults() as f64)
             ))
             .unwrap();
         let mut unobservable = 0;
+        let mut untargeted = 0;
         for i in 0..self.nb_faults() {
-            if self.detection[i] {
+            if self.is_complete(i) {
                 continue;
             }
-            let p = find_pattern_detecting_fault(self.aig, self.faults[i]);
-            if let Some(pattern) = p {
-                self.add_random_patterns_from(pattern, false);
-            } else {
+            if !self.targeted[i] {
+                untargeted += (self.detection[i] == 0) as usize;
+                continue;
+            }
+            if !self.target_fault(i) {
+                unobservable += 1;
+            }
+            progress.set_postfix(format!(
+                "patterns={} unobservable={}",
+                self.nb_patterns(),
+                unobservable
+            ));
+            progress
+                .update_to(self.nb_detected() + unobservable + untargeted)
+                .unwrap();
+        }
+        // Faults dropped by dominance collapsing are detected with the faults they dominate,
+        // unless those are redundant or aborted: the remaining ones are targeted directly
+        for i in 0..self.nb_faults() {
+            if self.targeted[i] || self.is_complete(i) {
+                continue;
+            }
+            if self.detection[i] == 0 {
+                untargeted -= 1;
+            }
+            if !self.target_fault(i) {
                 unobservable += 1;
             }
             progress.set_postfix(format!(
@@ -375,7 +777,7 @@ impl<'a> TestPatternGenerator<'a> {
                 unobservable
             ));
             progress
-                .update_to(self.nb_detected() + unobservable)
+                .update_to(self.nb_detected() + unobservable + untargeted)
                 .unwrap();
         }
         progress
@@ -387,95 +789,511 @@ impl<'a> TestPatternGenerator<'a> {
                 100.0 * (self.nb_detected() as f64) / (self.nb_faults() as f64)
             ))
             .unwrap();
+        progress
+            .write(format!(
+                "PODEM resolved {} faults ({} detected, {} untestable), SAT resolved {} faults ({} detected, {} untestable)",
+                self.podem_resolved.0 + self.podem_resolved.1,
+                self.podem_resolved.0,
+                self.podem_resolved.1,
+                self.sat_resolved.0 + self.sat_resolved.1,
+                self.sat_resolved.0,
+                self.sat_resolved.1,
+            ))
+            .unwrap();
         println!();
     }
 }
 
+/// Options for combinatorial test pattern generation and analysis
+#[derive(Clone, Debug)]
+pub struct AtpgOptions {
+    /// Seed of the random pattern generation
+    pub seed: u64,
+    /// Target every fault, instead of one fault per equivalence class
+    pub with_redundant_faults: bool,
+    /// Pairs of gates with bridging faults between them
+    pub bridges: Vec<(usize, usize)>,
+    /// Cell-internal faults (Lut bit-flips and library defects)
+    pub cell_faults: Vec<Fault>,
+    /// Number of threads used for fault simulation, or 0 for all available cores
+    pub nb_threads: usize,
+    /// Compaction algorithm for the final set of patterns
+    pub compaction: Compaction,
+    /// Extend each deterministic pattern to target additional faults
+    pub dynamic_compaction: bool,
+    /// Number of distinct patterns that should detect each fault
+    pub n_detect: usize,
+    /// Inputs with an unknown value
+    pub x_inputs: Vec<usize>,
+    /// Number of backtracks before PODEM gives up on a fault
+    pub podem_backtrack_limit: usize,
+}
+
+impl Default for AtpgOptions {
+    fn default() -> Self {
+        AtpgOptions {
+            seed: 1,
+            with_redundant_faults: false,
+            bridges: Vec::new(),
+            cell_faults: Vec::new(),
+            nb_threads: 0,
+            compaction: Compaction::default(),
+            dynamic_compaction: false,
+            n_detect: 1,
+            x_inputs: Vec::new(),
+            podem_backtrack_limit: 100,
+        }
+    }
+}
+
 /// Generate combinatorial test patterns
 ///
 /// This will generate random test patterns, then try to exercize the remaining faults
 /// using a SAT solver. The network needs to be combinatorial.
-pub fn generate_comb_test_patterns(
-    aig: &Network,
-    seed: u64,
-    with_redundant_faults: bool,
-) -> Vec<Vec<bool>> {
-    assert!(aig.is_comb());
-    let faults = Fault::all(aig);
-    let unique_faults = Fault::all_unique(aig);
-
-    println!(
-        "Analyzing network with {} inputs, {} outputs, {} gates, {} possible faults, {} unique faults",
-        aig.nb_inputs(),
-        aig.nb_outputs(),
-        aig.nb_nodes(),
-        faults.len(),
-        unique_faults.len(),
-    );
-
-    let mut gen = TestPatternGenerator::from(
-        aig,
-        if with_redundant_faults {
-            faults.clone()
-        } else {
-            unique_faults.clone()
-        },
-        seed,
-    );
+/// Unless redundant faults are requested, the faults are collapsed by equivalence, and the coverage
+/// is reported on this list. The faults that remain after dominance collapsing are targeted first
+/// by deterministic test generation, as their tests also detect the dominating faults. Dominating
+/// faults that remain undetected, for example because the faults they dominate are redundant, are
+/// targeted afterwards.
+/// Bridging faults between the given pairs of gates are targeted in addition to stuck-at faults,
+/// as well as the given cell-internal faults (Lut bit-flips and library defects).
+/// Fault simulation uses the given number of threads, or all available cores for 0.
+/// With dynamic compaction, each deterministic pattern is extended to target additional faults.
+/// In N-detect mode, each fault is targeted until it is detected by N distinct patterns.
+/// Inputs listed as X sources have an unknown value: a fault is only detected if an output
+/// takes known and different values with and without the fault. PODEM and dynamic compaction
+/// only handle known values, so with X sources every fault is targeted with a SAT solver on a
+/// 3-valued encoding of the network, and dynamic compaction is disabled.
+/// PODEM gives up on a fault after the given number of backtracks, and a SAT solver is used instead.
+pub fn generate_comb_test_patterns(aig: &Network, options: &AtpgOptions) -> Vec<Vec<bool>> {
+    let mut gen = comb_test_generator(aig, options);
     gen.detect_faults();
-    gen.check();
-    gen.compress_patterns();
-    gen.check();
-    println!(
-        "Kept {} patterns, detecting {}/{} faults ({:.2}% coverage)",
-        gen.nb_patterns(),
-        gen.nb_detected(),
-        gen.nb_faults(),
-        100.0 * (gen.nb_detected() as f64) / (gen.nb_faults() as f64)
-    );
+    gen.finish();
     gen.patterns
 }
 
-/// Analyze combinatorial test patterns
-///
-/// This will show the coverage obtained by these test patterns. The network needs to be combinatorial.
-pub fn report_comb_test_patterns(
-    aig: &Network,
-    patterns: Vec<Vec<bool>>,
-    with_redundant_faults: bool,
-) {
+/// Setup the generator for combinatorial test patterns, with the options of [`generate_comb_test_patterns`]
+fn comb_test_generator<'a>(aig: &'a Network, options: &AtpgOptions) -> TestPatternGenerator<'a> {
     assert!(aig.is_comb());
+    assert!(options.n_detect >= 1);
+    assert!(options.x_inputs.iter().all(|i| *i < aig.nb_inputs()));
     let faults = Fault::all(aig);
-    let unique_faults = Fault::all_unique(aig);
+    let collapsed_faults = Fault::all_collapsed(aig);
+    let dominance_faults = Fault::all_dominance_collapsed(aig);
+    let bridging_faults = Fault::all_bridging(&options.bridges);
 
     println!(
-        "Analyzing network with {} inputs, {} outputs, {} gates, {} possible faults, {} unique faults",
+        "Analyzing network with {} inputs, {} outputs, {} gates, {} uncollapsed faults, {} collapsed faults, {} after dominance collapsing",
         aig.nb_inputs(),
         aig.nb_outputs(),
         aig.nb_nodes(),
         faults.len(),
-        unique_faults.len(),
+        collapsed_faults.len(),
+        dominance_faults.len(),
     );
+    if !bridging_faults.is_empty() {
+        println!("Adding {} bridging faults", bridging_faults.len());
+    }
+    if !options.cell_faults.is_empty() {
+        println!("Adding {} cell-internal faults", options.cell_faults.len());
+    }
+    if !options.x_inputs.is_empty() {
+        println!("Using {} inputs as X sources", options.x_inputs.len());
+    }
 
-    let mut gen = TestPatternGenerator::from(
-        aig,
-        if with_redundant_faults {
-            faults.clone()
-        } else {
-            unique_faults.clone()
-        },
-        0,
-    );
-    for pattern in tqdm!(patterns.iter()) {
-        // TODO: make it faster by using multi-pattern simulation
-        gen.add_single_pattern(pattern.clone(), false);
+    let mut targets = if options.with_redundant_faults {
+        faults
+    } else {
+        collapsed_faults
+    };
+    let mut targeted: Vec<bool> = targets
+        .iter()
+        .map(|f| options.with_redundant_faults || dominance_faults.binary_search(f).is_ok())
+        .collect();
+    targets.extend(bridging_faults);
+    targets.extend_from_slice(&options.cell_faults);
+    targeted.resize(targets.len(), true);
+    let mut gen = TestPatternGenerator::from(aig, targets, options.seed);
+    gen.targeted = targeted;
+    gen.fault_sim = ParallelFaultSimulator::new(aig, options.nb_threads);
+    gen.compaction = options.compaction;
+    gen.dynamic_compaction = options.dynamic_compaction;
+    gen.n_detect = options.n_detect;
+    gen.x_inputs = options.x_inputs.clone();
+    gen.podem_backtrack_limit = options.podem_backtrack_limit;
+    gen
+}
+
+#[cfg(test)]
+mod tests {
+    use volute::Lut3;
+
+    use super::{
+        expose_dff, expose_dff_unobserved, find_pattern_detecting_fault,
+        generate_comb_test_patterns, AtpgOptions, Compaction, TestPatternGenerator,
+    };
+    use crate::network::NaryType;
+    use crate::sim::{detects_faults, DefectLibrary, Fault};
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_bridging_atpg() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let x = aig.and(i0, i1);
+        let y = aig.xor(i1, i2);
+        let z = aig.and(x, !i2);
+        aig.add_output(y);
+        aig.add_output(z);
+
+        let pairs = Fault::bridging_candidates(&aig, 1, 100);
+        // Gates 0 and 2 are directly connected
+        assert_eq!(pairs, vec![(0, 1), (1, 2)]);
+        assert_eq!(Fault::bridging_candidates(&aig, 1, 1), vec![(0, 1)]);
+        let faults = Fault::all_bridging(&pairs);
+        for f in &faults {
+            // Every bridge is detectable here
+            assert!(find_pattern_detecting_fault(&aig, *f).is_some(), "{}", f);
+        }
+        let patterns = generate_comb_test_patterns(
+            &aig,
+            &AtpgOptions {
+                bridges: pairs,
+                ..Default::default()
+            },
+        );
+        for f in &faults {
+            assert!(patterns
+                .iter()
+                .any(|p| detects_faults(&aig, p, &vec![*f])[0]));
+        }
     }
 
-    println!(
-        "Analyzed {} patterns, detecting {}/{} faults ({:.2}% coverage)",
-        gen.nb_patterns(),
-        gen.nb_detected(),
-        gen.nb_faults(),
-        100.0 * (gen.nb_detected() as f64) / (gen.nb_faults() as f64)
-    );
+    #[test]
+    fn test_bridging_candidates_locality() {
+        // Many independent gates on the same level
+        let mut aig = Network::new();
+        let inputs: Vec<_> = (0..1001).map(|_| aig.add_input()).collect();
+        for i in 0..1000 {
+            let x = aig.and(inputs[i], inputs[i + 1]);
+            aig.add_output(x);
+        }
+        let pairs = Fault::bridging_candidates(&aig, 0, 100000);
+        assert!(pairs.len() <= 4 * 1000);
+        assert!(pairs.iter().all(|(a, b)| b - a <= 4));
+        let pairs = Fault::bridging_candidates(&aig, 0, 500);
+        assert_eq!(pairs.len(), 500);
+        assert!(pairs.iter().all(|(a, b)| b - a == 1));
+    }
+
+    #[test]
+    fn test_transition_fault_single_pattern() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let x = aig.and(i0, i1);
+        aig.add_output(x);
+        // A single timestep never detects a transition fault
+        for value in [false, true] {
+            let f = Fault::TransitionFault { gate: 0, value };
+            assert_eq!(find_pattern_detecting_fault(&aig, f), None);
+            for p in [vec![false, true], vec![true, true]] {
+                assert_eq!(detects_faults(&aig, &p, &vec![f]), vec![false]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cell_fault_atpg() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let l = aig.add(Gate::lut(&[i0, i1, i2], Lut3::majority().into()));
+        let x = aig.xor(l, i2);
+        aig.add_output(x);
+
+        let mut library = DefectLibrary::new();
+        // Xor gate stuck at its first input, and behaving as an And
+        library.add("Xor", 2, 0xa);
+        library.add("Xor", 2, 0x8);
+        let mut faults = Fault::all_lut_bit_flips(&aig);
+        faults.extend(Fault::all_defects(&aig, &library));
+        assert_eq!(faults.len(), 10);
+        for f in &faults {
+            // Every cell-internal fault is detectable here
+            let p = find_pattern_detecting_fault(&aig, *f).unwrap();
+            assert!(detects_faults(&aig, &p, &vec![*f])[0], "{}", f);
+        }
+        let patterns = generate_comb_test_patterns(
+            &aig,
+            &AtpgOptions {
+                cell_faults: faults.clone(),
+                ..Default::default()
+            },
+        );
+        for f in &faults {
+            assert!(patterns
+                .iter()
+                .any(|p| detects_faults(&aig, p, &vec![*f])[0]));
+        }
+    }
+
+    #[test]
+    fn test_compaction_modes() {
+        let mut aig = Network::new();
+        let inputs: Vec<_> = (0..6).map(|_| aig.add_input()).collect();
+        let a = aig.and(inputs[0], inputs[1]);
+        let b = aig.and(inputs[2], !inputs[3]);
+        let c = aig.xor(a, inputs[4]);
+        let d = aig.and(b, c);
+        let e = aig.and(!a, inputs[5]);
+        aig.add_output(d);
+        aig.add_output(e);
+        aig.add_output(c);
+
+        let testable: Vec<Fault> = Fault::all(&aig)
+            .into_iter()
+            .filter(|f| find_pattern_detecting_fault(&aig, *f).is_some())
+            .collect();
+        let mut sizes = Vec::new();
+        for compaction in [
+            Compaction::Greedy,
+            Compaction::ReverseOrder,
+            Compaction::RandomOrder,
+            Compaction::Exact,
+        ] {
+            for dynamic_compaction in [false, true] {
+                let patterns = generate_comb_test_patterns(
+                    &aig,
+                    &AtpgOptions {
+                        compaction,
+                        dynamic_compaction,
+                        ..Default::default()
+                    },
+                );
+                for f in &testable {
+                    assert!(patterns
+                        .iter()
+                        .any(|p| detects_faults(&aig, p, &vec![*f])[0]));
+                }
+                sizes.push(patterns.len());
+            }
+        }
+        // The exact cover is no larger than the greedy one
+        assert!(sizes[6] <= sizes[0]);
+        assert!(sizes[7] <= sizes[1]);
+    }
+
+    #[test]
+    fn test_reverse_order_compaction() {
+        // Random network, where the random patterns are made redundant by the deterministic ones
+        let mut aig = Network::new();
+        let mut signals: Vec<_> = (0..32).map(|_| aig.add_input()).collect();
+        let mut state = 0x9e3779b97f4a7c15u64;
+        let mut next = |n: usize| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as usize % n
+        };
+        for i in 0..400 {
+            let a = signals[next(signals.len())];
+            let b = signals[next(signals.len())];
+            let x = if i % 4 == 0 {
+                aig.xor(a, b)
+            } else {
+                aig.and(a ^ (next(2) == 0), b ^ (next(2) == 0))
+            };
+            signals.push(x);
+        }
+        // Gates without fanout are outputs
+        let mut used = vec![false; aig.nb_nodes()];
+        for i in 0..aig.nb_nodes() {
+            for v in aig.gate(i).vars() {
+                used[v as usize] = true;
+            }
+        }
+        for i in 0..aig.nb_nodes() {
+            if !used[i] {
+                aig.add_output(Signal::from_var(i as u32));
+            }
+        }
+        let nb_patterns = |compaction| {
+            generate_comb_test_patterns(
+                &aig,
+                &AtpgOptions {
+                    compaction,
+                    ..Default::default()
+                },
+            )
+            .len()
+        };
+        let greedy = nb_patterns(Compaction::Greedy);
+        let reverse = nb_patterns(Compaction::ReverseOrder);
+        assert!(reverse <= greedy);
+    }
+
+    #[test]
+    fn test_distinct_detections() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let x = aig.and(i0, i1);
+        aig.add_output(x);
+        let faults = Fault::all(&aig);
+        let mut gen = TestPatternGenerator::from(&aig, faults, 1);
+        gen.n_detect = 4;
+
+        // The 64 patterns of the batch are identical, and count as a single detection
+        gen.add_patterns(vec![!0, !0], true);
+        assert_eq!(gen.nb_patterns(), 64);
+        assert!(gen.detection.iter().all(|d| *d <= 1));
+        assert!(gen.detection.iter().any(|d| *d == 1));
+
+        // Existing patterns are only counted if they are new
+        let before = gen.detection.clone();
+        gen.add_existing_patterns(&[vec![true, true], vec![false, true], vec![false, true]]);
+        for (i, f) in gen.faults.clone().iter().enumerate() {
+            let new = detects_faults(&aig, &vec![false, true], &vec![*f])[0];
+            assert_eq!(gen.detection[i], before[i] + new as usize);
+        }
+        gen.check();
+    }
+
+    #[test]
+    fn test_n_detect() {
+        let mut aig = Network::new();
+        let inputs: Vec<_> = (0..6).map(|_| aig.add_input()).collect();
+        let a = aig.and(inputs[0], inputs[1]);
+        let b = aig.xor(inputs[2], inputs[3]);
+        let c = aig.and(a, b);
+        let d = aig.and(!c, inputs[4]);
+        let e = aig.xor(d, inputs[5]);
+        aig.add_output(c);
+        aig.add_output(e);
+
+        // Number of patterns detecting each fault, among all possible patterns
+        let all_patterns: Vec<Vec<bool>> = (0..64)
+            .map(|v: u32| (0..6).map(|i| (v >> i) & 1 != 0).collect())
+            .collect();
+        let faults = Fault::all_dominance_collapsed(&aig);
+        let nb_detecting = |patterns: &[Vec<bool>], f: Fault| {
+            patterns
+                .iter()
+                .filter(|p| detects_faults(&aig, p, &vec![f])[0])
+                .count()
+        };
+        let single = generate_comb_test_patterns(&aig, &AtpgOptions::default());
+        for compaction in [
+            Compaction::Greedy,
+            Compaction::ReverseOrder,
+            Compaction::RandomOrder,
+            Compaction::Exact,
+        ] {
+            let patterns = generate_comb_test_patterns(
+                &aig,
+                &AtpgOptions {
+                    compaction,
+                    n_detect: 3,
+                    ..Default::default()
+                },
+            );
+            let mut sorted = patterns.clone();
+            sorted.sort();
+            sorted.dedup();
+            assert_eq!(sorted.len(), patterns.len());
+            assert!(patterns.len() > single.len());
+            for f in &faults {
+                let possible = nb_detecting(&all_patterns, *f);
+                assert!(nb_detecting(&patterns, *f) >= possible.min(3), "{}", f);
+            }
+        }
+    }
+
+    #[test]
+    fn test_atpg_x_flops() {
+        // The only output is a flip-flop with an unknown value, whose data input is not observed
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let x = aig.and(i0, i1);
+        let d = aig.dff(x, Signal::one(), Signal::zero());
+        aig.add_output(d);
+        let generate = |comb: &Network, x_inputs: &[usize]| {
+            generate_comb_test_patterns(
+                comb,
+                &AtpgOptions {
+                    x_inputs: x_inputs.to_vec(),
+                    ..Default::default()
+                },
+            )
+        };
+        // With a scanned flip-flop, the And gate is tested through its data input
+        assert!(!generate(&expose_dff(&aig), &[]).is_empty());
+        // With an unknown flip-flop, exposed as the input after the primary inputs, no fault can be detected
+        let comb = expose_dff_unobserved(&aig, &[d.var() as usize]);
+        assert!(generate(&comb, &[2]).is_empty());
+    }
+
+    #[test]
+    fn test_n_detect_bridging() {
+        // Bridge between two wide And gates, only detected when all inputs of the first one are set;
+        // the free inputs can still change between detecting patterns
+        let mut aig = Network::new();
+        let a: Vec<_> = (0..20).map(|_| aig.add_input()).collect();
+        let b = aig.add_input();
+        let free: Vec<_> = (0..8).map(|_| aig.add_input()).collect();
+        let g = aig.add(Gate::Nary(a.clone().into(), NaryType::And));
+        let mut h_inputs = a.clone();
+        h_inputs.push(b);
+        let h = aig.add(Gate::Nary(h_inputs.into(), NaryType::And));
+        let x = aig.add(Gate::Nary(free.into(), NaryType::Xor));
+        aig.add_output(g);
+        aig.add_output(h);
+        aig.add_output(x);
+
+        let pairs = vec![(g.var() as usize, h.var() as usize)];
+        let mut gen = TestPatternGenerator::from(&aig, Fault::all_bridging(&pairs), 1);
+        gen.n_detect = 3;
+        let mut pattern = vec![true; 20];
+        pattern.extend([false; 9]);
+        gen.add_existing_patterns(&[pattern]);
+        assert!(gen.detection.iter().all(|d| *d == 1));
+        for i in 0..gen.nb_faults() {
+            gen.add_n_detect_patterns(i);
+            assert!(gen.is_complete(i), "{}", gen.faults[i]);
+        }
+        gen.check();
+    }
+
+    #[test]
+    fn test_dominating_fault_retargeted() {
+        // The output stuck-at-1 of g dominates its redundant input stuck-at-1 faults. It is only
+        // detected when all 40 inputs are set, far from the tests of the other faults
+        let mut aig = Network::new();
+        let x: Vec<_> = (0..20).map(|_| aig.add_input()).collect();
+        let mut k: Vec<_> = (0..20).map(|_| aig.add_input()).collect();
+        let a = aig.add(Gate::Nary(x.into(), NaryType::And));
+        let buf = aig.add(Gate::Buf(!a));
+        let g = aig.add(Gate::and(!a, buf));
+        k.push(a);
+        let k = aig.add(Gate::Nary(k.into(), NaryType::And));
+        let o = aig.add(Gate::and(g, k));
+        aig.add_output(o);
+
+        let fault = Fault::OutputStuckAtFault {
+            gate: g.var() as usize,
+            value: true,
+        };
+        assert!(Fault::all_dominance_collapsed(&aig)
+            .binary_search(&fault)
+            .is_err());
+        let patterns = generate_comb_test_patterns(&aig, &AtpgOptions::default());
+        assert!(patterns
+            .iter()
+            .any(|p| detects_faults(&aig, p, &vec![fault])[0]));
+    }
 }
@@ -0,0 +1,418 @@
+use std::fmt;
+use std::ops::BitXor;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::sim::{detects_faults_multi_parallel, pack_patterns, Fault};
+use crate::{Gate, Network, Signal};
+
+use super::compactor::primitive_taps;
+use super::find_pattern_detecting_fault;
+
+/// Source of pseudo-random patterns for logic built-in self-test
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BistSource {
+    /// Fibonacci linear feedback shift register, given by the taps of its feedback polynomial
+    ///
+    /// The largest tap is the width of the register. Its output is shifted serially into the
+    /// inputs, as for a single scan chain: each pattern takes one shift cycle per input.
+    Lfsr(Vec<usize>),
+    /// Hybrid cellular automaton with one cell per input, and null boundaries
+    ///
+    /// Each cell uses rule 150 if its flag is set, and rule 90 otherwise. The state of the cells
+    /// is applied to the inputs at each cycle. Flags are repeated if there are more inputs.
+    CellularAutomaton(Vec<bool>),
+}
+
+impl BistSource {
+    /// LFSR of the given width, with a primitive feedback polynomial
+    pub fn lfsr(width: usize) -> BistSource {
+        BistSource::Lfsr(primitive_taps(width).to_vec())
+    }
+
+    /// Cellular automaton alternating rules 150 and 90
+    pub fn cellular_automaton() -> BistSource {
+        BistSource::CellularAutomaton(vec![true, false])
+    }
+
+    /// Number of bits of the seed, for a network with the given number of inputs
+    pub fn seed_width(&self, nb_inputs: usize) -> usize {
+        match self {
+            BistSource::Lfsr(taps) => *taps.iter().max().unwrap(),
+            BistSource::CellularAutomaton(_) => nb_inputs,
+        }
+    }
+
+    /// Seed of the generator from an integer, avoiding the all-zero state
+    pub fn seed_from_u64(&self, nb_inputs: usize, seed: u64) -> Vec<bool> {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        loop {
+            let ret: Vec<bool> = (0..self.seed_width(nb_inputs)).map(|_| rng.gen()).collect();
+            if ret.contains(&true) || ret.is_empty() {
+                return ret;
+            }
+        }
+    }
+}
+
+/// One shift of a Fibonacci LFSR, returning the output bit
+///
+/// This works both on concrete values and on linear combinations of the seed bits.
+fn lfsr_shift<T: Copy + BitXor<Output = T>>(state: &mut [T], taps: &[usize]) -> T {
+    let width = state.len();
+    let out = state[width - 1];
+    let feedback = taps[1..].iter().fold(out, |acc, t| acc ^ state[*t - 1]);
+    state.copy_within(0..width - 1, 1);
+    state[0] = feedback;
+    out
+}
+
+/// Pattern generator for logic built-in self-test
+#[derive(Clone, Debug)]
+pub struct BistGenerator {
+    source: BistSource,
+    nb_inputs: usize,
+    state: Vec<bool>,
+}
+
+impl BistGenerator {
+    /// Create a generator for a network with the given number of inputs
+    pub fn new(source: &BistSource, nb_inputs: usize, seed: &[bool]) -> BistGenerator {
+        if let BistSource::Lfsr(taps) = source {
+            assert!(!taps.is_empty() && !taps.contains(&0) && taps.iter().max() == taps.first());
+            assert!(taps[0] <= 64, "LFSRs are supported up to 64 bits");
+        }
+        if let BistSource::CellularAutomaton(rules) = source {
+            assert!(!rules.is_empty());
+        }
+        assert_eq!(seed.len(), source.seed_width(nb_inputs));
+        BistGenerator {
+            source: source.clone(),
+            nb_inputs,
+            state: seed.to_vec(),
+        }
+    }
+
+    /// Generate the next pattern
+    pub fn next_pattern(&mut self) -> Vec<bool> {
+        match &self.source {
+            BistSource::Lfsr(taps) => (0..self.nb_inputs)
+                .map(|_| lfsr_shift(&mut self.state, taps))
+                .collect(),
+            BistSource::CellularAutomaton(rules) => {
+                let ret = self.state.clone();
+                let n = self.state.len();
+                for i in 0..n {
+                    let left = i > 0 && ret[i - 1];
+                    let right = i + 1 < n && ret[i + 1];
+                    self.state[i] = left ^ right ^ (rules[i % rules.len()] && ret[i]);
+                }
+                ret
+            }
+        }
+    }
+
+    /// Generate the given number of patterns
+    pub fn patterns(&mut self, nb_patterns: usize) -> Vec<Vec<bool>> {
+        (0..nb_patterns).map(|_| self.next_pattern()).collect()
+    }
+}
+
+/// Generate BIST patterns from successive seeds, each used for a number of patterns
+pub fn bist_patterns(
+    source: &BistSource,
+    nb_inputs: usize,
+    seeds: &[(Vec<bool>, usize)],
+) -> Vec<Vec<bool>> {
+    let mut ret = Vec::new();
+    for (seed, nb_patterns) in seeds {
+        ret.extend(BistGenerator::new(source, nb_inputs, seed).patterns(*nb_patterns));
+    }
+    ret
+}
+
+/// Fault coverage of BIST patterns, as a function of the number of patterns applied
+#[derive(Clone, Debug)]
+pub struct BistCoverage {
+    /// Number of patterns applied
+    pub nb_patterns: usize,
+    /// Index of the first pattern detecting each fault
+    pub first_detection: Vec<Option<usize>>,
+}
+
+impl BistCoverage {
+    /// Number of faults analyzed
+    pub fn nb_faults(&self) -> usize {
+        self.first_detection.len()
+    }
+
+    /// Number of faults detected by the given number of first patterns
+    pub fn nb_detected(&self, nb_patterns: usize) -> usize {
+        self.first_detection
+            .iter()
+            .filter(|d| d.is_some_and(|p| p < nb_patterns))
+            .count()
+    }
+
+    /// Coverage curve, with the number of faults detected after each power of two and after the last pattern
+    pub fn curve(&self) -> Vec<(usize, usize)> {
+        let mut ret = Vec::new();
+        let mut n = 1;
+        while n < self.nb_patterns {
+            ret.push((n, self.nb_detected(n)));
+            n *= 2;
+        }
+        ret.push((self.nb_patterns, self.nb_detected(self.nb_patterns)));
+        ret
+    }
+}
+
+impl fmt::Display for BistCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Patterns\tDetected\tCoverage")?;
+        for (n, d) in self.curve() {
+            writeln!(
+                f,
+                "{}\t{}/{}\t{:.2}%",
+                n,
+                d,
+                self.nb_faults(),
+                100.0 * d as f64 / self.nb_faults().max(1) as f64
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// First pattern detecting each fault, by fault simulation
+fn first_detections(
+    aig: &Network,
+    patterns: &[Vec<bool>],
+    faults: &[Fault],
+    nb_threads: usize,
+) -> Vec<Option<usize>> {
+    let (packed, masks) = pack_patterns(patterns, aig.nb_inputs());
+    let detections = detects_faults_multi_parallel(aig, &packed, faults, nb_threads);
+    let mut ret = vec![None; faults.len()];
+    for (w, (d, mask)) in detections.iter().zip(masks).enumerate() {
+        for (f, v) in d.iter().enumerate() {
+            if ret[f].is_none() && v & mask != 0 {
+                ret[f] = Some(64 * w + (v & mask).trailing_zeros() as usize);
+            }
+        }
+    }
+    ret
+}
+
+/// Fault coverage of the patterns, for the collapsed stuck-at faults of a combinational network
+pub fn bist_coverage(aig: &Network, patterns: &[Vec<bool>], nb_threads: usize) -> BistCoverage {
+    assert!(aig.is_comb());
+    let faults = Fault::all_collapsed(aig);
+    BistCoverage {
+        nb_patterns: patterns.len(),
+        first_detection: first_detections(aig, patterns, &faults, nb_threads),
+    }
+}
+
+/// Select the seed that detects the most faults with the given number of patterns, among random candidates
+pub fn best_bist_seed(
+    aig: &Network,
+    source: &BistSource,
+    nb_patterns: usize,
+    nb_candidates: usize,
+    seed: u64,
+    nb_threads: usize,
+) -> Vec<bool> {
+    assert!(aig.is_comb() && nb_candidates >= 1);
+    let faults = Fault::all_collapsed(aig);
+    let mut best: Option<(Vec<bool>, usize)> = None;
+    for c in 0..nb_candidates {
+        let s = source.seed_from_u64(aig.nb_inputs(), seed.wrapping_add(c as u64));
+        let patterns = BistGenerator::new(source, aig.nb_inputs(), &s).patterns(nb_patterns);
+        let detected = first_detections(aig, &patterns, &faults, nb_threads)
+            .iter()
+            .filter(|d| d.is_some())
+            .count();
+        if best.as_ref().is_none_or(|(_, b)| detected > *b) {
+            best = Some((s, detected));
+        }
+    }
+    best.unwrap().0
+}
+
+/// Network whose inputs are the seed of an LFSR, and whose gates compute the first pattern
+///
+/// The original gates come after the expansion gates, whose number is returned.
+fn lfsr_expansion(aig: &Network, taps: &[usize]) -> (Network, usize) {
+    let width = taps[0];
+    let mut state: Vec<u64> = (0..width).map(|i| 1 << i).collect();
+    let masks: Vec<u64> = (0..aig.nb_inputs())
+        .map(|_| lfsr_shift(&mut state, taps))
+        .collect();
+
+    let mut ret = Network::new();
+    ret.add_inputs(width);
+    let bits: Vec<Signal> = masks
+        .iter()
+        .map(|m| {
+            let v: Vec<Signal> = (0..width)
+                .filter(|i| (m >> i) & 1 != 0)
+                .map(|i| Signal::from_input(i as u32))
+                .collect();
+            if v.len() == 1 {
+                v[0]
+            } else {
+                ret.add(Gate::xorn(&v))
+            }
+        })
+        .collect();
+    let offset = ret.nb_nodes();
+    let t = |s: &Signal| -> Signal {
+        if s.is_input() {
+            bits[s.input() as usize] ^ s.is_inverted()
+        } else if s.is_var() {
+            Signal::from_var(s.var() + offset as u32) ^ s.is_inverted()
+        } else {
+            *s
+        }
+    };
+    for i in 0..aig.nb_nodes() {
+        ret.add(aig.gate(i).remap(t));
+    }
+    for o in 0..aig.nb_outputs() {
+        ret.add_output(t(&aig.output(o)));
+    }
+    (ret, offset)
+}
+
+/// Find a seed whose first pattern detects the fault, using a SAT solver
+fn seed_detecting_fault(aig: &Network, source: &BistSource, fault: Fault) -> Option<Vec<bool>> {
+    match source {
+        BistSource::Lfsr(taps) => {
+            let (expanded, offset) = lfsr_expansion(aig, taps);
+            let shifted = match fault {
+                Fault::OutputStuckAtFault { gate, value } => Fault::OutputStuckAtFault {
+                    gate: gate + offset,
+                    value,
+                },
+                Fault::InputStuckAtFault { gate, input, value } => Fault::InputStuckAtFault {
+                    gate: gate + offset,
+                    input,
+                    value,
+                },
+                _ => return None,
+            };
+            find_pattern_detecting_fault(&expanded, shifted)
+        }
+        // The first pattern of a cellular automaton is its seed
+        BistSource::CellularAutomaton(_) => find_pattern_detecting_fault(aig, fault),
+    }
+}
+
+/// Select reseeding points to detect the faults that random BIST patterns miss
+///
+/// The initial seed is used for the given number of patterns. Then, for each undetected fault,
+/// a SAT solver finds a seed whose first pattern detects it, and the seed is used for
+/// `patterns_per_seed` patterns. Returns each seed with its number of patterns, initial seed
+/// included, for use with [`bist_patterns`].
+pub fn bist_reseeding(
+    aig: &Network,
+    source: &BistSource,
+    seed: &[bool],
+    nb_patterns: usize,
+    patterns_per_seed: usize,
+    max_seeds: usize,
+    nb_threads: usize,
+) -> Vec<(Vec<bool>, usize)> {
+    assert!(aig.is_comb() && patterns_per_seed >= 1);
+    let faults = Fault::all_collapsed(aig);
+    let patterns = BistGenerator::new(source, aig.nb_inputs(), seed).patterns(nb_patterns);
+    let mut detected: Vec<bool> = first_detections(aig, &patterns, &faults, nb_threads)
+        .iter()
+        .map(|d| d.is_some())
+        .collect();
+    let mut ret = vec![(seed.to_vec(), nb_patterns)];
+    for i in 0..faults.len() {
+        if ret.len() > max_seeds {
+            break;
+        }
+        if detected[i] {
+            continue;
+        }
+        let Some(s) = seed_detecting_fault(aig, source, faults[i]) else {
+            continue;
+        };
+        let patterns = BistGenerator::new(source, aig.nb_inputs(), &s).patterns(patterns_per_seed);
+        let remaining: Vec<usize> = (i..faults.len()).filter(|f| !detected[*f]).collect();
+        let remaining_faults: Vec<Fault> = remaining.iter().map(|f| faults[*f]).collect();
+        let d = first_detections(aig, &patterns, &remaining_faults, nb_threads);
+        for (f, v) in remaining.iter().zip(d) {
+            detected[*f] |= v.is_some();
+        }
+        assert!(detected[i]);
+        ret.push((s, patterns_per_seed));
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bist_coverage, bist_patterns, bist_reseeding, BistGenerator, BistSource};
+    use crate::{Gate, Network};
+
+    #[test]
+    fn test_lfsr_period() {
+        let source = BistSource::lfsr(5);
+        let seed = vec![true, false, false, false, false];
+        // Single-input patterns are the output stream of the LFSR
+        let stream: Vec<bool> = BistGenerator::new(&source, 1, &seed)
+            .patterns(62)
+            .into_iter()
+            .map(|p| p[0])
+            .collect();
+        assert_eq!(stream[..31], stream[31..]);
+        assert_eq!(stream.iter().filter(|b| **b).count(), 32);
+        // Each window of 5 bits is seen exactly once, except all zeros
+        let mut windows: Vec<&[bool]> = stream[..35].windows(5).take(31).collect();
+        windows.sort();
+        windows.dedup();
+        assert_eq!(windows.len(), 31);
+    }
+
+    #[test]
+    fn test_cellular_automaton() {
+        let source = BistSource::cellular_automaton();
+        let mut gen = BistGenerator::new(&source, 4, &[true, false, false, false]);
+        assert_eq!(gen.next_pattern(), vec![true, false, false, false]);
+        // Rule 150 on the first cell, rule 90 on the second
+        assert_eq!(gen.next_pattern(), vec![true, true, false, false]);
+        assert_eq!(gen.next_pattern(), vec![false, true, true, false]);
+    }
+
+    #[test]
+    fn test_bist_reseeding() {
+        // Wide And gate, whose output stuck-at-0 fault is hard to detect with random patterns
+        let mut aig = Network::new();
+        let inputs: Vec<_> = (0..20).map(|_| aig.add_input()).collect();
+        let a = aig.add(Gate::andn(&inputs));
+        aig.add_output(a);
+        for source in [BistSource::lfsr(24), BistSource::cellular_automaton()] {
+            let seed = source.seed_from_u64(20, 1);
+            let patterns = BistGenerator::new(&source, 20, &seed).patterns(256);
+            let coverage = bist_coverage(&aig, &patterns, 1);
+            assert!(coverage.nb_detected(256) < coverage.nb_faults());
+            assert_eq!(
+                coverage.curve().last(),
+                Some(&(256, coverage.nb_detected(256)))
+            );
+
+            let seeds = bist_reseeding(&aig, &source, &seed, 256, 4, 32, 1);
+            assert!(seeds.len() > 1);
+            let patterns = bist_patterns(&source, 20, &seeds);
+            assert_eq!(patterns.len(), 256 + 4 * (seeds.len() - 1));
+            let coverage = bist_coverage(&aig, &patterns, 1);
+            assert_eq!(coverage.nb_detected(patterns.len()), coverage.nb_faults());
+        }
+    }
+}
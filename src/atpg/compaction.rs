@@ -0,0 +1,348 @@
+use std::iter::zip;
+
+use kdam::{tqdm, BarExt};
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rustsat::encodings::card::{BoundLower, BoundUpper, Totalizer};
+use rustsat::instances::{BasicVarManager, Cnf};
+use rustsat::solvers::{Solve, SolverResult};
+use rustsat::types::{Clause, Lit, TernaryVal, Var};
+use rustsat_kissat::{Kissat, Limit};
+
+/// Algorithm used to compact the final set of test patterns
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compaction {
+    /// Greedy set cover, taking the pattern that detects the most new faults each time
+    #[default]
+    Greedy,
+    /// Fault simulation in reverse order, dropping the patterns that detect no new fault, then the
+    /// patterns made redundant by the ones selected after them
+    ReverseOrder,
+    /// Reverse-order fault simulation, followed by fault simulation in random orders
+    RandomOrder,
+    /// Minimum set cover, solved exactly with a SAT solver and cardinality constraints
+    Exact,
+}
+
+/// Number of random orders tried for random-order compaction
+const RANDOM_ORDER_PASSES: usize = 8;
+
+/// Number of conflicts allowed to the SAT solver for each step of exact compaction
+const EXACT_COVER_CONFLICT_LIMIT: u32 = 100_000;
+
+/// Indices of the bits set in a bitset
+pub(crate) fn set_bits(v: &[u64]) -> impl Iterator<Item = usize> + '_ {
+    v.iter().enumerate().flat_map(|(w, b)| {
+        (0..64)
+            .filter(move |i| (b >> i) & 1 != 0)
+            .map(move |i| 64 * w + i)
+    })
+}
+
+/// Greedy set cover of the detected faults
+///
+/// Each pattern is given as the bitset of the faults it detects. Each fault is covered by up to
+/// n patterns, or by all the patterns detecting it if there are fewer.
+/// Returns the indices of the selected patterns.
+pub(crate) fn greedy_cover(detections: &[Vec<u64>], nb_faults: usize, n: usize) -> Vec<usize> {
+    // Which patterns detect a given fault
+    let mut fault_to_patterns = vec![Vec::new(); nb_faults];
+    // Which faults are detected by a given pattern
+    let mut pattern_to_faults = Vec::new();
+    for (p, d) in detections.iter().enumerate() {
+        let faults: Vec<usize> = set_bits(d).collect();
+        for f in &faults {
+            fault_to_patterns[*f].push(p);
+        }
+        pattern_to_faults.push(faults);
+    }
+    // How many more times each fault must be covered
+    let mut remaining: Vec<usize> = fault_to_patterns.iter().map(|v| v.len().min(n)).collect();
+    let mut remaining_to_detect: usize = remaining.iter().sum();
+
+    let mut progress = tqdm!(total = remaining_to_detect);
+    progress.set_description("Compression progress");
+    progress
+        .set_bar_format("{desc}{percentage:3.0}%|{animation}| [{elapsed}<{remaining}{postfix}]")
+        .unwrap();
+
+    // How many faults still to cover each pattern detects
+    let mut nb_detected_by_pattern: Vec<_> = pattern_to_faults.iter().map(|v| v.len()).collect();
+
+    let mut selected_patterns = Vec::new();
+    while remaining_to_detect > 0 {
+        // Pick the pattern that detects the most faults
+        let best_pattern = nb_detected_by_pattern
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.cmp(b))
+            .map(|(index, _)| index)
+            .unwrap();
+        selected_patterns.push(best_pattern);
+        remaining_to_detect -= nb_detected_by_pattern[best_pattern];
+        progress.set_postfix(format!("patterns={}", selected_patterns.len()));
+        progress
+            .update(nb_detected_by_pattern[best_pattern])
+            .unwrap();
+
+        // Remove the faults covered enough times from consideration
+        assert!(nb_detected_by_pattern[best_pattern] > 0);
+        for f in &pattern_to_faults[best_pattern] {
+            if remaining[*f] == 0 {
+                continue;
+            }
+            remaining[*f] -= 1;
+            if remaining[*f] == 0 {
+                for p in &fault_to_patterns[*f] {
+                    nb_detected_by_pattern[*p] -= 1;
+                }
+                // So we don't remove a fault twice
+                fault_to_patterns[*f].clear();
+            } else {
+                nb_detected_by_pattern[best_pattern] -= 1;
+                fault_to_patterns[*f].retain(|p| *p != best_pattern);
+            }
+        }
+        assert_eq!(nb_detected_by_pattern[best_pattern], 0);
+    }
+    println!();
+    selected_patterns
+}
+
+/// Fault simulation with fault dropping, keeping the patterns that detect a new fault
+///
+/// Faults are dropped after n detections. Patterns are visited in the given order, then the
+/// selected patterns whose faults are all detected n times by the others are removed.
+/// The detections must be complete, and not limited to the faults not dropped when the patterns
+/// were generated. Returns the indices of the selected patterns, sorted.
+pub(crate) fn ordered_cover(detections: &[Vec<u64>], order: &[usize], n: usize) -> Vec<usize> {
+    let Some(first) = detections.first() else {
+        return Vec::new();
+    };
+    let mut nb_covered = vec![0; 64 * first.len()];
+    let mut selected = Vec::new();
+    for p in order {
+        let d = &detections[*p];
+        if set_bits(d).any(|f| nb_covered[f] < n) {
+            for f in set_bits(d) {
+                nb_covered[f] += 1;
+            }
+            selected.push(*p);
+        }
+    }
+    // Drop the patterns made redundant by the ones selected after them
+    let mut kept = Vec::new();
+    for p in selected {
+        let d = &detections[p];
+        if set_bits(d).all(|f| nb_covered[f] > n) {
+            for f in set_bits(d) {
+                nb_covered[f] -= 1;
+            }
+        } else {
+            kept.push(p);
+        }
+    }
+    let mut selected = kept;
+    selected.sort();
+    selected
+}
+
+/// Fault simulation in reverse order, then in random orders on the remaining patterns
+pub(crate) fn random_order_cover(
+    detections: &[Vec<u64>],
+    rng: &mut SmallRng,
+    n: usize,
+) -> Vec<usize> {
+    let order: Vec<usize> = (0..detections.len()).rev().collect();
+    let mut best = ordered_cover(detections, &order, n);
+    for _ in 0..RANDOM_ORDER_PASSES {
+        let mut order = best.clone();
+        order.shuffle(rng);
+        let selected = ordered_cover(detections, &order, n);
+        if selected.len() < best.len() {
+            best = selected;
+        }
+    }
+    best
+}
+
+/// Find a cover with at most the given number of patterns with a SAT solver
+///
+/// Each constraint requires a number of its patterns to be selected.
+/// Returns the result of the solver, and the cover if one was found.
+fn cover_with_at_most(
+    constraints: &[(Vec<usize>, usize)],
+    nb_patterns: usize,
+    k: usize,
+) -> (SolverResult, Vec<usize>) {
+    let lit = |p: usize| Lit::new(p as u32, false);
+    let mut cnf = Cnf::new();
+    let mut var_manager = BasicVarManager::from_next_free(Var::new(nb_patterns as u32));
+    for (c, m) in constraints {
+        if *m == 1 {
+            cnf.add_clause(Clause::from_iter(c.iter().map(|p| lit(*p))));
+        } else if *m == c.len() {
+            for p in c {
+                cnf.add_unit(lit(*p));
+            }
+        } else {
+            let mut at_least = Totalizer::from_iter(c.iter().map(|p| lit(*p)));
+            at_least.encode_lb(*m..=*m, &mut cnf, &mut var_manager);
+            for l in at_least.enforce_lb(*m).unwrap() {
+                cnf.add_unit(l);
+            }
+        }
+    }
+    let mut totalizer = Totalizer::from_iter((0..nb_patterns).map(lit));
+    totalizer.encode_ub(k..=k, &mut cnf, &mut var_manager);
+    for l in totalizer.enforce_ub(k).unwrap() {
+        cnf.add_unit(l);
+    }
+
+    let mut solver = Kissat::default();
+    solver.set_limit(Limit::Conflicts(EXACT_COVER_CONFLICT_LIMIT));
+    solver.add_cnf(cnf).unwrap();
+    let res = solver.solve().unwrap();
+    if res != SolverResult::Sat {
+        return (res, Vec::new());
+    }
+    let sol = solver.full_solution().unwrap();
+    let selected = (0..nb_patterns)
+        .filter(|p| sol.lit_value(lit(*p)) == TernaryVal::True)
+        .collect();
+    (res, selected)
+}
+
+/// Lower bound on the size of a cover, as a number of faults that no pattern detects together
+///
+/// Each fault requires a number of its patterns, and this bound is the sum of the requirements.
+fn cover_lower_bound(constraints: &[(Vec<usize>, usize)], nb_patterns: usize) -> usize {
+    let mut used = vec![false; nb_patterns];
+    let mut by_size: Vec<&(Vec<usize>, usize)> = constraints.iter().collect();
+    by_size.sort_by_key(|(c, _)| c.len());
+    let mut ret = 0;
+    for (c, m) in by_size {
+        if c.iter().all(|p| !used[*p]) {
+            for p in c {
+                used[*p] = true;
+            }
+            ret += m;
+        }
+    }
+    ret
+}
+
+/// Minimum set cover of the detected faults, solved exactly
+///
+/// Each fault is covered by up to n patterns, as for [`greedy_cover`]. Starting from a known
+/// cover, the SAT solver is asked for a cover with one pattern less until it proves that none
+/// exists, or reaches its conflict limit.
+/// Returns the indices of the selected patterns, sorted.
+pub(crate) fn exact_cover(
+    detections: &[Vec<u64>],
+    nb_faults: usize,
+    n: usize,
+    initial: Vec<usize>,
+) -> Vec<usize> {
+    // Only keep candidate patterns that are not dominated by n other ones: they can always be
+    // replaced by a dominating pattern that is not selected yet
+    let dominates = |a: &[u64], b: &[u64]| zip(a, b).all(|(x, y)| y & !x == 0);
+    let mut candidates: Vec<usize> = Vec::new();
+    for (p, d) in detections.iter().enumerate() {
+        if d.iter().all(|w| *w == 0) {
+            continue;
+        }
+        let nb_dominating = detections
+            .iter()
+            .enumerate()
+            .filter(|(q, e)| *q != p && dominates(e, d) && (*q < p || !dominates(d, e)))
+            .take(n)
+            .count();
+        if nb_dominating < n {
+            candidates.push(p);
+        }
+    }
+
+    // One constraint per detected fault: up to n candidates detecting it are selected
+    let mut clauses = vec![Vec::new(); nb_faults];
+    for (i, p) in candidates.iter().enumerate() {
+        for f in set_bits(&detections[*p]) {
+            clauses[f].push(i);
+        }
+    }
+    let mut constraints: Vec<(Vec<usize>, usize)> = clauses
+        .into_iter()
+        .filter(|c| !c.is_empty())
+        .map(|c| {
+            let m = c.len().min(n);
+            (c, m)
+        })
+        .collect();
+    constraints.sort();
+    constraints.dedup();
+
+    let lower_bound = cover_lower_bound(&constraints, candidates.len());
+    let mut best = initial;
+    best.sort();
+    while best.len() > lower_bound {
+        let (res, selected) = cover_with_at_most(&constraints, candidates.len(), best.len() - 1);
+        match res {
+            SolverResult::Sat => best = selected.iter().map(|i| candidates[*i]).collect(),
+            SolverResult::Unsat => break,
+            SolverResult::Interrupted => {
+                println!(
+                    "Exact compaction stopped at {} patterns, lower bound is {}",
+                    best.len(),
+                    lower_bound
+                );
+                break;
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    use super::{exact_cover, greedy_cover, ordered_cover, random_order_cover};
+
+    /// Patterns as bitsets, for a set cover problem where greedy is not optimal
+    fn detections() -> Vec<Vec<u64>> {
+        // Faults 0-2 and 3-5 are covered by two patterns, but the greedy
+        // algorithm first picks the pattern detecting faults 0, 1, 3 and 4
+        vec![vec![0b000111], vec![0b111000], vec![0b011011]]
+    }
+
+    #[test]
+    fn test_compaction() {
+        let d = detections();
+        let greedy = greedy_cover(&d, 6, 1);
+        assert_eq!(greedy.len(), 3);
+        assert_eq!(greedy[0], 2);
+        // The first pattern selected is redundant with the next two
+        assert_eq!(ordered_cover(&d, &[2, 1, 0], 1), vec![0, 1]);
+        assert_eq!(ordered_cover(&d, &[0, 1, 2], 1), vec![0, 1]);
+        let random = random_order_cover(&d, &mut SmallRng::seed_from_u64(1), 1);
+        assert_eq!(random.len(), 2);
+        assert_eq!(exact_cover(&d, 6, 1, greedy), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_n_detect_compaction() {
+        // With two detections, faults 0, 1, 3 and 4 need two patterns each
+        let mut d = detections();
+        d.push(vec![0b100100]);
+        let greedy = greedy_cover(&d, 6, 2);
+        assert_eq!(greedy.len(), 4);
+        assert_eq!(ordered_cover(&d, &[0, 1, 2, 3], 2), vec![0, 1, 2, 3]);
+        assert_eq!(ordered_cover(&d, &[0, 1, 2, 3], 1), vec![0, 1]);
+        let mut exact = exact_cover(&d, 6, 2, greedy);
+        exact.sort();
+        assert_eq!(exact, vec![0, 1, 2, 3]);
+        let random = random_order_cover(&d, &mut SmallRng::seed_from_u64(1), 2);
+        assert_eq!(random.len(), 4);
+    }
+}
@@ -0,0 +1,382 @@
+use std::fmt;
+
+use crate::sim::{
+    detects_faults_multi_parallel, pack_patterns, simulate_multi, simulate_multi_with_faults, Fault,
+};
+use crate::{Gate, Network, Signal};
+
+/// Taps of maximum-length linear feedback shift registers, for widths 2 to 32
+///
+/// For a width n, the feedback polynomial is x^n + 1 plus the x^k terms for the taps k below n.
+const PRIMITIVE_TAPS: [&[usize]; 31] = [
+    &[2, 1],
+    &[3, 2],
+    &[4, 3],
+    &[5, 3],
+    &[6, 5],
+    &[7, 6],
+    &[8, 6, 5, 4],
+    &[9, 5],
+    &[10, 7],
+    &[11, 9],
+    &[12, 6, 4, 1],
+    &[13, 4, 3, 1],
+    &[14, 5, 3, 1],
+    &[15, 14],
+    &[16, 15, 13, 4],
+    &[17, 14],
+    &[18, 11],
+    &[19, 6, 2, 1],
+    &[20, 17],
+    &[21, 19],
+    &[22, 21],
+    &[23, 18],
+    &[24, 23, 22, 17],
+    &[25, 22],
+    &[26, 6, 2, 1],
+    &[27, 5, 2, 1],
+    &[28, 25],
+    &[29, 27],
+    &[30, 6, 4, 1],
+    &[31, 28],
+    &[32, 22, 2, 1],
+];
+
+/// Taps of a maximum-length linear feedback shift register of the given width
+pub(crate) fn primitive_taps(width: usize) -> &'static [usize] {
+    assert!(
+        (2..=32).contains(&width),
+        "Linear feedback shift registers are supported for widths 2 to 32"
+    );
+    PRIMITIVE_TAPS[width - 2]
+}
+
+/// Test response compactor appended to the outputs of a network
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compactor {
+    /// Space compactor: each compacted output is the Xor of the outputs with the same index modulo its width
+    Xor(usize),
+    /// Multiple-input signature register of the given width, with a primitive feedback polynomial
+    ///
+    /// Each bit of the register takes the Xor of the outputs with the same index modulo its width.
+    Misr(usize),
+}
+
+/// Xor of the signals, or the signal itself if there is only one
+fn xor_all(aig: &mut Network, v: &[Signal]) -> Signal {
+    let v: Vec<Signal> = v.iter().copied().filter(|s| *s != Signal::zero()).collect();
+    match v.len() {
+        0 => Signal::zero(),
+        1 => v[0],
+        2 => aig.add(Gate::xor(v[0], v[1])),
+        _ => aig.add(Gate::xorn(&v)),
+    }
+}
+
+/// Xor of the outputs of a network, grouped by index modulo the width
+fn fold_outputs(aig: &Network, ret: &mut Network, width: usize) -> Vec<Signal> {
+    let mut groups = vec![Vec::new(); width];
+    for o in 0..aig.nb_outputs() {
+        groups[o % width].push(aig.output(o));
+    }
+    groups.iter().map(|g| xor_all(ret, g)).collect()
+}
+
+/// Next state of a MISR of the given width, with the state and inputs given as bitsets
+///
+/// This matches the register built by [`add_compactor`].
+fn misr_step(state: u64, inputs: u64, width: usize) -> u64 {
+    let feedback = state >> (width - 1) & 1;
+    let mut next = (state << 1 | feedback) & ((1 << width) - 1);
+    for t in primitive_taps(width) {
+        if *t < width {
+            next ^= feedback << t;
+        }
+    }
+    next ^ inputs
+}
+
+/// Create a copy of the network with a compactor on its outputs
+///
+/// The outputs of the new network are those of the compactor: the compacted values for a Xor
+/// compactor, or the state of the register for a MISR. Gate indices are unchanged, and the
+/// compactor is added after the original gates. A Xor compactor may not have more outputs than
+/// the network, as some of them would be constant.
+pub fn add_compactor(aig: &Network, compactor: Compactor) -> Network {
+    let mut ret = Network::new();
+    ret.add_inputs(aig.nb_inputs());
+    for i in 0..aig.nb_nodes() {
+        ret.add(aig.gate(i).clone());
+    }
+    let compacted = match compactor {
+        Compactor::Xor(width) => {
+            assert!(
+                (1..=aig.nb_outputs()).contains(&width),
+                "A Xor compactor should have between 1 output and the number of outputs"
+            );
+            fold_outputs(aig, &mut ret, width)
+        }
+        Compactor::Misr(width) => {
+            let taps = primitive_taps(width);
+            let inputs = fold_outputs(aig, &mut ret, width);
+            // Galois configuration: the last bit is fed back to the first bit and to the taps
+            let state: Vec<Signal> = (0..width)
+                .map(|_| {
+                    ret.add(Gate::dff(
+                        Signal::placeholder(),
+                        Signal::one(),
+                        Signal::zero(),
+                    ))
+                })
+                .collect();
+            let feedback = state[width - 1];
+            for i in 0..width {
+                let mut v = vec![inputs[i]];
+                if i == 0 {
+                    v.push(feedback);
+                } else {
+                    v.push(state[i - 1]);
+                    if taps.contains(&i) {
+                        v.push(feedback);
+                    }
+                }
+                let d = xor_all(&mut ret, &v);
+                ret.replace(
+                    state[i].var() as usize,
+                    Gate::dff(d, Signal::one(), Signal::zero()),
+                );
+            }
+            state
+        }
+    };
+    for s in compacted {
+        ret.add_output(s);
+    }
+    ret.check();
+    ret
+}
+
+/// Effect of a compactor on the faults detected by a set of test patterns
+#[derive(Clone, Debug)]
+pub struct CompactionReport {
+    /// Compactor analyzed
+    pub compactor: Compactor,
+    /// Number of faults analyzed
+    pub nb_faults: usize,
+    /// Number of faults detected on the original outputs
+    pub nb_detected: usize,
+    /// Faults detected on the original outputs, but not after compaction
+    pub aliased: Vec<Fault>,
+}
+
+impl CompactionReport {
+    /// Fraction of the detected faults that are lost after compaction
+    pub fn aliasing_rate(&self) -> f64 {
+        if self.nb_detected == 0 {
+            0.0
+        } else {
+            self.aliased.len() as f64 / self.nb_detected as f64
+        }
+    }
+
+    /// Probability that an erroneous response yields the correct signature, for a long test
+    ///
+    /// This is only defined for a MISR, for which it is 2^-width.
+    pub fn theoretical_aliasing(&self) -> Option<f64> {
+        match self.compactor {
+            Compactor::Xor(_) => None,
+            Compactor::Misr(width) => Some(0.5f64.powi(width as i32)),
+        }
+    }
+}
+
+impl fmt::Display for CompactionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let after = self.nb_detected - self.aliased.len();
+        writeln!(
+            f,
+            "Detected {}/{} faults before compaction, {}/{} after ({} aliased, {:.4}% aliasing)",
+            self.nb_detected,
+            self.nb_faults,
+            after,
+            self.nb_faults,
+            self.aliased.len(),
+            100.0 * self.aliasing_rate()
+        )?;
+        if let Some(p) = self.theoretical_aliasing() {
+            writeln!(f, "Theoretical aliasing probability of the MISR: {:e}", p)?;
+        }
+        for fault in &self.aliased {
+            writeln!(f, "Aliased: {}", fault)?;
+        }
+        Ok(())
+    }
+}
+
+/// Analyze which faults are lost after compaction of the responses to the given patterns
+///
+/// The network must be combinational. For a Xor compactor, each pattern is observed separately.
+/// For a MISR, the patterns are applied one per cycle, and only the final signature is observed.
+/// The faults are the collapsed stuck-at faults of the network.
+pub fn analyze_compaction(
+    aig: &Network,
+    compactor: Compactor,
+    patterns: &[Vec<bool>],
+    nb_threads: usize,
+) -> CompactionReport {
+    assert!(aig.is_comb());
+    let faults = Fault::all_collapsed(aig);
+    let compacted = add_compactor(aig, compactor);
+
+    let (packed, masks) = pack_patterns(patterns, aig.nb_inputs());
+    let detected_by = |network: &Network| -> Vec<bool> {
+        let mut ret = vec![false; faults.len()];
+        let detections = detects_faults_multi_parallel(network, &packed, &faults, nb_threads);
+        for (d, mask) in detections.iter().zip(&masks) {
+            for (f, v) in d.iter().enumerate() {
+                ret[f] |= v & mask != 0;
+            }
+        }
+        ret
+    };
+    let before = detected_by(aig);
+    let after = match compactor {
+        Compactor::Xor(_) => detected_by(&compacted),
+        Compactor::Misr(width) => {
+            // The register is linear: the faulty signature differs from the expected one by the
+            // signature of the output errors, obtained from the 64 patterns of each word at once
+            let expected = simulate_multi(aig, &packed);
+            faults
+                .iter()
+                .zip(&before)
+                .map(|(f, d)| {
+                    if !*d {
+                        return false;
+                    }
+                    let actual = simulate_multi_with_faults(aig, &packed, &vec![*f]);
+                    let mut signature = 0u64;
+                    for ((e, a), mask) in expected.iter().zip(&actual).zip(&masks) {
+                        for b in 0..mask.count_ones() {
+                            let mut error = 0u64;
+                            for (o, (x, y)) in e.iter().zip(a).enumerate() {
+                                error ^= ((x ^ y) >> b & 1) << (o % width);
+                            }
+                            signature = misr_step(signature, error, width);
+                        }
+                    }
+                    signature != 0
+                })
+                .collect()
+        }
+    };
+    CompactionReport {
+        compactor,
+        nb_faults: faults.len(),
+        nb_detected: before.iter().filter(|b| **b).count(),
+        aliased: faults
+            .iter()
+            .zip(before.iter().zip(&after))
+            .filter(|(_, (b, a))| **b && !**a)
+            .map(|(f, _)| *f)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_compactor, analyze_compaction, Compactor};
+    use crate::atpg::generate_random_comb_patterns;
+    use crate::sim::{simulate, simulate_comb, simulate_with_faults, Fault};
+    use crate::Network;
+
+    #[test]
+    fn test_xor_compactor() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let x = aig.and(i0, i1);
+        aig.add_output(x);
+        aig.add_output(x);
+        aig.add_output(i2);
+        let compacted = add_compactor(&aig, Compactor::Xor(2));
+        assert_eq!(compacted.nb_outputs(), 2);
+        assert!(compacted.is_comb());
+        for p in generate_random_comb_patterns(3, 8, 1) {
+            let o = simulate_comb(&aig, &p);
+            assert_eq!(simulate_comb(&compacted, &p), vec![o[0] ^ o[2], o[1]]);
+        }
+        // Each fault still reaches a compacted output through a single path
+        let patterns = generate_random_comb_patterns(3, 16, 1);
+        let report = analyze_compaction(&aig, Compactor::Xor(2), &patterns, 1);
+        assert_eq!(report.nb_detected, report.nb_faults);
+        assert!(report.aliased.is_empty());
+        // Duplicate outputs cancel each other when compacted together
+        let mut dup = Network::new();
+        let i0 = dup.add_input();
+        let i1 = dup.add_input();
+        let x = dup.and(i0, i1);
+        dup.add_output(x);
+        dup.add_output(x);
+        let patterns = generate_random_comb_patterns(2, 16, 1);
+        let report = analyze_compaction(&dup, Compactor::Xor(1), &patterns, 1);
+        assert_eq!(report.aliased.len(), report.nb_detected);
+        assert_eq!(report.aliasing_rate(), 1.0);
+    }
+
+    #[test]
+    fn test_misr() {
+        let mut aig = Network::new();
+        let inputs: Vec<_> = (0..4).map(|_| aig.add_input()).collect();
+        for i in &inputs {
+            aig.add_output(*i);
+        }
+        let misr = add_compactor(&aig, Compactor::Misr(4));
+        assert_eq!(misr.nb_outputs(), 4);
+        // With no input, the register only shifts its state; with all inputs, it loads them
+        let mut seq = vec![vec![true, false, false, false]];
+        seq.extend(vec![vec![false; 4]; 16]);
+        let states: Vec<Vec<bool>> = simulate(&misr, &seq);
+        assert_eq!(states[1], vec![true, false, false, false]);
+        assert_eq!(states[2], vec![false, true, false, false]);
+        // Maximum-length sequence: back to the first state after 15 cycles
+        assert_eq!(states[16], states[1]);
+        assert!((2..16).all(|t| states[t] != states[1]));
+
+        let patterns = generate_random_comb_patterns(4, 32, 1);
+        let report = analyze_compaction(&aig, Compactor::Misr(4), &patterns, 1);
+        assert_eq!(report.nb_detected, report.nb_faults);
+        assert_eq!(report.theoretical_aliasing(), Some(1.0 / 16.0));
+    }
+
+    #[test]
+    fn test_misr_aliasing() {
+        // Compare to the simulation of the register, one pattern per cycle
+        let mut aig = Network::new();
+        let inputs: Vec<_> = (0..5).map(|_| aig.add_input()).collect();
+        let x = aig.and(inputs[0], inputs[1]);
+        let y = aig.xor(x, inputs[2]);
+        let z = aig.and(inputs[3], !inputs[4]);
+        aig.add_output(x);
+        aig.add_output(y);
+        aig.add_output(z);
+        aig.add_output(inputs[4]);
+        aig.add_output(y);
+        let misr = add_compactor(&aig, Compactor::Misr(2));
+        let patterns = generate_random_comb_patterns(5, 70, 2);
+        let mut sequence = patterns.clone();
+        sequence.push(vec![false; 5]);
+        let expected = simulate(&misr, &sequence);
+        let report = analyze_compaction(&aig, Compactor::Misr(2), &patterns, 1);
+        let mut nb_different = 0;
+        for f in Fault::all_collapsed(&aig) {
+            let actual = simulate_with_faults(&misr, &sequence, &vec![f]);
+            if actual.last() != expected.last() {
+                nb_different += 1;
+                assert!(!report.aliased.contains(&f));
+            }
+        }
+        assert!(nb_different > 0);
+        assert_eq!(report.nb_detected - report.aliased.len(), nb_different);
+    }
+}
@@ -0,0 +1,298 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::sim::{pack_patterns, simulate_multi, simulate_multi_with_faults, Fault};
+use crate::Network;
+
+/// Number of hypotheses kept at each step when building multiple-fault hypotheses
+const BEAM_WIDTH: usize = 16;
+
+/// Candidate explanation of the failures observed on the tester
+///
+/// The candidate is compared to the tester responses bit by bit, for each pattern and output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiagnosisCandidate {
+    /// Faults of the hypothesis, all present at the same time
+    pub faults: Vec<Fault>,
+    /// Number of bits failing on the tester and in simulation
+    pub tfsf: usize,
+    /// Number of bits failing on the tester but not in simulation
+    pub tfsp: usize,
+    /// Number of bits passing on the tester but failing in simulation
+    pub tpsf: usize,
+}
+
+impl DiagnosisCandidate {
+    /// Score of the candidate, between 0 and 1, with 1 for a perfect match of the failing bits
+    pub fn score(&self) -> f64 {
+        let total = self.tfsf + self.tfsp + self.tpsf;
+        if total == 0 {
+            0.0
+        } else {
+            self.tfsf as f64 / total as f64
+        }
+    }
+
+    /// Returns whether the candidate explains all failing bits, and only them
+    pub fn is_exact(&self) -> bool {
+        self.tfsf > 0 && self.tfsp == 0 && self.tpsf == 0
+    }
+
+    /// Order candidates from best to worst: higher score, then fewer faults
+    fn rank_cmp(&self, other: &DiagnosisCandidate) -> Ordering {
+        // Compare the scores exactly, as tfsf_a / total_a > tfsf_b / total_b
+        let total = |c: &DiagnosisCandidate| (c.tfsf + c.tfsp + c.tpsf).max(1) as u128;
+        let score_a = self.tfsf as u128 * total(other);
+        let score_b = other.tfsf as u128 * total(self);
+        score_b
+            .cmp(&score_a)
+            .then(self.faults.len().cmp(&other.faults.len()))
+            .then(self.tpsf.cmp(&other.tpsf))
+    }
+}
+
+impl fmt::Display for DiagnosisCandidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let faults: Vec<String> = self.faults.iter().map(|f| f.to_string()).collect();
+        write!(
+            f,
+            "{:.3}\t{}\t{}\t{}\t{}",
+            self.score(),
+            self.tfsf,
+            self.tfsp,
+            self.tpsf,
+            faults.join(" + ")
+        )
+    }
+}
+
+/// Effect-cause diagnosis of a failing circuit
+struct Diagnosis<'a> {
+    aig: &'a Network,
+    patterns: Vec<Vec<u64>>,
+    masks: Vec<u64>,
+    expected: Vec<Vec<u64>>,
+    tester_fail: Vec<Vec<u64>>,
+}
+
+impl<'a> Diagnosis<'a> {
+    /// Compare the simulated responses with the faults to the tester responses
+    fn evaluate(&self, faults: Vec<Fault>) -> DiagnosisCandidate {
+        let actual = simulate_multi_with_faults(self.aig, &self.patterns, &faults);
+        let mut ret = DiagnosisCandidate {
+            faults,
+            tfsf: 0,
+            tfsp: 0,
+            tpsf: 0,
+        };
+        for (((a, e), t), m) in actual
+            .iter()
+            .zip(&self.expected)
+            .zip(&self.tester_fail)
+            .zip(&self.masks)
+        {
+            for ((x, y), tester_fail) in a.iter().zip(e).zip(t) {
+                let sim_fail = (x ^ y) & m;
+                ret.tfsf += (*tester_fail & sim_fail).count_ones() as usize;
+                ret.tfsp += (*tester_fail & !sim_fail).count_ones() as usize;
+                ret.tpsf += (!*tester_fail & sim_fail).count_ones() as usize;
+            }
+        }
+        ret
+    }
+
+    fn nb_failing_bits(&self) -> usize {
+        self.tester_fail
+            .iter()
+            .flatten()
+            .map(|v| v.count_ones() as usize)
+            .sum()
+    }
+}
+
+/// Rank candidate defects explaining the responses observed on the tester
+///
+/// Each fault is simulated against the patterns, and compared to the observed responses bit by
+/// bit. Candidates are ranked by the ratio of explained failing bits to mispredicted bits.
+/// With a multiplicity above one, hypotheses with several faults present at the same time are
+/// built from the best candidates, and preferred only when they explain the failures better.
+/// Faults that are equivalent to a candidate cannot be distinguished from it.
+pub fn diagnose(
+    aig: &Network,
+    faults: &[Fault],
+    patterns: &[Vec<bool>],
+    observed: &[Vec<bool>],
+    max_multiplicity: usize,
+    nb_candidates: usize,
+) -> Vec<DiagnosisCandidate> {
+    assert!(aig.is_comb());
+    assert_eq!(patterns.len(), observed.len());
+    let (packed, masks) = pack_patterns(patterns, aig.nb_inputs());
+    let (observed, _) = pack_patterns(observed, aig.nb_outputs());
+    let expected = simulate_multi(aig, &packed);
+    let tester_fail = expected
+        .iter()
+        .zip(&observed)
+        .zip(&masks)
+        .map(|((e, o), m)| e.iter().zip(o).map(|(x, y)| (x ^ y) & m).collect())
+        .collect();
+    let diag = Diagnosis {
+        aig,
+        patterns: packed,
+        masks,
+        expected,
+        tester_fail,
+    };
+    if diag.nb_failing_bits() == 0 {
+        return Vec::new();
+    }
+
+    let mut singles: Vec<DiagnosisCandidate> = faults
+        .iter()
+        .map(|f| diag.evaluate(vec![*f]))
+        .filter(|c| c.tfsf > 0)
+        .collect();
+    singles.sort_by(|a, b| a.rank_cmp(b));
+    let mut candidates = singles.clone();
+
+    // Beam search over hypotheses with more faults, each explaining some of the failing bits
+    singles.truncate(BEAM_WIDTH);
+    let mut beam = singles.clone();
+    for _ in 1..max_multiplicity {
+        let mut next = Vec::new();
+        for h in &beam {
+            if h.is_exact() {
+                continue;
+            }
+            for s in &singles {
+                let f = s.faults[0];
+                if h.faults.iter().any(|g| *g >= f) {
+                    continue;
+                }
+                let mut faults = h.faults.clone();
+                faults.push(f);
+                if Fault::has_duplicate_gate(&faults) {
+                    continue;
+                }
+                let c = diag.evaluate(faults);
+                if c.rank_cmp(h) == Ordering::Less {
+                    next.push(c);
+                }
+            }
+        }
+        next.sort_by(|a, b| a.rank_cmp(b));
+        next.truncate(BEAM_WIDTH);
+        if next.is_empty() {
+            break;
+        }
+        candidates.extend(next.iter().cloned());
+        beam = next;
+    }
+    candidates.sort_by(|a, b| a.rank_cmp(b));
+    candidates.truncate(nb_candidates);
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use super::{diagnose, DiagnosisCandidate};
+    use crate::atpg::generate_random_comb_patterns;
+    use crate::sim::{simulate_comb_with_faults, Fault};
+    use crate::Network;
+
+    /// Two independent cones, each with an And and an Xor
+    fn example() -> Network {
+        let mut aig = Network::new();
+        let i: Vec<_> = (0..6).map(|_| aig.add_input()).collect();
+        let a0 = aig.and(i[0], i[1]);
+        let x0 = aig.xor(a0, i[2]);
+        let a1 = aig.and(i[3], i[4]);
+        let x1 = aig.xor(a1, i[5]);
+        aig.add_output(x0);
+        aig.add_output(x1);
+        aig
+    }
+
+    fn responses(aig: &Network, patterns: &[Vec<bool>], faults: &Vec<Fault>) -> Vec<Vec<bool>> {
+        patterns
+            .iter()
+            .map(|p| simulate_comb_with_faults(aig, p, faults))
+            .collect()
+    }
+
+    #[test]
+    fn test_single_fault_diagnosis() {
+        let aig = example();
+        let faults = Fault::all_collapsed(&aig);
+        let patterns = generate_random_comb_patterns(6, 64, 1);
+        let injected = Fault::OutputStuckAtFault {
+            gate: 0,
+            value: true,
+        };
+        let observed = responses(&aig, &patterns, &vec![injected]);
+        let candidates = diagnose(&aig, &faults, &patterns, &observed, 1, 5);
+        assert!(candidates[0].is_exact());
+        assert_eq!(candidates[0].score(), 1.0);
+        // The injected fault is equivalent to the top candidate
+        assert_eq!(responses(&aig, &patterns, &candidates[0].faults), observed);
+        assert!(candidates[1..].iter().all(|c| c.score() < 1.0));
+
+        // Passing responses have no candidate
+        let observed = responses(&aig, &patterns, &Vec::new());
+        assert!(diagnose(&aig, &faults, &patterns, &observed, 1, 5).is_empty());
+    }
+
+    #[test]
+    fn test_multiple_fault_diagnosis() {
+        let aig = example();
+        let faults = Fault::all_collapsed(&aig);
+        let patterns = generate_random_comb_patterns(6, 64, 1);
+        let injected = vec![
+            Fault::OutputStuckAtFault {
+                gate: 0,
+                value: true,
+            },
+            Fault::OutputStuckAtFault {
+                gate: 3,
+                value: false,
+            },
+        ];
+        let observed = responses(&aig, &patterns, &injected);
+        let single = diagnose(&aig, &faults, &patterns, &observed, 1, 5);
+        assert!(!single[0].is_exact());
+        let multiple = diagnose(&aig, &faults, &patterns, &observed, 2, 5);
+        assert!(multiple[0].is_exact());
+        assert_eq!(multiple[0].faults.len(), 2);
+        assert_eq!(responses(&aig, &patterns, &multiple[0].faults), observed);
+    }
+
+    #[test]
+    fn test_candidate_ranking() {
+        let candidate = |nb_faults: usize, tfsf: usize, tfsp: usize, tpsf: usize| {
+            let fault = Fault::OutputStuckAtFault {
+                gate: 0,
+                value: false,
+            };
+            DiagnosisCandidate {
+                faults: vec![fault; nb_faults],
+                tfsf,
+                tfsp,
+                tpsf,
+            }
+        };
+        // Large counts do not overflow, and close scores are still distinguished
+        let big = usize::MAX / 4;
+        let a = candidate(2, big, 1, 0);
+        let b = candidate(1, big - 1, 1, 0);
+        assert_eq!(a.rank_cmp(&b), Ordering::Less);
+        assert_eq!(b.rank_cmp(&a), Ordering::Greater);
+        // Same score: fewer faults first
+        let c = candidate(1, 2 * big, 2, 0);
+        assert_eq!(c.rank_cmp(&a), Ordering::Less);
+        // No failing bit explained: lowest score
+        let d = candidate(1, 0, 0, 0);
+        assert_eq!(d.rank_cmp(&b), Ordering::Greater);
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::sim::Fault;
+use crate::Network;
+
+use super::{comb_test_generator, AtpgOptions};
+
+/// Coverage changes after regenerating test patterns for a modified network
+#[derive(Clone, Debug)]
+pub struct IncrementalReport {
+    /// Number of faults targeted on the new network
+    pub nb_faults: usize,
+    /// Number of patterns in the old set
+    pub nb_old_patterns: usize,
+    /// Number of faults of the new network detected by the old patterns
+    pub nb_old_detected: usize,
+    /// Number of faults detected by the merged patterns
+    pub nb_detected: usize,
+    /// Number of old patterns kept after compaction
+    pub nb_old_kept: usize,
+    /// Number of new patterns kept after compaction
+    pub nb_new_kept: usize,
+    /// Faults missed by the old patterns and detected by the new ones
+    pub recovered: Vec<Fault>,
+    /// Faults missed by the old patterns that remain undetected
+    pub undetected: Vec<Fault>,
+}
+
+impl IncrementalReport {
+    fn coverage(&self, nb_detected: usize) -> f64 {
+        if self.nb_faults == 0 {
+            1.0
+        } else {
+            nb_detected as f64 / self.nb_faults as f64
+        }
+    }
+
+    /// Fault coverage of the old patterns on the new network
+    pub fn old_coverage(&self) -> f64 {
+        self.coverage(self.nb_old_detected)
+    }
+
+    /// Fault coverage of the merged patterns
+    pub fn new_coverage(&self) -> f64 {
+        self.coverage(self.nb_detected)
+    }
+}
+
+impl fmt::Display for IncrementalReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Old patterns: {} patterns, detecting {}/{} faults ({:.2}% coverage)",
+            self.nb_old_patterns,
+            self.nb_old_detected,
+            self.nb_faults,
+            100.0 * self.old_coverage()
+        )?;
+        writeln!(
+            f,
+            "Merged patterns: {} patterns ({} old, {} new), detecting {}/{} faults ({:.2}% coverage)",
+            self.nb_old_kept + self.nb_new_kept,
+            self.nb_old_kept,
+            self.nb_new_kept,
+            self.nb_detected,
+            self.nb_faults,
+            100.0 * self.new_coverage()
+        )?;
+        writeln!(
+            f,
+            "Coverage change: {:+.2}%, {} faults recovered, {} undetected",
+            100.0 * (self.new_coverage() - self.old_coverage()),
+            self.recovered.len(),
+            self.undetected.len()
+        )?;
+        for fault in &self.recovered {
+            writeln!(f, "Recovered: {}", fault)?;
+        }
+        for fault in &self.undetected {
+            writeln!(f, "Undetected: {}", fault)?;
+        }
+        Ok(())
+    }
+}
+
+/// Update combinatorial test patterns after a modification of the network
+///
+/// The old patterns are graded on the new network with the fault simulator, and new patterns
+/// are only generated for the faults they miss. The merged set is compacted, so that old
+/// patterns made redundant by the new ones are dropped. The options are the same as for
+/// [`generate_comb_test_patterns`](super::generate_comb_test_patterns).
+pub fn generate_incremental_comb_test_patterns(
+    aig: &Network,
+    old_patterns: &[Vec<bool>],
+    options: &AtpgOptions,
+) -> (Vec<Vec<bool>>, IncrementalReport) {
+    let mut gen = comb_test_generator(aig, options);
+    gen.add_existing_patterns(old_patterns);
+    let nb_old_detected = gen.nb_detected();
+    let missed: Vec<usize> = (0..gen.nb_faults())
+        .filter(|i| gen.detection[*i] == 0)
+        .collect();
+    println!(
+        "Old patterns detect {}/{} faults, generating patterns for the {} others",
+        nb_old_detected,
+        gen.nb_faults(),
+        missed.len()
+    );
+    gen.detect_faults();
+    gen.finish();
+
+    let old: HashSet<&Vec<bool>> = old_patterns.iter().collect();
+    let nb_old_kept = gen.patterns.iter().filter(|p| old.contains(p)).count();
+    let (recovered, undetected): (Vec<usize>, Vec<usize>) =
+        missed.into_iter().partition(|i| gen.detection[*i] > 0);
+    let report = IncrementalReport {
+        nb_faults: gen.nb_faults(),
+        nb_old_patterns: old_patterns.len(),
+        nb_old_detected,
+        nb_detected: gen.nb_detected(),
+        nb_old_kept,
+        nb_new_kept: gen.nb_patterns() - nb_old_kept,
+        recovered: recovered.iter().map(|i| gen.faults[*i]).collect(),
+        undetected: undetected.iter().map(|i| gen.faults[*i]).collect(),
+    };
+    (gen.patterns, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::generate_incremental_comb_test_patterns;
+    use crate::atpg::{generate_comb_test_patterns, AtpgOptions};
+    use crate::sim::{detects_faults, Fault};
+    use crate::{Gate, Network, Signal};
+
+    fn example(eco: bool) -> Network {
+        let mut aig = Network::new();
+        let i: Vec<Signal> = (0..5).map(|_| aig.add_input()).collect();
+        let a = aig.and(i[0], i[1]);
+        let x = aig.xor(a, i[2]);
+        // The change replaces an And by an Or, and adds logic on a new cone
+        let b = if eco {
+            !aig.and(!i[3], i[4])
+        } else {
+            aig.and(i[3], i[4])
+        };
+        aig.add_output(x);
+        aig.add_output(b);
+        if eco {
+            let m = aig.add(Gate::mux(i[4], x, i[3]));
+            aig.add_output(m);
+        }
+        aig
+    }
+
+    fn generate(aig: &Network, old: &[Vec<bool>]) -> (Vec<Vec<bool>>, super::IncrementalReport) {
+        generate_incremental_comb_test_patterns(aig, old, &AtpgOptions::default())
+    }
+
+    #[test]
+    fn test_incremental_atpg() {
+        let before = example(false);
+        let old = generate_comb_test_patterns(&before, &AtpgOptions::default());
+
+        // Unchanged network: the old patterns are kept as is
+        let (patterns, report) = generate(&before, &old);
+        assert_eq!(report.nb_old_detected, report.nb_faults);
+        assert_eq!(report.nb_new_kept, 0);
+        assert_eq!(patterns.len(), old.len());
+        assert!(report.recovered.is_empty());
+
+        // Modified network: the faults missed by the old patterns are recovered
+        let after = example(true);
+        let (patterns, report) = generate(&after, &old);
+        assert_eq!(report.nb_detected, report.nb_faults);
+        assert!(report.undetected.is_empty());
+        assert_eq!(
+            report.nb_old_detected + report.recovered.len(),
+            report.nb_detected
+        );
+        assert_eq!(report.nb_old_kept + report.nb_new_kept, patterns.len());
+        for f in Fault::all_dominance_collapsed(&after) {
+            assert!(patterns
+                .iter()
+                .any(|p| detects_faults(&after, p, &vec![f])[0]));
+        }
+    }
+}
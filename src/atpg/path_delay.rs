@@ -0,0 +1,362 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+
+use crate::equiv::prove;
+use crate::network::NaryType;
+use crate::utils::compute_levels;
+use crate::{Gate, Network, Signal};
+
+/// A structural path from a primary input to a gate driving an output
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Path {
+    /// Input where the path starts
+    pub input: usize,
+    /// Gates along the path, with the index of the on-path input of each gate
+    pub gates: Vec<(usize, usize)>,
+}
+
+impl Path {
+    /// Number of gates along the path
+    pub fn len(&self) -> usize {
+        self.gates.len()
+    }
+
+    /// Returns whether the path goes through no gate
+    pub fn is_empty(&self) -> bool {
+        self.gates.is_empty()
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "i{}", self.input)?;
+        for (g, _) in &self.gates {
+            write!(f, " -> x{}", g)?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of test generation for a path-delay fault
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathStatus {
+    /// The path has a robust test, given as two timesteps
+    Robust(Vec<Vec<bool>>),
+    /// The path only has a non-robust test, given as two timesteps
+    NonRobust(Vec<Vec<bool>>),
+    /// The path can be sensitized, but no test launches the transition along it
+    Untestable,
+    /// No input pattern sensitizes the path
+    FalsePath,
+}
+
+/// Result of test generation for a path, with its transition
+#[derive(Clone, Debug)]
+pub struct PathDelayResult {
+    /// Path under test
+    pub path: Path,
+    /// Whether the transition is rising at the input of the path
+    pub rising: bool,
+    /// Test generation result
+    pub status: PathStatus,
+}
+
+/// Path being built backwards: the head gate if any, and the gates after it in reverse order
+type PartialPath = (Option<usize>, Vec<(usize, usize)>);
+
+/// Enumerate the K longest paths of a combinatorial network, by number of gates
+///
+/// This is a best-first search from the outputs, with the logic levels as an exact bound on the
+/// length of the remaining path. Paths are returned from the longest to the shortest.
+pub fn longest_paths(aig: &Network, k: usize) -> Vec<Path> {
+    assert!(aig.is_comb());
+    let levels = compute_levels(aig, true);
+
+    // Partial paths, as the head gate and the rest of the path in reverse order
+    let mut partial: Vec<PartialPath> = Vec::new();
+    let mut heap = BinaryHeap::new();
+    let mut heads: Vec<usize> = (0..aig.nb_outputs())
+        .map(|o| aig.output(o))
+        .filter(|s| s.is_var())
+        .map(|s| s.var() as usize)
+        .collect();
+    heads.sort();
+    heads.dedup();
+    for g in heads {
+        heap.push((levels[g], Reverse(partial.len())));
+        partial.push((Some(g), Vec::new()));
+    }
+
+    let mut ret = Vec::new();
+    while let Some((_, Reverse(id))) = heap.pop() {
+        if ret.len() >= k {
+            break;
+        }
+        let (head, tail) = std::mem::take(&mut partial[id]);
+        let Some(g) = head else {
+            // Complete path: the input is stored with the first gate
+            let mut gates = tail;
+            gates.reverse();
+            let input = aig.gate(gates[0].0).dependencies()[gates[0].1].input() as usize;
+            ret.push(Path { input, gates });
+            continue;
+        };
+        for (i, s) in aig.gate(g).dependencies().iter().enumerate() {
+            let mut t = tail.clone();
+            t.push((g, i));
+            let len = t.len() as i32;
+            if s.is_input() {
+                heap.push((len, Reverse(partial.len())));
+                partial.push((None, t));
+            } else if s.is_var() {
+                heap.push((levels[s.var() as usize] + len, Reverse(partial.len())));
+                partial.push((Some(s.var() as usize), t));
+            }
+        }
+    }
+    ret
+}
+
+/// Controlling value of the inputs of an And-like gate
+fn controlling_value(g: &Gate) -> Option<bool> {
+    if !g.is_and_like() {
+        return None;
+    }
+    Some(matches!(
+        g,
+        Gate::Nary(_, NaryType::Or) | Gate::Nary(_, NaryType::Nor)
+    ))
+}
+
+/// Or of two signals
+fn or(aig: &mut Network, a: Signal, b: Signal) -> Signal {
+    !aig.and(!a, !b)
+}
+
+/// Build the conditions to test a path
+///
+/// The network has two copies of the original network, with the inputs of the first and second
+/// timesteps. If `transition` is false, only the sensitization in the second timestep is required.
+fn path_condition(
+    aig: &Network,
+    path: &Path,
+    rising: bool,
+    robust: bool,
+    transition: bool,
+) -> Network {
+    let n = aig.nb_nodes() as u32;
+    let nb_inputs = aig.nb_inputs() as u32;
+    let mut ret = Network::new();
+    ret.add_inputs(2 * aig.nb_inputs());
+    for i in 0..aig.nb_nodes() {
+        ret.add(aig.gate(i).clone());
+    }
+    let t = |s: &Signal| -> Signal {
+        if s.is_input() {
+            Signal::from_input(s.input() + nb_inputs) ^ s.is_inverted()
+        } else if s.is_var() {
+            Signal::from_var(s.var() + n) ^ s.is_inverted()
+        } else {
+            *s
+        }
+    };
+    for i in 0..aig.nb_nodes() {
+        ret.add(aig.gate(i).remap(t));
+    }
+
+    let mut conds = Vec::new();
+    if transition {
+        let start = Signal::from_input(path.input as u32);
+        conds.push(start ^ rising);
+        conds.push(t(&start) ^ !rising);
+    }
+    for (g, k) in &path.gates {
+        let gate = aig.gate(*g);
+        // Sensitization in the second timestep: the output depends on the on-path input
+        let g0 = ret.add(gate.remap_with_ind(|s, i| if i == *k { Signal::zero() } else { t(s) }));
+        let g1 = ret.add(gate.remap_with_ind(|s, i| if i == *k { Signal::one() } else { t(s) }));
+        let sensitized = ret.xor(g0, g1);
+        conds.push(sensitized);
+        if !transition {
+            continue;
+        }
+        // The on-path signals switch at every gate
+        let out = Signal::from_var(*g as u32);
+        let d = ret.xor(out, t(&out));
+        conds.push(d);
+        if !robust {
+            continue;
+        }
+        let on_path = gate.dependencies()[*k];
+        for (i, s) in gate.dependencies().iter().enumerate() {
+            if i == *k {
+                continue;
+            }
+            match controlling_value(gate) {
+                Some(c) => {
+                    // Side inputs must be non-controlling in both timesteps, unless the on-path input ends at the controlling value
+                    let final_ctrl = t(&on_path) ^ !c;
+                    let stable_nc = *s ^ c;
+                    let c = or(&mut ret, final_ctrl, stable_nc);
+                    conds.push(c);
+                }
+                None => {
+                    // Side inputs must be stable
+                    let d = ret.xor(*s, t(s));
+                    conds.push(!d);
+                }
+            }
+        }
+    }
+    let o = ret.add(Gate::andn(&conds));
+    ret.add_output(o);
+    ret.make_canonical();
+    ret.cleanup();
+    ret
+}
+
+/// Generate a two-timestep test for a path-delay fault
+///
+/// A non-robust test launches a transition at the input of the path and sensitizes each gate
+/// in the second timestep, so that the transition propagates along the path. A robust test also
+/// requires side inputs to be stable, or non-controlling in both timesteps when the on-path
+/// transition goes to the non-controlling value.
+pub fn generate_path_delay_test(aig: &Network, path: &Path, rising: bool) -> PathStatus {
+    assert!(aig.is_comb());
+    let to_timesteps = |p: Vec<bool>| -> Vec<Vec<bool>> {
+        let (v1, v2) = p.split_at(aig.nb_inputs());
+        vec![v1.to_vec(), v2.to_vec()]
+    };
+    if let Some(p) = prove(&path_condition(aig, path, rising, true, true)) {
+        return PathStatus::Robust(to_timesteps(p));
+    }
+    if let Some(p) = prove(&path_condition(aig, path, rising, false, true)) {
+        return PathStatus::NonRobust(to_timesteps(p));
+    }
+    if prove(&path_condition(aig, path, rising, false, false)).is_some() {
+        PathStatus::Untestable
+    } else {
+        PathStatus::FalsePath
+    }
+}
+
+/// Generate path-delay tests for the K longest paths of a combinatorial network
+///
+/// Both rising and falling transitions are targeted for each path.
+pub fn generate_path_delay_tests(aig: &Network, k: usize) -> Vec<PathDelayResult> {
+    let mut ret = Vec::new();
+    for path in longest_paths(aig, k) {
+        for rising in [true, false] {
+            let status = generate_path_delay_test(aig, &path, rising);
+            ret.push(PathDelayResult {
+                path: path.clone(),
+                rising,
+                status,
+            });
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_path_delay_test, longest_paths, Path, PathStatus};
+    use crate::sim::simulate;
+    use crate::Network;
+
+    #[test]
+    fn test_longest_paths() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let x = aig.and(i0, i1);
+        let y = aig.and(x, i2);
+        let z = aig.xor(y, i0);
+        aig.add_output(z);
+        aig.add_output(x);
+
+        let paths = longest_paths(&aig, 10);
+        let lens: Vec<usize> = paths.iter().map(|p| p.len()).collect();
+        assert_eq!(lens, vec![3, 3, 2, 1, 1, 1]);
+        for p in &paths[0..2] {
+            let gates: Vec<usize> = p.gates.iter().map(|(g, _)| *g).collect();
+            assert_eq!(gates, vec![0, 1, 2]);
+        }
+        assert_eq!(longest_paths(&aig, 2).len(), 2);
+    }
+
+    /// Path from the first input through the given gate, which drives an output
+    fn single_gate_path(aig: &Network, gate: usize) -> Path {
+        let k = aig
+            .gate(gate)
+            .dependencies()
+            .iter()
+            .position(|s| *s == aig.input(0))
+            .unwrap();
+        Path {
+            input: 0,
+            gates: vec![(gate, k)],
+        }
+    }
+
+    #[test]
+    fn test_path_delay() {
+        // The path through x is only sensitized when i1 is true, which blocks it at y
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let x = aig.and(i0, i1);
+        let y = aig.and(x, !i1);
+        let z = aig.and(i0, i1);
+        aig.add_output(y);
+        aig.add_output(z);
+        let mut false_path = single_gate_path(&aig, 0);
+        let k = aig
+            .gate(1)
+            .dependencies()
+            .iter()
+            .position(|s| *s == x)
+            .unwrap();
+        false_path.gates.push((1, k));
+        assert_eq!(
+            generate_path_delay_test(&aig, &false_path, true),
+            PathStatus::FalsePath
+        );
+
+        let path = single_gate_path(&aig, 2);
+        for rising in [true, false] {
+            let PathStatus::Robust(p) = generate_path_delay_test(&aig, &path, rising) else {
+                panic!("Path should be robustly testable");
+            };
+            assert_eq!(p[0][0], !rising);
+            assert_eq!(p[1][0], rising);
+            // The side input is non-controlling, and stable for a rising transition
+            assert!(p[1][1]);
+            assert!(p[0][1] || !rising);
+            let out = simulate(&aig, &p);
+            assert_ne!(out[0][1], out[1][1]);
+        }
+    }
+
+    #[test]
+    fn test_path_delay_non_robust() {
+        // The side input of y is controlling whenever i0 is false
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let w = aig.and(i0, i1);
+        let y = aig.and(i0, w);
+        aig.add_output(y);
+        let path = single_gate_path(&aig, 1);
+        assert!(matches!(
+            generate_path_delay_test(&aig, &path, true),
+            PathStatus::NonRobust(_)
+        ));
+        assert_eq!(
+            generate_path_delay_test(&aig, &path, false),
+            PathStatus::Untestable
+        );
+    }
+}
@@ -0,0 +1,613 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap};
+
+use volute::Lut;
+
+use crate::network::{BinaryType, NaryType, TernaryType};
+use crate::sim::Fault;
+use crate::utils::FanoutView;
+use crate::{Gate, Network, Signal};
+
+/// Result of structural test generation for a fault
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PodemResult {
+    /// A test pattern detecting the fault
+    Detected(Vec<bool>),
+    /// The search space was exhausted: the fault is untestable
+    Untestable,
+    /// The backtrack limit was reached, or the fault type is not supported
+    Aborted,
+}
+
+/// Three-valued logic value, with None for an unknown value
+type Tri = Option<bool>;
+
+/// Evaluate a gate in three-valued logic, from the values at its inputs
+fn eval_gate(g: &Gate, v: &[Tri]) -> Tri {
+    let and = |v: &[Tri]| -> Tri {
+        if v.contains(&Some(false)) {
+            Some(false)
+        } else if v.iter().all(|x| *x == Some(true)) {
+            Some(true)
+        } else {
+            None
+        }
+    };
+    let xor = |v: &[Tri]| -> Tri { v.iter().try_fold(false, |acc, x| x.map(|b| acc ^ b)) };
+    let not = |x: Tri| x.map(|b| !b);
+    let inv = |v: &[Tri]| -> Vec<Tri> { v.iter().map(|x| not(*x)).collect() };
+    match g {
+        Gate::Binary(_, BinaryType::And)
+        | Gate::Ternary(_, TernaryType::And)
+        | Gate::Nary(_, NaryType::And) => and(v),
+        Gate::Nary(_, NaryType::Nand) => not(and(v)),
+        Gate::Nary(_, NaryType::Or) => not(and(&inv(v))),
+        Gate::Nary(_, NaryType::Nor) => and(&inv(v)),
+        Gate::Binary(_, BinaryType::Xor)
+        | Gate::Ternary(_, TernaryType::Xor)
+        | Gate::Nary(_, NaryType::Xor) => xor(v),
+        Gate::Nary(_, NaryType::Xnor) => not(xor(v)),
+        Gate::Ternary(_, TernaryType::Mux) => match v[0] {
+            Some(true) => v[1],
+            Some(false) => v[2],
+            None => {
+                if v[1] == v[2] {
+                    v[1]
+                } else {
+                    None
+                }
+            }
+        },
+        Gate::Ternary(_, TernaryType::Maj) => {
+            let ones = v.iter().filter(|x| **x == Some(true)).count();
+            let zeros = v.iter().filter(|x| **x == Some(false)).count();
+            if ones >= 2 {
+                Some(true)
+            } else if zeros >= 2 {
+                Some(false)
+            } else {
+                None
+            }
+        }
+        Gate::Buf(_) => v[0],
+        Gate::Dff(_) => None,
+        Gate::Lut(lut) => {
+            // Cofactor the known inputs, and check whether the function is constant
+            let nb_vars = lut.lut.num_vars();
+            let mut f = lut.lut.clone();
+            for (i, x) in v.iter().enumerate() {
+                if let Some(b) = x {
+                    let (c0, c1) = f.cofactors(i);
+                    f = if *b { c1 } else { c0 };
+                }
+            }
+            if f == Lut::zero(nb_vars) {
+                Some(false)
+            } else if f == Lut::one(nb_vars) {
+                Some(true)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Structural test generation for a single stuck-at fault
+///
+/// Values of the good and faulty circuits are tracked separately in three-valued logic, which
+/// subsumes the 5-valued D-calculus: D is good 1/faulty 0, and D' is good 0/faulty 1.
+/// Implication is event-driven: only the fanout of a modified input is re-evaluated.
+struct Podem<'a> {
+    aig: &'a Network,
+    fanouts: &'a FanoutView,
+    /// Gate where the fault is located
+    gate: usize,
+    /// Faulty input of the gate, or None for an output fault
+    input: Option<usize>,
+    /// Stuck-at value
+    value: bool,
+    /// Assignment of the inputs
+    inputs: Vec<Tri>,
+    /// Values in the good circuit
+    good: Vec<Tri>,
+    /// Values in the faulty circuit
+    faulty: Vec<Tri>,
+    /// Gates with known and different values in the good and faulty circuits
+    errors: BTreeSet<usize>,
+    /// Number of outputs driven by a gate with an error
+    nb_detecting: usize,
+    /// Number of outputs driven by each gate
+    nb_outputs: Vec<usize>,
+    /// Gates to re-evaluate, in topological order
+    queue: BinaryHeap<Reverse<usize>>,
+    /// Whether each gate is already in the queue
+    queued: Vec<bool>,
+}
+
+impl<'a> Podem<'a> {
+    fn signal_value(&self, values: &[Tri], s: Signal) -> Tri {
+        if s.is_constant() {
+            Some(s == Signal::one())
+        } else if s.is_input() {
+            self.inputs[s.input() as usize].map(|b| b ^ s.is_inverted())
+        } else {
+            values[s.var() as usize].map(|b| b ^ s.is_inverted())
+        }
+    }
+
+    fn pin_values(&self, values: &[Tri], g: usize) -> Vec<Tri> {
+        self.aig
+            .gate(g)
+            .dependencies()
+            .iter()
+            .map(|s| self.signal_value(values, *s))
+            .collect()
+    }
+
+    /// Values at the inputs of a gate in the faulty circuit, with the fault applied
+    fn faulty_pin_values(&self, g: usize) -> Vec<Tri> {
+        let mut pins = self.pin_values(&self.faulty, g);
+        if g == self.gate {
+            if let Some(input) = self.input {
+                pins[input] = Some(self.value);
+            }
+        }
+        pins
+    }
+
+    /// Evaluate a gate in the good and faulty circuits, and return whether its value changed
+    fn update(&mut self, i: usize) -> bool {
+        let g = self.aig.gate(i);
+        let good = eval_gate(g, &self.pin_values(&self.good, i));
+        let faulty = if i == self.gate && self.input.is_none() {
+            Some(self.value)
+        } else {
+            eval_gate(g, &self.faulty_pin_values(i))
+        };
+        if good == self.good[i] && faulty == self.faulty[i] {
+            return false;
+        }
+        let was_error = self.is_error(i);
+        self.good[i] = good;
+        self.faulty[i] = faulty;
+        let is_error = self.is_error(i);
+        if is_error && !was_error {
+            self.errors.insert(i);
+            self.nb_detecting += self.nb_outputs[i];
+        } else if was_error && !is_error {
+            self.errors.remove(&i);
+            self.nb_detecting -= self.nb_outputs[i];
+        }
+        true
+    }
+
+    /// Queue the gates using a signal for re-evaluation
+    fn schedule_fanouts(&mut self, s: Signal) {
+        for j in self.fanouts.fanouts(s) {
+            let j = *j as usize;
+            if !self.queued[j] {
+                self.queued[j] = true;
+                self.queue.push(Reverse(j));
+            }
+        }
+    }
+
+    /// Simulate the good and faulty circuits from scratch
+    fn simulate(&mut self) {
+        for i in 0..self.aig.nb_nodes() {
+            self.update(i);
+        }
+    }
+
+    /// Assign an input, and propagate the implications in topological order
+    fn set_input(&mut self, i: usize, value: Tri) {
+        self.inputs[i] = value;
+        self.schedule_fanouts(Signal::from_input(i as u32));
+        while let Some(Reverse(j)) = self.queue.pop() {
+            self.queued[j] = false;
+            if self.update(j) {
+                self.schedule_fanouts(Signal::from_var(j as u32));
+            }
+        }
+    }
+
+    fn is_error(&self, i: usize) -> bool {
+        self.good[i].is_some() && self.faulty[i].is_some() && self.good[i] != self.faulty[i]
+    }
+
+    /// Whether the fault effect reached an output
+    fn detected(&self) -> bool {
+        self.nb_detecting > 0
+    }
+
+    /// Value required at the fault site to activate the fault, as a signal and a value
+    fn activation(&self) -> (Signal, bool) {
+        match self.input {
+            Some(input) => (self.aig.gate(self.gate).dependencies()[input], !self.value),
+            None => (Signal::from_var(self.gate as u32), !self.value),
+        }
+    }
+
+    /// Find the next objective, or None if the current assignment cannot detect the fault
+    fn objective(&self) -> Option<(Signal, bool)> {
+        let (site, v) = self.activation();
+        match self.signal_value(&self.good, site) {
+            None => return Some((site, v)),
+            Some(b) if b != v => return None,
+            _ => (),
+        }
+        // Propagate through the D-frontier: a gate with an unknown output and an error on an input.
+        // Errors only appear in the fanout of the gates with an error, or at the faulty input.
+        let mut frontier: Vec<usize> = self
+            .errors
+            .iter()
+            .flat_map(|e| self.fanouts.fanouts(Signal::from_var(*e as u32)))
+            .map(|j| *j as usize)
+            .collect();
+        if self.input.is_some() {
+            frontier.push(self.gate);
+        }
+        frontier.sort();
+        frontier.dedup();
+        let mut has_frontier = false;
+        for i in frontier {
+            if self.good[i].is_some() && self.faulty[i].is_some() {
+                continue;
+            }
+            let g = self.aig.gate(i);
+            let good = self.pin_values(&self.good, i);
+            let faulty = self.faulty_pin_values(i);
+            let has_error = (0..good.len())
+                .any(|j| good[j].is_some() && faulty[j].is_some() && good[j] != faulty[j]);
+            if !has_error {
+                continue;
+            }
+            has_frontier = true;
+            // Pick an unknown input and a value that does not block the error
+            for (j, s) in g.dependencies().iter().enumerate() {
+                if good[j].is_some() {
+                    continue;
+                }
+                for b in [false, true] {
+                    let mut gv = good.clone();
+                    let mut fv = faulty.clone();
+                    gv[j] = Some(b);
+                    if fv[j].is_none() {
+                        fv[j] = Some(b);
+                    }
+                    let (go, fo) = (eval_gate(g, &gv), eval_gate(g, &fv));
+                    if go.is_none() || fo.is_none() || go != fo {
+                        return Some((*s, b));
+                    }
+                }
+            }
+        }
+        // The unknown values are only in the faulty circuit: decide on any free input
+        if has_frontier {
+            self.free_input()
+        } else {
+            None
+        }
+    }
+
+    /// An unassigned input, as an objective
+    fn free_input(&self) -> Option<(Signal, bool)> {
+        self.inputs
+            .iter()
+            .position(|b| b.is_none())
+            .map(|i| (Signal::from_input(i as u32), false))
+    }
+
+    /// Trace an objective back to an unassigned input
+    fn backtrace(&self, mut s: Signal, mut v: bool) -> Option<(usize, bool)> {
+        loop {
+            if s.is_input() {
+                return Some((s.input() as usize, v ^ s.is_inverted()));
+            }
+            if !s.is_var() {
+                return None;
+            }
+            v ^= s.is_inverted();
+            let i = s.var() as usize;
+            let g = self.aig.gate(i);
+            let pins = self.pin_values(&self.good, i);
+            // Prefer an input value that sets the output, then one that keeps it possible
+            let mut choice = None;
+            let mut fallback = None;
+            'search: for (j, d) in g.dependencies().iter().enumerate() {
+                if pins[j].is_some() {
+                    continue;
+                }
+                for b in [false, true] {
+                    let mut p = pins.clone();
+                    p[j] = Some(b);
+                    match eval_gate(g, &p) {
+                        Some(o) if o == v => {
+                            choice = Some((*d, b));
+                            break 'search;
+                        }
+                        None if fallback.is_none() => fallback = Some((*d, b)),
+                        _ => (),
+                    }
+                }
+            }
+            let (d, b) = choice.or(fallback)?;
+            s = d;
+            v = b;
+        }
+    }
+
+    /// Search for a test, with the inputs already assigned kept fixed
+    ///
+    /// Returns whether a test was found, or None if the backtrack limit was reached. On success,
+    /// the inputs that are not required by the test are left unassigned.
+    fn run(&mut self, backtrack_limit: usize) -> Option<bool> {
+        // Decisions, with whether the other value has been tried
+        let mut stack: Vec<(usize, bool)> = Vec::new();
+        let mut nb_backtracks = 0;
+        self.simulate();
+        loop {
+            if self.detected() {
+                return Some(true);
+            }
+            let decision = self.objective().and_then(|(s, v)| {
+                self.backtrace(s, v)
+                    .or_else(|| self.free_input().and_then(|(s, v)| self.backtrace(s, v)))
+            });
+            if let Some((i, b)) = decision {
+                stack.push((i, false));
+                self.set_input(i, Some(b));
+                continue;
+            }
+            // Backtrack to the last decision whose alternative has not been tried
+            loop {
+                let Some((i, flipped)) = stack.pop() else {
+                    return Some(false);
+                };
+                if flipped {
+                    self.set_input(i, None);
+                    continue;
+                }
+                nb_backtracks += 1;
+                if nb_backtracks > backtrack_limit {
+                    return None;
+                }
+                stack.push((i, true));
+                self.set_input(i, self.inputs[i].map(|b| !b));
+                break;
+            }
+        }
+    }
+}
+
+impl<'a> Podem<'a> {
+    /// Setup the search for a stuck-at fault, or None for other fault types
+    fn new(
+        aig: &'a Network,
+        fanouts: &'a FanoutView,
+        fault: Fault,
+        assignment: &[Option<bool>],
+    ) -> Option<Podem<'a>> {
+        assert!(aig.is_comb());
+        assert_eq!(assignment.len(), aig.nb_inputs());
+        let (gate, input, value) = match fault {
+            Fault::OutputStuckAtFault { gate, value } => (gate, None, value),
+            Fault::InputStuckAtFault { gate, input, value } => (gate, Some(input), value),
+            _ => return None,
+        };
+        let mut nb_outputs = vec![0; aig.nb_nodes()];
+        for o in 0..aig.nb_outputs() {
+            let s = aig.output(o);
+            if s.is_var() {
+                nb_outputs[s.var() as usize] += 1;
+            }
+        }
+        Some(Podem {
+            aig,
+            fanouts,
+            gate,
+            input,
+            value,
+            inputs: assignment.to_vec(),
+            good: vec![None; aig.nb_nodes()],
+            faulty: vec![None; aig.nb_nodes()],
+            errors: BTreeSet::new(),
+            nb_detecting: 0,
+            nb_outputs,
+            queue: BinaryHeap::new(),
+            queued: vec![false; aig.nb_nodes()],
+        })
+    }
+}
+
+/// Find a test pattern for a stuck-at fault with the PODEM algorithm
+///
+/// Decisions are only made on the inputs of the network, and the search gives up after the
+/// given number of backtracks. Other fault types are not supported and are always aborted.
+/// The fanout view of the network is shared between calls.
+pub fn podem(
+    aig: &Network,
+    fanouts: &FanoutView,
+    fault: Fault,
+    backtrack_limit: usize,
+) -> PodemResult {
+    let Some(mut p) = Podem::new(aig, fanouts, fault, &vec![None; aig.nb_inputs()]) else {
+        return PodemResult::Aborted;
+    };
+    match p.run(backtrack_limit) {
+        Some(true) => PodemResult::Detected(p.inputs.iter().map(|b| b.unwrap_or(false)).collect()),
+        Some(false) => PodemResult::Untestable,
+        None => PodemResult::Aborted,
+    }
+}
+
+/// Extend a partial assignment of the inputs to also detect a stuck-at fault
+///
+/// The inputs already assigned are kept, and the inputs that are not required stay unassigned.
+/// Returns None if no such test was found within the backtrack limit.
+pub(crate) fn podem_extend(
+    aig: &Network,
+    fanouts: &FanoutView,
+    fault: Fault,
+    backtrack_limit: usize,
+    assignment: &[Option<bool>],
+) -> Option<Vec<Option<bool>>> {
+    let mut p = Podem::new(aig, fanouts, fault, assignment)?;
+    match p.run(backtrack_limit) {
+        Some(true) => Some(p.inputs),
+        _ => None,
+    }
+}
+
+/// Relax a test pattern for a stuck-at fault to a test cube
+///
+/// Inputs are unassigned one at a time, as long as three-valued simulation shows that the
+/// fault is still detected. Other fault types keep the full pattern.
+pub(crate) fn relax_pattern(
+    aig: &Network,
+    fanouts: &FanoutView,
+    fault: Fault,
+    pattern: &[bool],
+) -> Vec<Option<bool>> {
+    let assignment: Vec<Option<bool>> = pattern.iter().map(|b| Some(*b)).collect();
+    let Some(mut p) = Podem::new(aig, fanouts, fault, &assignment) else {
+        return assignment;
+    };
+    p.simulate();
+    for i in 0..aig.nb_inputs() {
+        let b = p.inputs[i];
+        p.set_input(i, None);
+        if !p.detected() {
+            p.set_input(i, b);
+        }
+    }
+    p.inputs
+}
+
+#[cfg(test)]
+mod tests {
+    use volute::Lut;
+
+    use super::{podem, podem_extend, relax_pattern, PodemResult};
+    use crate::atpg::find_pattern_detecting_fault;
+    use crate::network::NaryType;
+    use crate::sim::{detects_faults, Fault};
+    use crate::utils::FanoutView;
+    use crate::{Gate, Network};
+
+    /// Check PODEM against SAT on all stuck-at faults of a network
+    fn check_against_sat(aig: &Network) {
+        let fanouts = FanoutView::new(aig);
+        for f in Fault::all(aig) {
+            match podem(aig, &fanouts, f, 1000) {
+                PodemResult::Detected(p) => {
+                    assert!(detects_faults(aig, &p, &vec![f])[0], "{:?}", f);
+                }
+                PodemResult::Untestable => {
+                    assert!(find_pattern_detecting_fault(aig, f).is_none(), "{:?}", f);
+                }
+                PodemResult::Aborted => panic!("PODEM aborted on {:?}", f),
+            }
+        }
+    }
+
+    #[test]
+    fn test_podem_gate_types() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let i3 = aig.add_input();
+        let a = aig.and(i0, !i1);
+        let x = aig.xor(i2, i3);
+        let m = aig.add(Gate::mux(i1, a, x));
+        let j = aig.add(Gate::maj(i0, !x, i3));
+        let l = aig.add(Gate::lut(
+            &[m, j, i2],
+            Lut::nth_var(3, 0).xor(&Lut::nth_var(3, 2)),
+        ));
+        let o = aig.add(Gate::Nary(vec![l, !a, i3].into(), NaryType::Nor));
+        aig.add_output(o);
+        aig.add_output(j);
+        check_against_sat(&aig);
+    }
+
+    #[test]
+    fn test_podem_redundant() {
+        // x & !x is constant: its faults stuck at 0 are untestable
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let a = aig.add(Gate::and(i0, i1));
+        let b = aig.add(Gate::and(a, !i0));
+        let c = aig.add(Gate::Nary(vec![b, i1].into(), NaryType::Or));
+        aig.add_output(c);
+        let f = Fault::OutputStuckAtFault {
+            gate: 1,
+            value: false,
+        };
+        let fanouts = FanoutView::new(&aig);
+        assert_eq!(podem(&aig, &fanouts, f, 1000), PodemResult::Untestable);
+        check_against_sat(&aig);
+    }
+
+    #[test]
+    fn test_podem_abort() {
+        // Reconvergent xors require backtracking on an untestable fault
+        let mut aig = Network::new();
+        let inputs: Vec<_> = (0..6).map(|_| aig.add_input()).collect();
+        let x = aig.add(Gate::xorn(&inputs));
+        let y = aig.add(Gate::xorn(&inputs));
+        let z = aig.add(Gate::xor(x, y));
+        aig.add_output(z);
+        let f = Fault::OutputStuckAtFault {
+            gate: 2,
+            value: false,
+        };
+        let fanouts = FanoutView::new(&aig);
+        assert_eq!(podem(&aig, &fanouts, f, 2), PodemResult::Aborted);
+        assert_eq!(podem(&aig, &fanouts, f, 1000), PodemResult::Untestable);
+    }
+
+    #[test]
+    fn test_podem_cube() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let i3 = aig.add_input();
+        let a = aig.add(Gate::and(i0, i1));
+        let b = aig.add(Gate::and(i2, i3));
+        aig.add_output(a);
+        aig.add_output(b);
+
+        // Only the inputs of the And gate are required to detect its fault
+        let fa = Fault::OutputStuckAtFault {
+            gate: 0,
+            value: false,
+        };
+        let fanouts = FanoutView::new(&aig);
+        let cube = relax_pattern(&aig, &fanouts, fa, &[true, true, false, true]);
+        assert_eq!(cube, vec![Some(true), Some(true), None, None]);
+
+        // The cube can be extended to detect a fault of the other gate
+        let fb = Fault::OutputStuckAtFault {
+            gate: 1,
+            value: false,
+        };
+        let cube = podem_extend(&aig, &fanouts, fb, 10, &cube).unwrap();
+        assert_eq!(cube, vec![Some(true); 4]);
+        let pattern: Vec<bool> = cube.iter().map(|b| b.unwrap()).collect();
+        assert_eq!(
+            detects_faults(&aig, &pattern, &vec![fa, fb]),
+            vec![true, true]
+        );
+
+        // But not to detect a conflicting fault
+        let fc = Fault::OutputStuckAtFault {
+            gate: 0,
+            value: true,
+        };
+        assert_eq!(podem_extend(&aig, &fanouts, fc, 10, &cube), None);
+    }
+}
@@ -0,0 +1,654 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::iter::zip;
+
+use kdam::{tqdm, BarExt};
+
+use crate::sim::{pack_patterns, Fault, ParallelFaultSimulator};
+use crate::utils::FanoutView;
+use crate::Network;
+
+use super::{
+    expose_dff, find_pattern_detecting_fault, podem, AtpgOptions, LaunchMode, PodemResult,
+    TwoFrameNetwork,
+};
+
+/// Status of a fault after analysis of the test patterns
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultStatus {
+    /// The fault is detected by a test pattern
+    Detected,
+    /// No test pattern can detect the fault: it is redundant
+    Untestable,
+    /// The fault is not detected, and test generation gave up before proving it untestable
+    Aborted,
+    /// The fault is not detected, but could be
+    Undetected,
+    /// The fault is not detected, and was not analyzed further
+    Unclassified,
+}
+
+impl fmt::Display for FaultStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultStatus::Detected => write!(f, "detected"),
+            FaultStatus::Untestable => write!(f, "untestable"),
+            FaultStatus::Aborted => write!(f, "aborted"),
+            FaultStatus::Undetected => write!(f, "undetected"),
+            FaultStatus::Unclassified => write!(f, "unclassified"),
+        }
+    }
+}
+
+/// Analysis result for a single fault
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FaultReport {
+    /// The fault
+    pub fault: Fault,
+    /// Type of the gate where the fault is located, or None for bridging faults
+    pub gate_type: Option<&'static str>,
+    /// Status of the fault
+    pub status: FaultStatus,
+    /// Index of the first pattern detecting the fault
+    pub first_pattern: Option<usize>,
+}
+
+impl FaultReport {
+    /// Location of the fault, as the gate and the input if any
+    pub fn location(&self) -> String {
+        match self.fault {
+            Fault::OutputStuckAtFault { gate, .. }
+            | Fault::TransitionFault { gate, .. }
+            | Fault::LutBitFlipFault { gate, .. }
+            | Fault::DefectFault { gate, .. } => format!("x{}", gate),
+            Fault::InputStuckAtFault { gate, input, .. } => format!("x{}:{}", gate, input),
+            Fault::BridgingFault { a, b, .. } => format!("x{}/x{}", a, b),
+        }
+    }
+
+    /// Fault model, such as stuck-at-0 or lut-bit-flip
+    pub fn fault_model(&self) -> String {
+        match self.fault {
+            Fault::OutputStuckAtFault { value, .. } | Fault::InputStuckAtFault { value, .. } => {
+                format!("stuck-at-{}", i32::from(value))
+            }
+            Fault::TransitionFault { value, .. } => {
+                if value {
+                    "slow-to-rise".to_string()
+                } else {
+                    "slow-to-fall".to_string()
+                }
+            }
+            Fault::BridgingFault { kind, .. } => format!("bridge-{}", kind),
+            Fault::LutBitFlipFault { .. } => "lut-bit-flip".to_string(),
+            Fault::DefectFault { .. } => "defect".to_string(),
+        }
+    }
+
+    /// Type of the fault, as the fault model with the bit or table of cell-internal faults
+    pub fn fault_type(&self) -> String {
+        match self.fault {
+            Fault::LutBitFlipFault { bit, .. } => format!("{}-{}", self.fault_model(), bit),
+            Fault::DefectFault { table, .. } => format!("{}-{:x}", self.fault_model(), table),
+            _ => self.fault_model(),
+        }
+    }
+}
+
+/// Result of the analysis of test patterns, with the status of each fault
+#[derive(Clone, Debug, Default)]
+pub struct AtpgReport {
+    /// Analysis result for each fault
+    pub faults: Vec<FaultReport>,
+}
+
+impl AtpgReport {
+    /// Number of faults
+    pub fn nb_faults(&self) -> usize {
+        self.faults.len()
+    }
+
+    /// Number of faults with the given status
+    pub fn nb_with_status(&self, status: FaultStatus) -> usize {
+        self.faults.iter().filter(|f| f.status == status).count()
+    }
+
+    /// Number of detected and total faults, grouped by a key
+    fn coverage_by<F: Fn(&FaultReport) -> Option<String>>(
+        &self,
+        key: F,
+    ) -> Vec<(String, usize, usize)> {
+        let mut groups: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for f in &self.faults {
+            let Some(k) = key(f) else {
+                continue;
+            };
+            let e = groups.entry(k).or_default();
+            if f.status == FaultStatus::Detected {
+                e.0 += 1;
+            }
+            e.1 += 1;
+        }
+        groups.into_iter().map(|(k, (d, t))| (k, d, t)).collect()
+    }
+
+    /// Number of detected and total faults for each gate type; bridging faults are not included
+    pub fn coverage_by_gate_type(&self) -> Vec<(String, usize, usize)> {
+        self.coverage_by(|f| f.gate_type.map(|t| t.to_string()))
+    }
+
+    /// Number of detected and total faults for each fault model
+    pub fn coverage_by_fault_type(&self) -> Vec<(String, usize, usize)> {
+        self.coverage_by(|f| Some(f.fault_model()))
+    }
+
+    /// Write the fault list in CSV format
+    pub fn write_csv<W: Write>(&self, w: &mut W) {
+        writeln!(w, "location,gate_type,fault_type,status,first_pattern").unwrap();
+        for f in &self.faults {
+            writeln!(
+                w,
+                "{},{},{},{},{}",
+                f.location(),
+                f.gate_type.unwrap_or(""),
+                f.fault_type(),
+                f.status,
+                f.first_pattern.map(|p| p.to_string()).unwrap_or_default()
+            )
+            .unwrap();
+        }
+    }
+
+    /// Write the fault list and the coverage in JSON format
+    pub fn write_json<W: Write>(&self, w: &mut W) {
+        let coverage = |v: Vec<(String, usize, usize)>| -> String {
+            v.iter()
+                .map(|(k, d, t)| format!("\"{}\": {{\"detected\": {}, \"total\": {}}}", k, d, t))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        writeln!(w, "{{").unwrap();
+        writeln!(
+            w,
+            "  \"coverage_by_gate_type\": {{{}}},",
+            coverage(self.coverage_by_gate_type())
+        )
+        .unwrap();
+        writeln!(
+            w,
+            "  \"coverage_by_fault_type\": {{{}}},",
+            coverage(self.coverage_by_fault_type())
+        )
+        .unwrap();
+        writeln!(w, "  \"faults\": [").unwrap();
+        for (i, f) in self.faults.iter().enumerate() {
+            let gate_type = match f.gate_type {
+                Some(t) => format!("\"{}\"", t),
+                None => "null".to_string(),
+            };
+            let first_pattern = match f.first_pattern {
+                Some(p) => p.to_string(),
+                None => "null".to_string(),
+            };
+            let sep = if i + 1 == self.faults.len() { "" } else { "," };
+            writeln!(
+                w,
+                "    {{\"location\": \"{}\", \"gate_type\": {}, \"fault_type\": \"{}\", \"status\": \"{}\", \"first_pattern\": {}}}{}",
+                f.location(),
+                gate_type,
+                f.fault_type(),
+                f.status,
+                first_pattern,
+                sep
+            )
+            .unwrap();
+        }
+        writeln!(w, "  ]").unwrap();
+        writeln!(w, "}}").unwrap();
+    }
+}
+
+impl fmt::Display for AtpgReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Faults: {} detected, {} untestable, {} aborted, {} undetected, {} unclassified",
+            self.nb_with_status(FaultStatus::Detected),
+            self.nb_with_status(FaultStatus::Untestable),
+            self.nb_with_status(FaultStatus::Aborted),
+            self.nb_with_status(FaultStatus::Undetected),
+            self.nb_with_status(FaultStatus::Unclassified),
+        )?;
+        for (name, coverage) in [
+            ("gate type", self.coverage_by_gate_type()),
+            ("fault type", self.coverage_by_fault_type()),
+        ] {
+            writeln!(f, "Coverage by {}:", name)?;
+            for (k, d, t) in coverage {
+                writeln!(
+                    f,
+                    "  {}: {}/{} ({:.2}%)",
+                    k,
+                    d,
+                    t,
+                    100.0 * (d as f64) / (t as f64)
+                )?;
+            }
+        }
+        fmt::Result::Ok(())
+    }
+}
+
+/// Analyze which faults are detected by a set of patterns, packed 64 at a time
+///
+/// Returns the index of the first pattern detecting each fault.
+fn first_detecting_patterns(
+    aig: &Network,
+    patterns: &[&Vec<bool>],
+    faults: &[Fault],
+    nb_threads: usize,
+) -> Vec<Option<usize>> {
+    let mut sim = ParallelFaultSimulator::new(aig, nb_threads);
+    let mut progress = tqdm!(total = patterns.len());
+    let mut ret = vec![None; faults.len()];
+    let (packed, masks) = pack_patterns(patterns, aig.nb_inputs());
+    for (w, (word, mask)) in zip(packed, masks).enumerate() {
+        let d = sim
+            .detects_faults_multi(std::slice::from_ref(&word), faults)
+            .pop()
+            .unwrap();
+        for (r, n) in zip(ret.iter_mut(), d) {
+            if r.is_none() && n & mask != 0 {
+                *r = Some(64 * w + (n & mask).trailing_zeros() as usize);
+            }
+        }
+        progress.update(mask.count_ones() as usize).unwrap();
+    }
+    println!();
+    ret
+}
+
+/// Classify a fault that is not detected by the patterns
+///
+/// PODEM is used first for stuck-at faults, and a SAT solver is used for the faults it aborts if
+/// requested. Other faults are only analyzed with the SAT solver, and are unclassified without it.
+fn classify_undetected(
+    aig: &Network,
+    two_frame: &TwoFrameNetwork,
+    fanouts: &FanoutView,
+    fault: Fault,
+    sat_fallback: bool,
+    podem_backtrack_limit: usize,
+) -> FaultStatus {
+    if matches!(
+        fault,
+        Fault::OutputStuckAtFault { .. } | Fault::InputStuckAtFault { .. }
+    ) {
+        match podem(aig, fanouts, fault, podem_backtrack_limit) {
+            PodemResult::Detected(_) => return FaultStatus::Undetected,
+            PodemResult::Untestable => return FaultStatus::Untestable,
+            PodemResult::Aborted => (),
+        }
+        if !sat_fallback {
+            return FaultStatus::Aborted;
+        }
+    } else if !sat_fallback {
+        return FaultStatus::Unclassified;
+    }
+    let pattern = match fault {
+        Fault::TransitionFault { .. } => two_frame.find_pattern_detecting_fault(fault),
+        _ => find_pattern_detecting_fault(aig, fault),
+    };
+    if pattern.is_some() {
+        FaultStatus::Undetected
+    } else {
+        FaultStatus::Untestable
+    }
+}
+
+/// Analyze combinatorial test patterns
+///
+/// This will show the coverage obtained by these test patterns, and return the status of each
+/// fault. Flip-flops of a sequential network are exposed as for [`expose_dff`], and the patterns
+/// give the values of its inputs then of its flip-flops.
+/// Stuck-at, bridging and cell-internal faults are analyzed on the first timestep of each
+/// pattern. Patterns with at least two timesteps are also analyzed for transition faults,
+/// launched between the first two timesteps.
+/// Stuck-at faults that are not detected are classified as untestable or undetected with PODEM,
+/// within the backtrack limit of the options, and with a SAT solver for the faults it aborts if
+/// `sat_fallback` is set. Other faults that are not detected are only classified with the SAT
+/// solver.
+/// Transition faults are classified on the two-frame unrolling of the original network, with the
+/// launch mode used to generate the patterns.
+/// The options give the bridging and cell-internal faults to analyze, and the number of threads
+/// for fault simulation. Unless redundant faults are requested, stuck-at faults are reported once
+/// per equivalence class. The other options only apply to test pattern generation.
+pub fn report_comb_test_patterns(
+    aig: &Network,
+    launch_mode: LaunchMode,
+    patterns: Vec<Vec<Vec<bool>>>,
+    sat_fallback: bool,
+    options: &AtpgOptions,
+) -> AtpgReport {
+    let two_frame = TwoFrameNetwork::from(aig, launch_mode);
+    let aig = &expose_dff(aig);
+    let mut faults = Fault::all(aig);
+    faults.sort();
+    let classes = Fault::equivalence_classes(aig);
+
+    println!(
+        "Analyzing network with {} inputs, {} outputs, {} gates, {} uncollapsed faults, {} collapsed faults",
+        aig.nb_inputs(),
+        aig.nb_outputs(),
+        aig.nb_nodes(),
+        faults.len(),
+        classes.len(),
+    );
+
+    let first_timestep: Vec<&Vec<bool>> = patterns.iter().map(|p| &p[0]).collect();
+    let first_patterns =
+        first_detecting_patterns(aig, &first_timestep, &faults, options.nb_threads);
+    let nb_detected = first_patterns.iter().filter(|p| p.is_some()).count();
+
+    // Equivalent faults are detected by the same patterns
+    let nb_detected_classes = classes
+        .iter()
+        .filter(|c| first_patterns[faults.binary_search(&c[0]).unwrap()].is_some())
+        .count();
+    println!(
+        "Analyzed {} patterns, detecting {}/{} uncollapsed faults ({:.2}% coverage)",
+        patterns.len(),
+        nb_detected,
+        faults.len(),
+        100.0 * (nb_detected as f64) / (faults.len() as f64)
+    );
+    println!(
+        "Analyzed {} patterns, detecting {}/{} collapsed faults ({:.2}% coverage)",
+        patterns.len(),
+        nb_detected_classes,
+        classes.len(),
+        100.0 * (nb_detected_classes as f64) / (classes.len() as f64)
+    );
+    let representatives = Fault::all_collapsed(aig);
+    let mut analyzed: Vec<(Fault, Option<usize>)> = zip(faults, first_patterns)
+        .filter(|(f, _)| options.with_redundant_faults || representatives.binary_search(f).is_ok())
+        .collect();
+
+    let bridging_faults = Fault::all_bridging(&options.bridges);
+    if !bridging_faults.is_empty() {
+        let first_patterns =
+            first_detecting_patterns(aig, &first_timestep, &bridging_faults, options.nb_threads);
+        let nb_detected = first_patterns.iter().filter(|p| p.is_some()).count();
+        println!(
+            "Analyzed {} patterns, detecting {}/{} bridging faults ({:.2}% coverage)",
+            patterns.len(),
+            nb_detected,
+            bridging_faults.len(),
+            100.0 * (nb_detected as f64) / (bridging_faults.len() as f64)
+        );
+        analyzed.extend(zip(bridging_faults, first_patterns));
+    }
+
+    if !options.cell_faults.is_empty() {
+        let first_patterns = first_detecting_patterns(
+            aig,
+            &first_timestep,
+            &options.cell_faults,
+            options.nb_threads,
+        );
+        let nb_detected = first_patterns.iter().filter(|p| p.is_some()).count();
+        println!(
+            "Analyzed {} patterns, detecting {}/{} cell-internal faults ({:.2}% coverage)",
+            patterns.len(),
+            nb_detected,
+            options.cell_faults.len(),
+            100.0 * (nb_detected as f64) / (options.cell_faults.len() as f64)
+        );
+        analyzed.extend(zip(options.cell_faults.iter().copied(), first_patterns));
+    }
+
+    let launch_patterns: Vec<usize> = (0..patterns.len())
+        .filter(|i| patterns[*i].len() >= 2)
+        .collect();
+    if !launch_patterns.is_empty() {
+        let transition_faults = Fault::all_transition(aig);
+        let mut first_patterns = vec![None; transition_faults.len()];
+        // The exposed network has no flip-flop: both timesteps are given by the patterns
+        let unrolled = TwoFrameNetwork::from(aig, LaunchMode::LaunchOnCapture);
+        let mut sim = ParallelFaultSimulator::new(unrolled.network(), options.nb_threads);
+        let launch: Vec<&Vec<bool>> = launch_patterns.iter().map(|i| &patterns[*i][0]).collect();
+        let capture: Vec<&Vec<bool>> = launch_patterns.iter().map(|i| &patterns[*i][1]).collect();
+        let (frames1, masks) = pack_patterns(&launch, aig.nb_inputs());
+        let (frames2, _) = pack_patterns(&capture, aig.nb_inputs());
+        for (w, (mut pattern, frame2)) in zip(frames1, frames2).enumerate() {
+            pattern.extend(frame2);
+            let det = unrolled.detects_faults_multi(&mut sim, &pattern, &transition_faults);
+            for (r, n) in zip(first_patterns.iter_mut(), det) {
+                let n = n & masks[w];
+                if r.is_none() && n != 0 {
+                    *r = Some(launch_patterns[64 * w + n.trailing_zeros() as usize]);
+                }
+            }
+        }
+        let nb_detected = first_patterns.iter().filter(|p| p.is_some()).count();
+        println!(
+            "Analyzed {} two-frame patterns, detecting {}/{} transition faults ({:.2}% coverage)",
+            launch_patterns.len(),
+            nb_detected,
+            transition_faults.len(),
+            100.0 * (nb_detected as f64) / (transition_faults.len() as f64)
+        );
+        analyzed.extend(zip(transition_faults, first_patterns));
+    }
+
+    let fanouts = FanoutView::new(aig);
+    let faults = analyzed
+        .into_iter()
+        .map(|(fault, first_pattern)| {
+            let status = if first_pattern.is_some() {
+                FaultStatus::Detected
+            } else {
+                classify_undetected(
+                    aig,
+                    &two_frame,
+                    &fanouts,
+                    fault,
+                    sat_fallback,
+                    options.podem_backtrack_limit,
+                )
+            };
+            let gate_type = match fault {
+                Fault::BridgingFault { .. } => None,
+                _ => Some(aig.gate(fault.gate()).type_name()),
+            };
+            FaultReport {
+                fault,
+                gate_type,
+                status,
+                first_pattern,
+            }
+        })
+        .collect();
+    let report = AtpgReport { faults };
+    print!("{}", report);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use volute::Lut3;
+
+    use super::{report_comb_test_patterns, FaultStatus};
+    use crate::atpg::{AtpgOptions, LaunchMode};
+    use crate::sim::Fault;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_report() {
+        // b is redundant: the output is i0 & i1
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let a = aig.add(Gate::and(i0, i1));
+        let b = aig.add(Gate::and(a, i0));
+        aig.add_output(b);
+        let patterns = vec![
+            vec![vec![true, true]],
+            vec![vec![false, true]],
+            vec![vec![true, false], vec![true, true]],
+        ];
+        let report = report_comb_test_patterns(
+            &aig,
+            LaunchMode::LaunchOnCapture,
+            patterns,
+            true,
+            &AtpgOptions {
+                with_redundant_faults: true,
+                bridges: vec![(0, 1)],
+                ..Default::default()
+            },
+        );
+        let status = |f: Fault| report.faults.iter().find(|r| r.fault == f).unwrap().clone();
+
+        let r = status(Fault::OutputStuckAtFault {
+            gate: 1,
+            value: false,
+        });
+        assert_eq!(r.status, FaultStatus::Detected);
+        assert_eq!(r.first_pattern, Some(0));
+        assert_eq!(r.gate_type, Some("And"));
+        assert_eq!(r.fault_type(), "stuck-at-0");
+        let r = status(Fault::OutputStuckAtFault {
+            gate: 1,
+            value: true,
+        });
+        assert_eq!(r.first_pattern, Some(1));
+        let r = status(Fault::TransitionFault {
+            gate: 1,
+            value: true,
+        });
+        assert_eq!(r.status, FaultStatus::Detected);
+        assert_eq!(r.first_pattern, Some(2));
+        let r = status(Fault::TransitionFault {
+            gate: 1,
+            value: false,
+        });
+        assert_eq!(r.status, FaultStatus::Undetected);
+        assert_eq!(r.location(), "x1");
+
+        // The input of b driven by i0 is redundant when stuck at 1
+        let input = aig.gate(1).dependencies().iter().position(|s| *s == i0);
+        let r = status(Fault::InputStuckAtFault {
+            gate: 1,
+            input: input.unwrap(),
+            value: true,
+        });
+        assert_eq!(r.status, FaultStatus::Untestable);
+
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv);
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), report.nb_faults() + 1);
+        assert!(csv.contains("x1,And,stuck-at-0,detected,0"));
+        let mut json = Vec::new();
+        report.write_json(&mut json);
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains("\"And\": {\"detected\""));
+        assert!(json.contains("\"gate_type\": null"));
+
+        // Without SAT, only stuck-at faults are classified
+        let patterns = vec![
+            vec![vec![true, true]],
+            vec![vec![true, false], vec![true, true]],
+        ];
+        let report = report_comb_test_patterns(
+            &aig,
+            LaunchMode::LaunchOnCapture,
+            patterns,
+            false,
+            &AtpgOptions {
+                with_redundant_faults: true,
+                bridges: vec![(0, 1)],
+                ..Default::default()
+            },
+        );
+        for r in &report.faults {
+            match r.fault {
+                Fault::OutputStuckAtFault { .. } | Fault::InputStuckAtFault { .. } => {
+                    assert_ne!(r.status, FaultStatus::Unclassified)
+                }
+                _ => assert!(matches!(
+                    r.status,
+                    FaultStatus::Detected | FaultStatus::Unclassified
+                )),
+            }
+        }
+        assert!(report.nb_with_status(FaultStatus::Unclassified) > 0);
+    }
+
+    #[test]
+    fn test_report_fault_models() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let l = aig.add(Gate::lut(&[i0, i1, i2], Lut3::majority().into()));
+        aig.add_output(l);
+        let patterns = vec![
+            vec![vec![true, true, false]],
+            vec![vec![false, false, true]],
+        ];
+        let report = report_comb_test_patterns(
+            &aig,
+            LaunchMode::LaunchOnCapture,
+            patterns,
+            true,
+            &AtpgOptions {
+                cell_faults: Fault::all_lut_bit_flips(&aig),
+                ..Default::default()
+            },
+        );
+        // Each bit-flip keeps its bit in the fault list, but they are covered as a single model
+        let r = report
+            .faults
+            .iter()
+            .find(|r| r.fault == Fault::LutBitFlipFault { gate: 0, bit: 3 })
+            .unwrap();
+        assert_eq!(r.fault_type(), "lut-bit-flip-3");
+        assert_eq!(r.fault_model(), "lut-bit-flip");
+        let coverage = report.coverage_by_fault_type();
+        assert_eq!(
+            coverage.iter().find(|c| c.0 == "lut-bit-flip"),
+            Some(&("lut-bit-flip".to_string(), 2, 8))
+        );
+        assert!(coverage.iter().all(|c| !c.0.starts_with("lut-bit-flip-")));
+    }
+
+    #[test]
+    fn test_report_launch_on_capture() {
+        // The flip-flop always captures 0, so that x can only rise with launch-on-shift
+        let mut aig = Network::new();
+        let i = aig.add_input();
+        let q = aig.add(Gate::dff(Signal::zero(), Signal::one(), Signal::zero()));
+        let x = aig.add(Gate::and(i, q));
+        aig.add_output(x);
+        let slow_to_rise = Fault::TransitionFault {
+            gate: x.var() as usize,
+            value: true,
+        };
+        let status = |mode: LaunchMode| {
+            let patterns = vec![vec![vec![false, false], vec![false, false]]];
+            let report =
+                report_comb_test_patterns(&aig, mode, patterns, true, &AtpgOptions::default());
+            report
+                .faults
+                .iter()
+                .find(|r| r.fault == slow_to_rise)
+                .unwrap()
+                .status
+        };
+        assert_eq!(status(LaunchMode::LaunchOnCapture), FaultStatus::Untestable);
+        assert_eq!(status(LaunchMode::LaunchOnShift), FaultStatus::Undetected);
+    }
+}
@@ -0,0 +1,278 @@
+use std::io::Write;
+
+use crate::network::stats;
+use crate::{Gate, Network, Signal};
+
+/// Order of the flip-flops along the scan chains
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ScanOrder {
+    /// Order of the flip-flops in the network
+    #[default]
+    Index,
+    /// Alphabetical order of the flip-flop names, given for each flip-flop in network order
+    Name(Vec<String>),
+}
+
+/// Scan chains inserted in a network
+///
+/// Each chain goes from its scan-in input through the flip-flops in order, and the last
+/// flip-flop is its scan-out output. When scan enable is set, each flip-flop loads the value of
+/// the previous one, regardless of its enable and reset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanChains {
+    /// Flip-flops of each chain, from scan-in to scan-out, given by their gate index
+    pub chains: Vec<Vec<usize>>,
+    /// Input index of the scan enable
+    pub scan_enable: usize,
+    /// Input index of the scan-in of each chain
+    pub scan_in: Vec<usize>,
+    /// Output index of the scan-out of each chain
+    pub scan_out: Vec<usize>,
+}
+
+/// Insert mux-scan flip-flops, stitched into balanced scan chains
+///
+/// The scan enable, then the scan-in of each chain, are added after the original inputs. The
+/// scan-out of each chain is added after the original outputs. Gate indices are unchanged, and
+/// the scan logic is added after the original gates.
+pub fn insert_scan_chains(
+    aig: &Network,
+    nb_chains: usize,
+    order: &ScanOrder,
+) -> (Network, ScanChains) {
+    assert!(nb_chains >= 1);
+    let mut dffs = stats::dff_gates(aig);
+    if let ScanOrder::Name(names) = order {
+        assert_eq!(names.len(), dffs.len(), "Expected one name per flip-flop");
+        let mut perm: Vec<usize> = (0..dffs.len()).collect();
+        perm.sort_by(|a, b| names[*a].cmp(&names[*b]));
+        dffs = perm.iter().map(|i| dffs[*i]).collect();
+    }
+    let nb_chains = nb_chains.min(dffs.len()).max(1);
+
+    // Balanced chains, whose lengths differ by at most one
+    let mut chains = Vec::new();
+    let mut begin = 0;
+    for k in 0..nb_chains {
+        let len = dffs.len() / nb_chains + usize::from(k < dffs.len() % nb_chains);
+        chains.push(dffs[begin..begin + len].to_vec());
+        begin += len;
+    }
+
+    let mut ret = aig.clone();
+    let scan_enable = ret.nb_inputs();
+    let se = ret.add_input();
+    let mut scan_in = Vec::new();
+    let mut scan_out = Vec::new();
+    for chain in &chains {
+        scan_in.push(ret.nb_inputs());
+        let mut prev = ret.add_input();
+        for ff in chain {
+            let Gate::Dff([d, en, res]) = *aig.gate(*ff) else {
+                unreachable!();
+            };
+            let d = ret.add(Gate::mux(se, prev, d));
+            let en = if en == Signal::one() {
+                en
+            } else {
+                !ret.add(Gate::and(!se, !en))
+            };
+            let res = if res == Signal::zero() {
+                res
+            } else {
+                ret.add(Gate::and(!se, res))
+            };
+            ret.replace(*ff, Gate::dff(d, en, res));
+            prev = aig.node(*ff);
+        }
+        scan_out.push(ret.nb_outputs());
+        ret.add_output(prev);
+    }
+    ret.check();
+    (
+        ret,
+        ScanChains {
+            chains,
+            scan_enable,
+            scan_in,
+            scan_out,
+        },
+    )
+}
+
+impl ScanChains {
+    /// Length of the longest chain, which is the number of shift cycles
+    pub fn max_length(&self) -> usize {
+        self.chains.iter().map(|c| c.len()).max().unwrap_or(0)
+    }
+
+    /// Write the description of the chains
+    ///
+    /// Each chain is described on one line, with its scan-in, its scan-out and its flip-flops in order.
+    /// Flip-flops are written by name if the names are given, one per flip-flop in network order.
+    pub fn write<W: Write>(&self, w: &mut W, aig: &Network, names: Option<&[String]>) {
+        let dffs = stats::dff_gates(aig);
+        if let Some(names) = names {
+            assert_eq!(names.len(), dffs.len(), "Expected one name per flip-flop");
+        }
+        let flop_name = |i: usize| match names {
+            Some(names) => names[dffs.iter().position(|d| *d == i).unwrap()].clone(),
+            None => aig.node(i).to_string(),
+        };
+        writeln!(w, "# Scan chains").unwrap();
+        writeln!(w, "# Generated by quaigh").unwrap();
+        writeln!(
+            w,
+            "scan_enable {}",
+            Signal::from_input(self.scan_enable as u32)
+        )
+        .unwrap();
+        for (k, chain) in self.chains.iter().enumerate() {
+            let flops: Vec<String> = chain.iter().map(|i| flop_name(*i)).collect();
+            writeln!(
+                w,
+                "chain {} scan_in {} scan_out o{} length {}: {}",
+                k,
+                Signal::from_input(self.scan_in[k] as u32),
+                self.scan_out[k],
+                chain.len(),
+                flops.join(" ")
+            )
+            .unwrap();
+        }
+    }
+
+    /// Translate patterns of the network with exposed flip-flops into serial scan sequences
+    ///
+    /// Each pattern gives the values of the original inputs, then of the flip-flops in the order of
+    /// the network, as for [`super::expose_dff`]. It becomes a sequence of shift cycles that load
+    /// the flip-flops, one capture cycle with the original inputs, and shift cycles that unload
+    /// the captured values to the scan-outs. The inputs are those of the network with scan chains.
+    pub fn scan_patterns(&self, aig: &Network, patterns: &[Vec<bool>]) -> Vec<Vec<Vec<bool>>> {
+        let dffs = stats::dff_gates(aig);
+        let nb_inputs = aig.nb_inputs();
+        let nb_scan_inputs = nb_inputs + 1 + self.chains.len();
+        let length = self.max_length();
+        let mut ret = Vec::new();
+        for p in patterns {
+            assert_eq!(p.len(), nb_inputs + dffs.len());
+            let mut seq = Vec::new();
+            // Load: the first value shifted in ends up in the last flip-flop of the chain
+            for t in 0..length {
+                let mut v = vec![false; nb_scan_inputs];
+                v[self.scan_enable] = true;
+                for (k, chain) in self.chains.iter().enumerate() {
+                    if let Some(ff) = chain.get(length - 1 - t) {
+                        let pos = dffs.binary_search(ff).unwrap();
+                        v[self.scan_in[k]] = p[nb_inputs + pos];
+                    }
+                }
+                seq.push(v);
+            }
+            // Capture
+            let mut v = vec![false; nb_scan_inputs];
+            v[..nb_inputs].copy_from_slice(&p[..nb_inputs]);
+            seq.push(v);
+            // Unload
+            for _ in 0..length {
+                let mut v = vec![false; nb_scan_inputs];
+                v[self.scan_enable] = true;
+                seq.push(v);
+            }
+            ret.push(seq);
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{insert_scan_chains, ScanOrder};
+    use crate::atpg::{expose_dff, generate_random_comb_patterns};
+    use crate::network::stats;
+    use crate::sim::{simulate, simulate_comb};
+    use crate::{Gate, Network, Signal};
+
+    /// Network with flip-flops using enable and reset
+    fn example() -> Network {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let d0 = aig.dff(i0, i1, Signal::zero());
+        let d1 = aig.dff(i2, Signal::one(), i1);
+        let x = aig.xor(d0, d1);
+        let d2 = aig.dff(x, Signal::one(), Signal::zero());
+        let y = aig.and(d2, i2);
+        let d3 = aig.dff(y, i0, i1);
+        let d4 = aig.dff(d3, Signal::one(), Signal::zero());
+        aig.add_output(x);
+        aig.add_output(d4);
+        aig
+    }
+
+    #[test]
+    fn test_scan_insertion() {
+        let aig = example();
+        let (scan, chains) = insert_scan_chains(&aig, 2, &ScanOrder::Index);
+        assert_eq!(chains.chains.len(), 2);
+        assert_eq!(chains.chains[0].len(), 3);
+        assert_eq!(chains.chains[1].len(), 2);
+        assert_eq!(scan.nb_inputs(), 6);
+        assert_eq!(scan.nb_outputs(), 4);
+        let names = ["q2", "q10", "q0", "q1", "q3"].map(String::from).to_vec();
+        let (_, by_name) = insert_scan_chains(&aig, 1, &ScanOrder::Name(names.clone()));
+        let dffs = stats::dff_gates(&aig);
+        assert_eq!(
+            by_name.chains[0],
+            vec![dffs[2], dffs[3], dffs[1], dffs[0], dffs[4]]
+        );
+        let mut description = Vec::new();
+        by_name.write(&mut description, &aig, Some(&names));
+        let description = String::from_utf8(description).unwrap();
+        assert!(description
+            .trim_end()
+            .ends_with("length 5: q0 q1 q10 q2 q3"));
+
+        // Capture and unload the response of each pattern of the combinational view
+        let comb = expose_dff(&aig);
+        let patterns = generate_random_comb_patterns(comb.nb_inputs(), 16, 1);
+        let length = chains.max_length();
+        for (p, seq) in patterns.iter().zip(chains.scan_patterns(&aig, &patterns)) {
+            assert_eq!(seq.len(), 2 * length + 1);
+            let expected = simulate_comb(&comb, p);
+            let actual = simulate(&scan, &seq);
+            assert_eq!(actual[length][..2], expected[..2]);
+
+            // Next value of each flip-flop, from its data, enable and reset
+            let mut next = Vec::new();
+            let mut o = aig.nb_outputs();
+            for (pos, ff) in dffs.iter().enumerate() {
+                let Gate::Dff([_, en, res]) = aig.gate(*ff) else {
+                    unreachable!();
+                };
+                let mut v = expected[o];
+                o += 1;
+                if !en.is_constant() {
+                    v = if expected[o] {
+                        v
+                    } else {
+                        p[aig.nb_inputs() + pos]
+                    };
+                    o += 1;
+                }
+                if !res.is_constant() {
+                    v &= !expected[o];
+                    o += 1;
+                }
+                next.push(v);
+            }
+            for (k, chain) in chains.chains.iter().enumerate() {
+                for (j, ff) in chain.iter().rev().enumerate() {
+                    let pos = dffs.binary_search(ff).unwrap();
+                    assert_eq!(actual[length + 1 + j][chains.scan_out[k]], next[pos]);
+                }
+            }
+        }
+    }
+}
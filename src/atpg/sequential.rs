@@ -0,0 +1,512 @@
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::equiv::{difference, prove, unroll};
+use crate::network::stats;
+use crate::sim::{simulate_multi, simulate_multi_with_faults, Fault};
+use crate::{Gate, Network, Signal};
+
+use super::faulty_network;
+
+/// Number of random multi-cycle patterns simulated together
+const RANDOM_BATCH_SIZE: usize = 64;
+
+/// Sequential network for partial-scan test generation
+///
+/// The scanned flip-flops are loaded from additional inputs in the first cycle only, and their
+/// next state is observed on additional outputs, that are only compared after the last cycle.
+/// The next state of every flip-flop is computed by combinatorial gates, so that the faults on
+/// the data, enable and reset inputs of the flip-flops are injected on ordinary gates.
+///
+/// Gates of the original network are shifted by a fixed offset, after a flip-flop that is zero in
+/// the first cycle only and one flip-flop holding the state of each original flip-flop.
+struct ScanNetwork {
+    network: Network,
+    /// Number of gates of the original network
+    nb_gates: usize,
+    /// Number of outputs of the original network
+    nb_outputs: usize,
+    /// Number of inputs of the original network
+    nb_inputs: usize,
+    /// Offset of the original gates in the network
+    offset: usize,
+    /// Index of each original gate among the flip-flops, if it is one
+    flop_index: Vec<Option<usize>>,
+}
+
+impl ScanNetwork {
+    /// Build the network for the given scanned flip-flops
+    fn from(aig: &Network, scanned: &[usize]) -> Result<ScanNetwork, String> {
+        let flops = stats::dff_gates(aig);
+        let nb_gates = aig.nb_nodes();
+        let offset = flops.len() + 1;
+        let mut flop_index = vec![None; nb_gates];
+        for (j, i) in flops.iter().enumerate() {
+            flop_index[*i] = Some(j);
+        }
+        for i in scanned {
+            if flop_index.get(*i).copied().flatten().is_none() {
+                return Err(format!(
+                    "Gate {} is not a flip-flop and cannot be scanned",
+                    i
+                ));
+            }
+        }
+        // Next state of each flip-flop, after the original gates
+        let next_state = |j: usize| Signal::from_var((offset + nb_gates + 2 * j + 1) as u32);
+        let t = |s: &Signal| -> Signal {
+            if s.is_var() {
+                Signal::from_var(s.var() + offset as u32) ^ s.is_inverted()
+            } else {
+                *s
+            }
+        };
+
+        let mut ret = Network::new();
+        ret.add_inputs(aig.nb_inputs() + scanned.len());
+        let started = ret.add(Gate::dff(Signal::one(), Signal::one(), Signal::zero()));
+        for j in 0..flops.len() {
+            ret.add(Gate::dff(next_state(j), Signal::one(), Signal::zero()));
+        }
+        for (i, flop) in flop_index.iter().enumerate() {
+            match flop {
+                Some(j) => {
+                    let state = Signal::from_var(1 + *j as u32);
+                    match scanned.iter().position(|g| *g == i) {
+                        Some(k) => {
+                            let scan_in = Signal::from_input((aig.nb_inputs() + k) as u32);
+                            ret.add(Gate::mux(started, state, scan_in))
+                        }
+                        None => ret.add(Gate::Buf(state)),
+                    };
+                }
+                None => {
+                    ret.add(aig.gate(i).remap(t));
+                }
+            }
+        }
+        for (j, i) in flops.iter().enumerate() {
+            let Gate::Dff([d, en, res]) = aig.gate(*i) else {
+                unreachable!()
+            };
+            let q = Signal::from_var((i + offset) as u32);
+            let next = ret.add(Gate::mux(t(en), t(d), q));
+            let s = ret.add(Gate::and(next, !t(res)));
+            assert_eq!(s, next_state(j));
+        }
+        for o in 0..aig.nb_outputs() {
+            ret.add_output(t(&aig.output(o)));
+        }
+        for i in scanned {
+            ret.add_output(next_state(flop_index[*i].unwrap()));
+        }
+        ret.check();
+        Ok(ScanNetwork {
+            network: ret,
+            nb_gates,
+            nb_outputs: aig.nb_outputs(),
+            nb_inputs: aig.nb_inputs(),
+            offset,
+            flop_index,
+        })
+    }
+
+    /// Translate a stuck-at fault of the original network
+    ///
+    /// Other fault models are not supported and return None.
+    fn translate_fault(&self, fault: Fault) -> Option<Fault> {
+        let ret = match fault {
+            Fault::OutputStuckAtFault { gate, value } => Fault::OutputStuckAtFault {
+                gate: gate + self.offset,
+                value,
+            },
+            Fault::InputStuckAtFault { gate, input, value } => match self.flop_index[gate] {
+                None => Fault::InputStuckAtFault {
+                    gate: gate + self.offset,
+                    input,
+                    value,
+                },
+                Some(j) => {
+                    // Next state logic: data and enable on a Mux, reset on an inverted And input
+                    let next = self.offset + self.nb_gates + 2 * j;
+                    match input {
+                        0 => Fault::InputStuckAtFault {
+                            gate: next,
+                            input: 1,
+                            value,
+                        },
+                        1 => Fault::InputStuckAtFault {
+                            gate: next,
+                            input: 0,
+                            value,
+                        },
+                        _ => Fault::InputStuckAtFault {
+                            gate: next + 1,
+                            input: 1,
+                            value: !value,
+                        },
+                    }
+                }
+            },
+            _ => return None,
+        };
+        Some(ret)
+    }
+
+    /// Whether an output is compared at a given cycle of a pattern of the given length
+    ///
+    /// Original outputs are compared at every cycle, and scanned flip-flops after the last cycle.
+    fn is_observed(&self, output: usize, cycle: usize, nb_cycles: usize) -> bool {
+        output < self.nb_outputs || cycle + 1 == nb_cycles
+    }
+
+    /// Copy of an unrolled network, keeping only the outputs that are compared
+    fn observed_outputs(&self, unrolled: &Network, nb_cycles: usize) -> Network {
+        let nb_outputs = self.network.nb_outputs();
+        let mut ret = Network::new();
+        ret.add_inputs(unrolled.nb_inputs());
+        for i in 0..unrolled.nb_nodes() {
+            ret.add(unrolled.gate(i).clone());
+        }
+        for o in 0..unrolled.nb_outputs() {
+            if self.is_observed(o % nb_outputs, o / nb_outputs, nb_cycles) {
+                ret.add_output(unrolled.output(o));
+            }
+        }
+        ret
+    }
+
+    /// Set the values of the scanned flip-flops to zero after the first cycle, where they are unused
+    fn clear_unused_scan_values(&self, pattern: &mut [Vec<bool>]) {
+        for cycle in pattern.iter_mut().skip(1) {
+            for v in &mut cycle[self.nb_inputs..] {
+                *v = false;
+            }
+        }
+    }
+}
+
+/// Multi-cycle test pattern generation by time-frame expansion
+///
+/// Patterns are applied from the reset state, where all flip-flops are zero, except the scanned
+/// flip-flops that are loaded in the first cycle. A fault is detected if an output differs at any
+/// cycle, or if a scanned flip-flop differs after the last cycle. Faults are present in every
+/// time frame.
+struct SequentialPatternGenerator<'a> {
+    scan: &'a ScanNetwork,
+    faults: Vec<Fault>,
+    /// Faults translated to the network with scan
+    scan_faults: Vec<Fault>,
+    max_depth: usize,
+    detection: Vec<bool>,
+    patterns: Vec<Vec<Vec<bool>>>,
+    rng: SmallRng,
+}
+
+impl<'a> SequentialPatternGenerator<'a> {
+    fn nb_detected(&self) -> usize {
+        self.detection.iter().filter(|b| **b).count()
+    }
+
+    /// Which of the packed patterns detect each of the undetected faults
+    ///
+    /// The patterns are given with one 64b value per cycle and per input.
+    fn simulate(&self, patterns: &Vec<Vec<u64>>) -> Vec<(usize, u64)> {
+        let aig = &self.scan.network;
+        let expected = simulate_multi(aig, patterns);
+        let mut ret = Vec::new();
+        for (i, f) in self.scan_faults.iter().enumerate() {
+            if self.detection[i] {
+                continue;
+            }
+            let actual = simulate_multi_with_faults(aig, patterns, &vec![*f]);
+            let mut mask = 0;
+            for (t, (e, a)) in expected.iter().zip(&actual).enumerate() {
+                for (o, (x, y)) in e.iter().zip(a).enumerate() {
+                    if self.scan.is_observed(o, t, patterns.len()) {
+                        mask |= x ^ y;
+                    }
+                }
+            }
+            if mask != 0 {
+                ret.push((i, mask));
+            }
+        }
+        ret
+    }
+
+    /// Add a multi-cycle pattern, and mark the faults it detects
+    fn add_pattern(&mut self, mut pattern: Vec<Vec<bool>>) -> usize {
+        self.scan.clear_unused_scan_values(&mut pattern);
+        let packed = pattern
+            .iter()
+            .map(|v| v.iter().map(|b| if *b { !0 } else { 0 }).collect())
+            .collect();
+        let detected = self.simulate(&packed);
+        for (i, _) in &detected {
+            self.detection[*i] = true;
+        }
+        self.patterns.push(pattern);
+        detected.len()
+    }
+
+    /// Simulate a batch of random patterns, keeping the ones that detect new faults
+    fn add_random_patterns(&mut self) {
+        let nb_inputs = self.scan.network.nb_inputs();
+        let packed: Vec<Vec<u64>> = (0..self.max_depth)
+            .map(|_| (0..nb_inputs).map(|_| self.rng.gen()).collect())
+            .collect();
+        let detected = self.simulate(&packed);
+        for b in 0..RANDOM_BATCH_SIZE {
+            let mut useful = false;
+            for (i, mask) in &detected {
+                if !self.detection[*i] && (mask >> b) & 1 != 0 {
+                    self.detection[*i] = true;
+                    useful = true;
+                }
+            }
+            if useful {
+                let mut pattern: Vec<Vec<bool>> = packed
+                    .iter()
+                    .map(|v| v.iter().map(|w| (w >> b) & 1 != 0).collect())
+                    .collect();
+                self.scan.clear_unused_scan_values(&mut pattern);
+                self.patterns.push(pattern);
+            }
+        }
+    }
+
+    /// Find a pattern detecting the fault, with the smallest number of cycles up to the maximum depth
+    fn find_pattern_detecting_fault(&self, fault_index: usize) -> Option<Vec<Vec<bool>>> {
+        let aig = &self.scan.network;
+        let fault_aig = faulty_network(aig, self.scan_faults[fault_index]);
+        for depth in 1..=self.max_depth {
+            let mut diff = difference(
+                &self.scan.observed_outputs(&unroll(aig, depth), depth),
+                &self
+                    .scan
+                    .observed_outputs(&unroll(&fault_aig, depth), depth),
+                None,
+            );
+            diff.make_canonical();
+            diff.cleanup();
+            if let Some(p) = prove(&diff) {
+                let n = aig.nb_inputs();
+                return Some((0..depth).map(|t| p[t * n..(t + 1) * n].to_vec()).collect());
+            }
+        }
+        None
+    }
+
+    fn detect_faults(&mut self) {
+        loop {
+            let nb_detected_before = self.nb_detected();
+            self.add_random_patterns();
+            let nb_new = self.nb_detected() - nb_detected_before;
+            if self.nb_detected() == self.faults.len()
+                || (nb_new as f64) < 0.01 * self.faults.len() as f64
+            {
+                break;
+            }
+        }
+        println!(
+            "Generated {} random patterns, detecting {}/{} faults",
+            self.patterns.len(),
+            self.nb_detected(),
+            self.faults.len()
+        );
+        let mut not_detected = 0;
+        for i in 0..self.faults.len() {
+            if self.detection[i] {
+                continue;
+            }
+            match self.find_pattern_detecting_fault(i) {
+                Some(pattern) => {
+                    let nb_detected = self.add_pattern(pattern);
+                    assert!(self.detection[i] && nb_detected > 0);
+                }
+                None => not_detected += 1,
+            }
+        }
+        println!(
+            "{} faults are not detectable within {} cycles from reset",
+            not_detected, self.max_depth
+        );
+    }
+}
+
+/// Generate multi-cycle test patterns for stuck-at faults, without full scan
+///
+/// The network is unrolled from its reset state, where all flip-flops are zero, over increasing
+/// numbers of cycles up to the given depth. The scanned flip-flops, given by their gate index,
+/// are loaded in the first cycle and observed after the last cycle.
+/// Each pattern gives the input values at each cycle, followed by the values loaded in the
+/// scanned flip-flops, that are only used in the first cycle and are zero afterwards.
+/// Faults on the data, enable and reset inputs of the flip-flops are targeted as well.
+///
+/// Returns an error if one of the scanned gates is not a flip-flop.
+pub fn generate_seq_test_patterns(
+    aig: &Network,
+    max_depth: usize,
+    scanned: &[usize],
+    seed: u64,
+) -> Result<Vec<Vec<Vec<bool>>>, String> {
+    assert!(max_depth >= 1);
+    let scan = ScanNetwork::from(aig, scanned)?;
+    let (faults, scan_faults): (Vec<Fault>, Vec<Fault>) = Fault::all_collapsed(aig)
+        .into_iter()
+        .filter_map(|f| Some((f, scan.translate_fault(f)?)))
+        .unzip();
+
+    println!(
+        "Analyzing network with {} inputs, {} outputs, {} gates, {} flip-flops ({} scanned), {} collapsed faults",
+        aig.nb_inputs(),
+        aig.nb_outputs(),
+        aig.nb_nodes(),
+        (0..aig.nb_nodes()).filter(|i| !aig.gate(*i).is_comb()).count(),
+        scanned.len(),
+        faults.len(),
+    );
+
+    let mut gen = SequentialPatternGenerator {
+        scan: &scan,
+        detection: vec![false; faults.len()],
+        faults,
+        scan_faults,
+        max_depth,
+        patterns: Vec::new(),
+        rng: SmallRng::seed_from_u64(seed),
+    };
+    gen.detect_faults();
+    println!(
+        "Kept {} patterns, detecting {}/{} faults ({:.2}% coverage)",
+        gen.patterns.len(),
+        gen.nb_detected(),
+        gen.faults.len(),
+        100.0 * (gen.nb_detected() as f64) / (gen.faults.len() as f64)
+    );
+    Ok(gen.patterns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_seq_test_patterns, ScanNetwork};
+    use crate::sim::{simulate, simulate_with_faults, Fault};
+    use crate::{Network, Signal};
+
+    /// Shift register of three flip-flops, with an And gate before the last one
+    fn shift_register() -> Network {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let d0 = aig.dff(i0, Signal::one(), Signal::zero());
+        let d1 = aig.dff(d0, Signal::one(), Signal::zero());
+        let x = aig.and(d1, i1);
+        let d2 = aig.dff(x, Signal::one(), Signal::zero());
+        aig.add_output(d2);
+        aig
+    }
+
+    /// Whether a fault is detected by the patterns, with the given scanned flip-flops
+    fn detected(aig: &Network, scanned: &[usize], patterns: &[Vec<Vec<bool>>], f: Fault) -> bool {
+        let scan = ScanNetwork::from(aig, scanned).unwrap();
+        let f = scan.translate_fault(f).unwrap();
+        patterns.iter().any(|p| {
+            let expected = simulate(&scan.network, p);
+            let actual = simulate_with_faults(&scan.network, p, &vec![f]);
+            (0..p.len()).any(|t| {
+                (0..scan.network.nb_outputs())
+                    .any(|o| scan.is_observed(o, t, p.len()) && expected[t][o] != actual[t][o])
+            })
+        })
+    }
+
+    #[test]
+    fn test_sequential_atpg() {
+        let aig = shift_register();
+        let fault = Fault::OutputStuckAtFault {
+            gate: 2,
+            value: false,
+        };
+        // A value needs four cycles to reach the output
+        let short = generate_seq_test_patterns(&aig, 3, &[], 1).unwrap();
+        assert!(!detected(&aig, &[], &short, fault));
+        let patterns = generate_seq_test_patterns(&aig, 4, &[], 1).unwrap();
+        assert!(patterns.iter().all(|p| p.len() <= 4));
+        let mut nb_dff_faults = 0;
+        for f in Fault::all_collapsed(&aig) {
+            if !aig.gate(f.gate()).is_comb() {
+                nb_dff_faults += 1;
+            }
+            if matches!(f, Fault::InputStuckAtFault { input, .. } if input > 0) {
+                // Constant enable and reset
+                continue;
+            }
+            assert!(detected(&aig, &[], &patterns, f), "{}", f);
+        }
+        assert!(nb_dff_faults > 0);
+        // Without scan, the outputs are the only observation points
+        assert!(patterns
+            .iter()
+            .any(|p| simulate(&aig, p) != simulate_with_faults(&aig, p, &vec![fault])));
+    }
+
+    #[test]
+    fn test_partial_scan_atpg() {
+        let aig = shift_register();
+        let fault = Fault::OutputStuckAtFault {
+            gate: 2,
+            value: false,
+        };
+        // Scanning the middle flip-flop shortens the sequence
+        let patterns = generate_seq_test_patterns(&aig, 2, &[1], 1).unwrap();
+        assert!(detected(&aig, &[1], &patterns, fault));
+        for p in &patterns {
+            // Scanned values are only loaded in the first cycle
+            assert!(p.iter().all(|v| v.len() == 3));
+            assert!(p[1..].iter().all(|v| !v[2]));
+        }
+
+        // The first flip-flop is observed through the scanned one, after the last cycle only
+        let fault = Fault::InputStuckAtFault {
+            gate: 0,
+            input: 0,
+            value: true,
+        };
+        let patterns = generate_seq_test_patterns(&aig, 1, &[1], 1).unwrap();
+        assert!(!detected(&aig, &[1], &patterns, fault));
+        let patterns = generate_seq_test_patterns(&aig, 2, &[1], 1).unwrap();
+        assert!(detected(&aig, &[1], &patterns, fault));
+        // A scanned flip-flop is observed directly
+        let patterns = generate_seq_test_patterns(&aig, 1, &[0], 1).unwrap();
+        assert!(detected(&aig, &[0], &patterns, fault));
+    }
+
+    #[test]
+    fn test_flip_flop_control_faults() {
+        // Flip-flop with an enable and a reset from the inputs
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let d = aig.dff(i0, i1, i2);
+        aig.add_output(d);
+        let patterns = generate_seq_test_patterns(&aig, 2, &[], 1).unwrap();
+        for f in Fault::all_collapsed(&aig) {
+            assert!(detected(&aig, &[], &patterns, f), "{}", f);
+        }
+        let patterns = generate_seq_test_patterns(&aig, 1, &[0], 1).unwrap();
+        for f in Fault::all_collapsed(&aig) {
+            assert!(detected(&aig, &[0], &patterns, f), "{}", f);
+        }
+    }
+
+    #[test]
+    fn test_invalid_scanned_flip_flop() {
+        let aig = shift_register();
+        let err = generate_seq_test_patterns(&aig, 2, &[0, 2], 1).unwrap_err();
+        assert!(err.contains("Gate 2"), "{}", err);
+        let err = generate_seq_test_patterns(&aig, 2, &[10], 1).unwrap_err();
+        assert!(err.contains("Gate 10"), "{}", err);
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::sim::{detects_faults_multi_parallel, pattern_mask, Fault};
+use crate::{Gate, Network, Signal};
+
+use super::{expose_dff, Cop};
+
+/// Lower bound on detection probabilities, so that undetectable faults have a finite cost
+const MIN_DETECTION_PROBABILITY: f64 = 1e-12;
+
+/// Number of gates in the fanin of the hardest fault considered for control points
+const MAX_CONTROL_CANDIDATES: usize = 16;
+
+/// Number of faults targeted for each test point, including the faults that no candidate improves
+const TARGETS_PER_TEST_POINT: usize = 4;
+
+/// Test point inserted to improve testability with random patterns
+///
+/// Test points use new inputs, that are zero in functional mode and random in test mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestPoint {
+    /// Control point, forcing the output of a gate to a value when its new input is set
+    ///
+    /// A control-1 point is an Or with the new input, and a control-0 point is an And with its complement.
+    Control {
+        /// Gate to control
+        gate: usize,
+        /// Value forced by the control point
+        value: bool,
+    },
+    /// Observation point, exposing the output of a gate as a new output
+    Observation {
+        /// Gate to observe
+        gate: usize,
+    },
+}
+
+impl fmt::Display for TestPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TestPoint::Control { gate, value } => {
+                write!(f, "control-{} point on x{}", *value as u8, gate)
+            }
+            TestPoint::Observation { gate } => write!(f, "observation point on x{}", gate),
+        }
+    }
+}
+
+/// Whether a gate already has a control point
+fn is_controlled(points: &[TestPoint], g: usize) -> bool {
+    points
+        .iter()
+        .any(|p| matches!(p, TestPoint::Control { gate, .. } if *gate == g))
+}
+
+/// Cost of the network for random patterns: the sum of the inverse detection probabilities of the faults
+fn random_pattern_cost(aig: &Network, cop: &Cop) -> f64 {
+    (0..aig.nb_nodes())
+        .flat_map(|i| [false, true].map(|v| cop.detection_probability(i, v)))
+        .map(|p| 1.0 / p.max(MIN_DETECTION_PROBABILITY))
+        .sum()
+}
+
+/// Test points that may help detect the output stuck-at fault of a gate
+///
+/// This is an observation point on the gate, and control points on the closest gates in its fanin.
+fn candidate_test_points(aig: &Network, gate: usize, points: &[TestPoint]) -> Vec<TestPoint> {
+    let mut ret = Vec::new();
+    if !points.contains(&TestPoint::Observation { gate }) {
+        ret.push(TestPoint::Observation { gate });
+    }
+    let mut visited = vec![false; aig.nb_nodes()];
+    let mut to_visit = VecDeque::from([gate]);
+    let mut nb_visited = 0;
+    while let Some(g) = to_visit.pop_front() {
+        if visited[g] || !aig.gate(g).is_comb() || nb_visited >= MAX_CONTROL_CANDIDATES {
+            continue;
+        }
+        visited[g] = true;
+        nb_visited += 1;
+        if !is_controlled(points, g) {
+            for value in [false, true] {
+                ret.push(TestPoint::Control { gate: g, value });
+            }
+        }
+        to_visit.extend(aig.gate(g).vars().map(|v| v as usize));
+    }
+    ret
+}
+
+/// Select test points to make the network easier to test with random patterns
+///
+/// Random-pattern-resistant faults are the output stuck-at faults whose COP detection probability
+/// is below the threshold. The hardest one is targeted at each step, and the candidate test point
+/// that minimizes the sum of the inverse detection probabilities is selected. Faults that no
+/// candidate improves are skipped.
+pub fn select_test_points(aig: &Network, max_points: usize, threshold: f64) -> Vec<TestPoint> {
+    let mut points = Vec::new();
+    let mut cop = Cop::compute(aig);
+    let mut cost = random_pattern_cost(aig, &cop);
+    // Gates whose faults no candidate test point improves
+    let mut skipped = vec![false; aig.nb_nodes()];
+    for _ in 0..TARGETS_PER_TEST_POINT * max_points {
+        if points.len() >= max_points {
+            break;
+        }
+        // Hardest fault that is still detectable
+        let hardest = (0..aig.nb_nodes())
+            .filter(|i| aig.gate(*i).is_comb() && !skipped[*i])
+            .flat_map(|i| [false, true].map(|v| (i, cop.detection_probability(i, v))))
+            .filter(|(_, p)| *p > 0.0 && *p < threshold)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((gate, _)) = hardest else {
+            break;
+        };
+        let mut best = None;
+        for c in candidate_test_points(aig, gate, &points) {
+            points.push(c);
+            let c_cop = Cop::compute_with_test_points(aig, &points);
+            let c_cost = random_pattern_cost(aig, &c_cop);
+            points.pop();
+            if c_cost < best.as_ref().map_or(cost, |(_, b, _)| *b) {
+                best = Some((c, c_cost, c_cop));
+            }
+        }
+        let Some((c, c_cost, c_cop)) = best else {
+            skipped[gate] = true;
+            continue;
+        };
+        points.push(c);
+        cost = c_cost;
+        cop = c_cop;
+    }
+    points
+}
+
+/// Index of each original gate in the network with test points
+fn gate_indices(aig: &Network, points: &[TestPoint]) -> Vec<usize> {
+    let mut ret = Vec::new();
+    let mut next = 0;
+    for i in 0..aig.nb_nodes() {
+        ret.push(next);
+        next += 1;
+        if is_controlled(points, i) {
+            next += 1;
+        }
+    }
+    ret
+}
+
+/// Create a copy of the network with test points inserted
+///
+/// The new inputs for control points are added after the original inputs, and the new outputs
+/// for observation points after the original outputs, in the order of the test points.
+/// Each control point adds a gate just after the gate it controls.
+pub fn insert_test_points(aig: &Network, points: &[TestPoint]) -> Network {
+    let indices = gate_indices(aig, points);
+    let mut ret = Network::new();
+    ret.add_inputs(aig.nb_inputs());
+
+    // Signal seen by the fanouts of each gate, and new input of its control point
+    let mut translation: Vec<Signal> = indices
+        .iter()
+        .map(|i| Signal::from_var(*i as u32))
+        .collect();
+    let mut control = vec![None; aig.nb_nodes()];
+    for p in points {
+        if let TestPoint::Control { gate, value } = p {
+            translation[*gate] = Signal::from_var(indices[*gate] as u32 + 1) ^ *value;
+            control[*gate] = Some((ret.add_input(), *value));
+        }
+    }
+    let t = |s: &Signal| -> Signal {
+        if s.is_var() {
+            translation[s.var() as usize] ^ s.is_inverted()
+        } else {
+            *s
+        }
+    };
+
+    for i in 0..aig.nb_nodes() {
+        ret.add(aig.gate(i).remap(t));
+        if let Some((input, value)) = control[i] {
+            let s = Signal::from_var(indices[i] as u32) ^ value;
+            ret.add(Gate::and(s, !input));
+        }
+    }
+    for o in 0..aig.nb_outputs() {
+        ret.add_output(t(&aig.output(o)));
+    }
+    for p in points {
+        if let TestPoint::Observation { gate } = p {
+            ret.add_output(Signal::from_var(indices[*gate] as u32));
+        }
+    }
+    ret.check();
+    ret
+}
+
+/// Number of faults detected by random patterns
+fn random_pattern_detections(
+    aig: &Network,
+    faults: &[Fault],
+    nb_patterns: usize,
+    seed: u64,
+    nb_threads: usize,
+) -> usize {
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let patterns: Vec<Vec<u64>> = (0..nb_patterns.div_ceil(64))
+        .map(|_| (0..aig.nb_inputs()).map(|_| rng.gen()).collect())
+        .collect();
+    let detections = detects_faults_multi_parallel(aig, &patterns, faults, nb_threads);
+    let mut detected = vec![false; faults.len()];
+    for (w, d) in detections.iter().enumerate() {
+        let mask = pattern_mask(nb_patterns - 64 * w);
+        for (f, v) in d.iter().enumerate() {
+            detected[f] |= v & mask != 0;
+        }
+    }
+    detected.iter().filter(|b| **b).count()
+}
+
+/// Fault coverage of random patterns, before and after test point insertion
+///
+/// The faults are the collapsed stuck-at faults of the original network with flip-flops exposed,
+/// and the same faults on the network with test points inserted, with flip-flops exposed.
+/// Returns the number of faults, and the number of faults detected before and after insertion.
+pub fn test_point_coverage(
+    aig: &Network,
+    points: &[TestPoint],
+    nb_patterns: usize,
+    seed: u64,
+    nb_threads: usize,
+) -> (usize, usize, usize) {
+    // Exposing the flip-flops keeps the gate indices, so the faults can be mapped in the same way
+    let exposed = expose_dff(aig);
+    let faults = Fault::all_collapsed(&exposed);
+    let indices = gate_indices(aig, points);
+    let mapped: Vec<Fault> = faults
+        .iter()
+        .map(|f| match *f {
+            Fault::OutputStuckAtFault { gate, value } => Fault::OutputStuckAtFault {
+                gate: indices[gate],
+                value,
+            },
+            Fault::InputStuckAtFault { gate, input, value } => Fault::InputStuckAtFault {
+                gate: indices[gate],
+                input,
+                value,
+            },
+            _ => unreachable!(),
+        })
+        .collect();
+    let modified = insert_test_points(aig, points);
+    let before = random_pattern_detections(&exposed, &faults, nb_patterns, seed, nb_threads);
+    let after = random_pattern_detections(
+        &expose_dff(&modified),
+        &mapped,
+        nb_patterns,
+        seed,
+        nb_threads,
+    );
+    (faults.len(), before, after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{insert_test_points, select_test_points, test_point_coverage, TestPoint};
+    use crate::sim::simulate_comb;
+    use crate::{Gate, Network, Signal};
+
+    /// Wide And gate followed by an Xor, whose output stuck-at-0 fault is random-pattern-resistant
+    fn wide_and() -> Network {
+        let mut aig = Network::new();
+        let inputs: Vec<_> = (0..16).map(|_| aig.add_input()).collect();
+        let mut a = inputs[0];
+        for i in &inputs[1..] {
+            a = aig.add(Gate::and(a, *i));
+        }
+        let x = aig.add(Gate::xor(a, inputs[0]));
+        aig.add_output(x);
+        aig
+    }
+
+    #[test]
+    fn test_insertion() {
+        let aig = wide_and();
+        let points = [
+            TestPoint::Control {
+                gate: 7,
+                value: true,
+            },
+            TestPoint::Observation { gate: 3 },
+        ];
+        let modified = insert_test_points(&aig, &points);
+        assert_eq!(modified.nb_inputs(), 17);
+        assert_eq!(modified.nb_outputs(), 2);
+        assert_eq!(modified.nb_nodes(), aig.nb_nodes() + 1);
+        // The network is unchanged when the control input is zero
+        for i in 0..16 {
+            let mut v = vec![true; 17];
+            v[i] = false;
+            v[16] = false;
+            assert_eq!(
+                simulate_comb(&modified, &v)[0],
+                simulate_comb(&aig, &v[..16].to_vec())[0]
+            );
+        }
+        // The control point sets the output of the And chain
+        let mut v = vec![true; 17];
+        v[0] = false;
+        assert!(!simulate_comb(&aig, &v[..16].to_vec())[0]);
+        assert!(simulate_comb(&modified, &v)[0]);
+    }
+
+    #[test]
+    fn test_selection() {
+        let aig = wide_and();
+        let points = select_test_points(&aig, 4, 1e-3);
+        assert!(!points.is_empty() && points.len() <= 4);
+        assert!(matches!(points[0], TestPoint::Control { value: true, .. }));
+        let (nb_faults, before, after) = test_point_coverage(&aig, &points, 1024, 1, 1);
+        assert!(before < nb_faults);
+        assert!(after > before);
+    }
+
+    #[test]
+    fn test_sequential_coverage() {
+        // Wide And gate registered by a flip-flop with an enable, then an Xor
+        let mut aig = Network::new();
+        let inputs: Vec<_> = (0..17).map(|_| aig.add_input()).collect();
+        let mut a = inputs[0];
+        for i in &inputs[1..16] {
+            a = aig.add(Gate::and(a, *i));
+        }
+        let d = aig.dff(a, inputs[16], Signal::zero());
+        let x = aig.add(Gate::xor(d, inputs[0]));
+        aig.add_output(x);
+        let points = select_test_points(&aig, 4, 1e-3);
+        assert!(!points.is_empty());
+        let (nb_faults, before, after) = test_point_coverage(&aig, &points, 1024, 1, 1);
+        assert!(before < nb_faults);
+        assert!(after > before);
+    }
+}
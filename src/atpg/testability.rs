@@ -0,0 +1,655 @@
+use std::io::Write;
+
+use crate::network::{BinaryType, NaryType, TernaryType};
+use crate::{Gate, Network, Signal};
+
+use super::TestPoint;
+
+/// COP testability measures, assuming independent signals and uniformly random inputs
+///
+/// Flip-flops are considered scanned: their outputs are random, and their inputs are observed.
+#[derive(Clone, Debug)]
+pub struct Cop {
+    /// Probability of each node being one
+    pub one_probability: Vec<f64>,
+    /// Probability of observing the value of each node at an output
+    pub observability: Vec<f64>,
+}
+
+/// Probability that any of two independent events happens
+fn either(a: f64, b: f64) -> f64 {
+    1.0 - (1.0 - a) * (1.0 - b)
+}
+
+/// Probability of the output of an And being one, and probability that each input is observed
+fn and_probabilities(p: &[f64]) -> (f64, Vec<f64>) {
+    let out = p.iter().product();
+    let sens = (0..p.len())
+        .map(|i| {
+            p.iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, x)| x)
+                .product()
+        })
+        .collect();
+    (out, sens)
+}
+
+/// Probability of the output of a gate being one, and probability that each input is observed
+///
+/// The probabilities of the inputs being one are given in the order of the dependencies.
+fn gate_probabilities(g: &Gate, p: &[f64]) -> (f64, Vec<f64>) {
+    use Gate::*;
+    let inverted: Vec<f64> = p.iter().map(|x| 1.0 - x).collect();
+    let xor = p.iter().fold(0.0, |a, b| a * (1.0 - b) + b * (1.0 - a));
+    let differ = |a: f64, b: f64| a * (1.0 - b) + b * (1.0 - a);
+    match g {
+        Binary(_, BinaryType::And) | Ternary(_, TernaryType::And) | Nary(_, NaryType::And) => {
+            and_probabilities(p)
+        }
+        Nary(_, NaryType::Nand) => {
+            let (out, sens) = and_probabilities(p);
+            (1.0 - out, sens)
+        }
+        Nary(_, NaryType::Or) => {
+            let (out, sens) = and_probabilities(&inverted);
+            (1.0 - out, sens)
+        }
+        Nary(_, NaryType::Nor) => and_probabilities(&inverted),
+        Binary(_, BinaryType::Xor) | Ternary(_, TernaryType::Xor) | Nary(_, NaryType::Xor) => {
+            (xor, vec![1.0; p.len()])
+        }
+        Nary(_, NaryType::Xnor) => (1.0 - xor, vec![1.0; p.len()]),
+        Ternary(_, TernaryType::Mux) => (
+            p[0] * p[1] + (1.0 - p[0]) * p[2],
+            vec![differ(p[1], p[2]), p[0], 1.0 - p[0]],
+        ),
+        Ternary(_, TernaryType::Maj) => (
+            p[0] * p[1] + p[0] * p[2] + p[1] * p[2] - 2.0 * p[0] * p[1] * p[2],
+            vec![differ(p[1], p[2]), differ(p[0], p[2]), differ(p[0], p[1])],
+        ),
+        Buf(_) => (p[0], vec![1.0]),
+        Dff(_) => (0.5, vec![1.0; 3]),
+        Lut(lut) => {
+            // Enumerate the minterms
+            let n = lut.lut.num_vars();
+            let minterm = |m: usize, skip: usize| -> f64 {
+                (0..n)
+                    .filter(|j| *j != skip)
+                    .map(|j| if (m >> j) & 1 != 0 { p[j] } else { 1.0 - p[j] })
+                    .product()
+            };
+            let out = (0..1 << n)
+                .filter(|m| lut.lut.value(*m))
+                .map(|m| minterm(m, n))
+                .sum();
+            let sens = (0..n)
+                .map(|i| {
+                    (0..1 << n)
+                        .filter(|m| {
+                            (m >> i) & 1 == 0 && lut.lut.value(*m) != lut.lut.value(m | 1 << i)
+                        })
+                        .map(|m| minterm(m, i))
+                        .sum()
+                })
+                .collect();
+            (out, sens)
+        }
+    }
+}
+
+impl Cop {
+    /// Compute the COP measures of a network
+    pub fn compute(aig: &Network) -> Cop {
+        Cop::compute_with_test_points(aig, &[])
+    }
+
+    /// Compute the COP measures of a network, as if the given test points were inserted
+    ///
+    /// The measures are those of the original gates. Control points are driven by random inputs.
+    pub(crate) fn compute_with_test_points(aig: &Network, points: &[TestPoint]) -> Cop {
+        let n = aig.nb_nodes();
+        let mut control = vec![None; n];
+        let mut observed = vec![false; n];
+        for p in points {
+            match p {
+                TestPoint::Control { gate, value } => control[*gate] = Some(*value),
+                TestPoint::Observation { gate } => observed[*gate] = true,
+            }
+        }
+
+        // Probability of each gate being one, then of its fanouts seeing a one
+        let mut one_probability = vec![0.0; n];
+        let mut seen = vec![0.0; n];
+        let prob = |s: &Signal, seen: &[f64]| -> f64 {
+            let p = if s.is_constant() {
+                0.0
+            } else if s.is_input() {
+                0.5
+            } else {
+                seen[s.var() as usize]
+            };
+            if s.is_inverted() {
+                1.0 - p
+            } else {
+                p
+            }
+        };
+        let mut sensitization = Vec::new();
+        for i in 0..n {
+            let g = aig.gate(i);
+            let p: Vec<f64> = g.dependencies().iter().map(|s| prob(s, &seen)).collect();
+            let (out, sens) = gate_probabilities(g, &p);
+            one_probability[i] = out;
+            seen[i] = match control[i] {
+                Some(true) => either(out, 0.5),
+                Some(false) => 0.5 * out,
+                None => out,
+            };
+            sensitization.push(sens);
+        }
+
+        // Observability of each gate, from its fanouts
+        let mut observability = vec![0.0; n];
+        let mut fanout_observability = vec![0.0; n];
+        for o in 0..aig.nb_outputs() {
+            let s = aig.output(o);
+            if s.is_var() {
+                fanout_observability[s.var() as usize] = 1.0;
+            }
+        }
+        for i in 0..n {
+            if let Gate::Dff(_) = aig.gate(i) {
+                for v in aig.gate(i).vars() {
+                    fanout_observability[v as usize] = 1.0;
+                }
+            }
+        }
+        for i in (0..n).rev() {
+            observability[i] = if observed[i] {
+                1.0
+            } else if control[i].is_some() {
+                0.5 * fanout_observability[i]
+            } else {
+                fanout_observability[i]
+            };
+            let g = aig.gate(i);
+            if !g.is_comb() {
+                continue;
+            }
+            for (s, sens) in g.dependencies().iter().zip(&sensitization[i]) {
+                if s.is_var() {
+                    let v = s.var() as usize;
+                    fanout_observability[v] =
+                        either(fanout_observability[v], observability[i] * sens);
+                }
+            }
+        }
+
+        Cop {
+            one_probability,
+            observability,
+        }
+    }
+
+    /// Probability that a random pattern detects the output stuck-at fault of a gate
+    pub fn detection_probability(&self, gate: usize, value: bool) -> f64 {
+        let activation = if value {
+            1.0 - self.one_probability[gate]
+        } else {
+            self.one_probability[gate]
+        };
+        activation * self.observability[gate]
+    }
+}
+
+/// Value of SCOAP measures for nodes that cannot be controlled or observed
+pub const SCOAP_INFINITY: usize = usize::MAX;
+
+/// SCOAP testability measures
+///
+/// Combinational measures count the number of gates to set or propagate a value, and sequential
+/// measures the number of clock cycles. Flip-flops are not scanned: they are set through their
+/// data, enable and reset inputs, and observed through their outputs. Unreachable values are
+/// [`SCOAP_INFINITY`].
+#[derive(Clone, Debug)]
+pub struct Scoap {
+    /// Combinational 0-controllability of each node
+    pub cc0: Vec<usize>,
+    /// Combinational 1-controllability of each node
+    pub cc1: Vec<usize>,
+    /// Combinational observability of each node
+    pub co: Vec<usize>,
+    /// Sequential 0-controllability of each node
+    pub sc0: Vec<usize>,
+    /// Sequential 1-controllability of each node
+    pub sc1: Vec<usize>,
+    /// Sequential observability of each node
+    pub so: Vec<usize>,
+}
+
+/// Sum of SCOAP measures, saturating at infinity
+fn scoap_sum(v: impl IntoIterator<Item = usize>) -> usize {
+    v.into_iter().fold(0, |a, b| a.saturating_add(b))
+}
+
+/// String representation of a SCOAP measure
+fn scoap_string(x: usize) -> String {
+    if x == SCOAP_INFINITY {
+        "inf".to_string()
+    } else {
+        x.to_string()
+    }
+}
+
+/// Truth table of a Mux, Maj or Lut gate, with one bit per input in the order of the dependencies
+fn truth_table(g: &Gate) -> Vec<bool> {
+    let bit = |m: usize, i: usize| (m >> i) & 1 != 0;
+    match g {
+        Gate::Ternary(_, TernaryType::Mux) => (0..8)
+            .map(|m| if bit(m, 0) { bit(m, 1) } else { bit(m, 2) })
+            .collect(),
+        Gate::Ternary(_, TernaryType::Maj) => (0..8).map(|m: usize| m.count_ones() >= 2).collect(),
+        Gate::Lut(lut) => (0..lut.lut.num_bits()).map(|m| lut.lut.value(m)).collect(),
+        _ => panic!("No truth table for this gate"),
+    }
+}
+
+/// Maximum number of inputs of a gate for which all cubes are enumerated to compute SCOAP measures
+const MAX_CUBE_INPUTS: usize = 6;
+
+/// Minimum cost of a cube whose minterms all satisfy the predicate
+///
+/// The cost of a cube is the sum of the controllabilities of its literals. All 3^n cubes are
+/// enumerated, and each is checked on its minterms. Above [`MAX_CUBE_INPUTS`] inputs, only
+/// the 2^n minterms are considered, which gives an upper bound on the cost.
+fn min_cube_cost(c: &[(usize, usize)], pred: impl Fn(usize) -> bool) -> usize {
+    let n = c.len();
+    let all = (1usize << n) - 1;
+    let mut best = SCOAP_INFINITY;
+    let cares = if n <= MAX_CUBE_INPUTS {
+        0..=all
+    } else {
+        all..=all
+    };
+    for care in cares {
+        let mut values = care;
+        loop {
+            let cost = scoap_sum((0..n).filter(|i| (care >> i) & 1 != 0).map(|i| {
+                if (values >> i) & 1 != 0 {
+                    c[i].1
+                } else {
+                    c[i].0
+                }
+            }));
+            // A full-care cube is a single minterm
+            let satisfied = || {
+                if care == all {
+                    pred(values)
+                } else {
+                    (0..1usize << n).filter(|m| m & care == values).all(&pred)
+                }
+            };
+            if cost < best && satisfied() {
+                best = cost;
+            }
+            if values == 0 {
+                break;
+            }
+            values = (values - 1) & care;
+        }
+    }
+    best
+}
+
+/// Controllability of the output of a combinational gate, before adding the cost of the gate
+fn gate_controllability(g: &Gate, c: &[(usize, usize)]) -> (usize, usize) {
+    use Gate::*;
+    let min0 = c.iter().map(|x| x.0).min().unwrap_or(SCOAP_INFINITY);
+    let min1 = c.iter().map(|x| x.1).min().unwrap_or(SCOAP_INFINITY);
+    let sum0 = scoap_sum(c.iter().map(|x| x.0));
+    let sum1 = scoap_sum(c.iter().map(|x| x.1));
+    // Cost of an even or odd number of ones
+    let parity = c
+        .iter()
+        .fold((0usize, SCOAP_INFINITY), |(e0, e1), (a0, a1)| {
+            (
+                e0.saturating_add(*a0).min(e1.saturating_add(*a1)),
+                e0.saturating_add(*a1).min(e1.saturating_add(*a0)),
+            )
+        });
+    match g {
+        Binary(_, BinaryType::And) | Ternary(_, TernaryType::And) | Nary(_, NaryType::And) => {
+            (min0, sum1)
+        }
+        Nary(_, NaryType::Nand) => (sum1, min0),
+        Nary(_, NaryType::Or) => (sum0, min1),
+        Nary(_, NaryType::Nor) => (min1, sum0),
+        Binary(_, BinaryType::Xor) | Ternary(_, TernaryType::Xor) | Nary(_, NaryType::Xor) => {
+            parity
+        }
+        Nary(_, NaryType::Xnor) => (parity.1, parity.0),
+        Buf(_) => c[0],
+        Ternary(_, TernaryType::Mux) | Ternary(_, TernaryType::Maj) | Lut(_) => {
+            let tt = truth_table(g);
+            (min_cube_cost(c, |m| !tt[m]), min_cube_cost(c, |m| tt[m]))
+        }
+        Dff(_) => panic!("Flip-flops are handled separately"),
+    }
+}
+
+/// Cost of propagating each input to the output of a combinational gate, before adding the cost of the gate
+fn gate_sensitization(g: &Gate, c: &[(usize, usize)]) -> Vec<usize> {
+    use Gate::*;
+    let others = |f: &dyn Fn(&(usize, usize)) -> usize| -> Vec<usize> {
+        (0..c.len())
+            .map(|i| scoap_sum((0..c.len()).filter(|j| *j != i).map(|j| f(&c[j]))))
+            .collect()
+    };
+    match g {
+        Binary(_, BinaryType::And)
+        | Ternary(_, TernaryType::And)
+        | Nary(_, NaryType::And)
+        | Nary(_, NaryType::Nand) => others(&|x| x.1),
+        Nary(_, NaryType::Or) | Nary(_, NaryType::Nor) => others(&|x| x.0),
+        Binary(_, BinaryType::Xor)
+        | Ternary(_, TernaryType::Xor)
+        | Nary(_, NaryType::Xor)
+        | Nary(_, NaryType::Xnor) => others(&|x| x.0.min(x.1)),
+        Buf(_) => vec![0],
+        Ternary(_, TernaryType::Mux) | Ternary(_, TernaryType::Maj) | Lut(_) => {
+            let tt = truth_table(g);
+            (0..c.len())
+                .map(|i| min_cube_cost(c, |m| tt[m] != tt[m ^ (1 << i)]))
+                .collect()
+        }
+        Dff(_) => panic!("Flip-flops are handled separately"),
+    }
+}
+
+/// Compute SCOAP controllability and observability
+///
+/// Each primary input costs input_cost to control, each combinational gate adds gate_cost and
+/// each flip-flop adds dff_cost. Values are iterated to a fixpoint to handle sequential loops.
+fn scoap_measures(
+    aig: &Network,
+    input_cost: usize,
+    gate_cost: usize,
+    dff_cost: usize,
+) -> (Vec<usize>, Vec<usize>, Vec<usize>) {
+    let n = aig.nb_nodes();
+    let mut cc0 = vec![SCOAP_INFINITY; n];
+    let mut cc1 = vec![SCOAP_INFINITY; n];
+    let controllability = |s: &Signal, cc0: &[usize], cc1: &[usize]| -> (usize, usize) {
+        let c = if s.is_constant() {
+            (0, SCOAP_INFINITY)
+        } else if s.is_input() {
+            (input_cost, input_cost)
+        } else {
+            (cc0[s.var() as usize], cc1[s.var() as usize])
+        };
+        if s.is_inverted() {
+            (c.1, c.0)
+        } else {
+            c
+        }
+    };
+    let inputs = |i: usize, cc0: &[usize], cc1: &[usize]| -> Vec<(usize, usize)> {
+        aig.gate(i)
+            .dependencies()
+            .iter()
+            .map(|s| controllability(s, cc0, cc1))
+            .collect()
+    };
+
+    // Controllability, with flip-flops initially uncontrollable
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in 0..n {
+            let c = inputs(i, &cc0, &cc1);
+            let (c0, c1) = if let Gate::Dff(_) = aig.gate(i) {
+                let (d, en, res) = (c[0], c[1], c[2]);
+                let load = en.1.saturating_add(res.0);
+                (
+                    d.0.saturating_add(load).min(res.1).saturating_add(dff_cost),
+                    d.1.saturating_add(load).saturating_add(dff_cost),
+                )
+            } else {
+                let (c0, c1) = gate_controllability(aig.gate(i), &c);
+                (c0.saturating_add(gate_cost), c1.saturating_add(gate_cost))
+            };
+            if c0 < cc0[i] || c1 < cc1[i] {
+                cc0[i] = cc0[i].min(c0);
+                cc1[i] = cc1[i].min(c1);
+                changed = true;
+            }
+        }
+    }
+
+    // Observability, from the outputs back to the inputs
+    let mut co = vec![SCOAP_INFINITY; n];
+    for o in 0..aig.nb_outputs() {
+        let s = aig.output(o);
+        if s.is_var() {
+            co[s.var() as usize] = 0;
+        }
+    }
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in (0..n).rev() {
+            if co[i] == SCOAP_INFINITY {
+                continue;
+            }
+            let c = inputs(i, &cc0, &cc1);
+            let obs: Vec<usize> = if let Gate::Dff(_) = aig.gate(i) {
+                let (d, en, res) = (c[0], c[1], c[2]);
+                vec![
+                    scoap_sum([en.1, res.0]),
+                    scoap_sum([d.0.min(d.1), res.0]),
+                    scoap_sum([d.1, en.1]),
+                ]
+                .into_iter()
+                .map(|x| scoap_sum([x, co[i], dff_cost]))
+                .collect()
+            } else {
+                gate_sensitization(aig.gate(i), &c)
+                    .into_iter()
+                    .map(|x| scoap_sum([x, co[i], gate_cost]))
+                    .collect()
+            };
+            for (s, o) in aig.gate(i).dependencies().iter().zip(obs) {
+                if s.is_var() && o < co[s.var() as usize] {
+                    co[s.var() as usize] = o;
+                    changed = true;
+                }
+            }
+        }
+    }
+    (cc0, cc1, co)
+}
+
+impl Scoap {
+    /// Compute the SCOAP measures of a network
+    pub fn compute(aig: &Network) -> Scoap {
+        let (cc0, cc1, co) = scoap_measures(aig, 1, 1, 0);
+        let (sc0, sc1, so) = scoap_measures(aig, 0, 0, 1);
+        Scoap {
+            cc0,
+            cc1,
+            co,
+            sc0,
+            sc1,
+            so,
+        }
+    }
+
+    /// Combinational difficulty of testing the output stuck-at faults of a node
+    ///
+    /// This is the worst of CC0 + CO and CC1 + CO.
+    pub fn difficulty(&self, node: usize) -> usize {
+        self.cc0[node]
+            .max(self.cc1[node])
+            .saturating_add(self.co[node])
+    }
+
+    /// Nodes sorted from the hardest to the easiest to test
+    ///
+    /// Nodes are sorted by combinational difficulty, then by sequential difficulty.
+    pub fn hardest_nodes(&self) -> Vec<usize> {
+        let seq = |i: usize| self.sc0[i].max(self.sc1[i]).saturating_add(self.so[i]);
+        let mut ret: Vec<usize> = (0..self.cc0.len()).collect();
+        ret.sort_by_key(|i| std::cmp::Reverse((self.difficulty(*i), seq(*i))));
+        ret
+    }
+
+    /// Short description of the measures of a node, as used in reports
+    pub fn node_summary(&self, node: usize) -> String {
+        format!(
+            "CC {}/{} CO {} SC {}/{} SO {}",
+            scoap_string(self.cc0[node]),
+            scoap_string(self.cc1[node]),
+            scoap_string(self.co[node]),
+            scoap_string(self.sc0[node]),
+            scoap_string(self.sc1[node]),
+            scoap_string(self.so[node])
+        )
+    }
+
+    /// Write the hardest nodes to test, with their measures
+    pub fn write_report<W: Write>(&self, w: &mut W, aig: &Network, nb_nodes: usize) {
+        writeln!(w, "Node\tType\tCC0\tCC1\tCO\tSC0\tSC1\tSO").unwrap();
+        for i in self.hardest_nodes().into_iter().take(nb_nodes) {
+            writeln!(
+                w,
+                "x{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                i,
+                aig.gate(i).type_name(),
+                scoap_string(self.cc0[i]),
+                scoap_string(self.cc1[i]),
+                scoap_string(self.co[i]),
+                scoap_string(self.sc0[i]),
+                scoap_string(self.sc1[i]),
+                scoap_string(self.so[i])
+            )
+            .unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use volute::Lut;
+
+    use super::{Cop, Scoap, SCOAP_INFINITY};
+    use crate::network::NaryType;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_cop() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let a = aig.add(Gate::and(i0, i1));
+        let o = aig.add(Gate::Nary([a, i2].into(), NaryType::Or));
+        let l = aig.add(Gate::lut(
+            &[i0, i1],
+            Lut::nth_var(2, 0).and(&Lut::nth_var(2, 1)),
+        ));
+        let m = aig.add(Gate::mux(i2, !a, l));
+        aig.add_output(o);
+        aig.add_output(m);
+        let cop = Cop::compute(&aig);
+        assert_eq!(cop.one_probability[a.var() as usize], 0.25);
+        assert_eq!(cop.one_probability[o.var() as usize], 0.625);
+        assert_eq!(cop.one_probability[l.var() as usize], 0.25);
+        assert_eq!(cop.one_probability[m.var() as usize], 0.5);
+        // The And is observed through the Or when i2 is zero, and through the Mux when i2 is one
+        assert_eq!(cop.observability[a.var() as usize], 0.75);
+        assert_eq!(cop.observability[l.var() as usize], 0.5);
+        assert_eq!(cop.detection_probability(a.var() as usize, false), 0.1875);
+    }
+
+    #[test]
+    fn test_scoap_comb() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let a = aig.add(Gate::and(i0, i1));
+        let o = aig.add(Gate::Nary([a, i2].into(), NaryType::Or));
+        let m = aig.add(Gate::mux(i2, !a, i0));
+        let l = aig.add(Gate::lut(
+            &[i0, i1],
+            Lut::nth_var(2, 0).xor(&Lut::nth_var(2, 1)),
+        ));
+        aig.add_output(o);
+        aig.add_output(m);
+        aig.add_output(l);
+        let scoap = Scoap::compute(&aig);
+        let a = a.var() as usize;
+        assert_eq!((scoap.cc0[a], scoap.cc1[a]), (2, 3));
+        assert_eq!((scoap.cc0[1], scoap.cc1[1]), (4, 2));
+        // The Mux selects the And with i2 = 1
+        assert_eq!((scoap.cc0[2], scoap.cc1[2]), (3, 3));
+        assert_eq!((scoap.cc0[3], scoap.cc1[3]), (3, 3));
+        // Observed through the Or with i2 = 0, or through the Mux with i2 = 1
+        assert_eq!(scoap.co[a], 2);
+        assert_eq!(scoap.co[3], 0);
+        assert_eq!(scoap.sc0[a], 0);
+        assert_eq!(scoap.so[a], 0);
+        assert_eq!(scoap.hardest_nodes()[0], a);
+    }
+
+    #[test]
+    fn test_scoap_wide_lut() {
+        // And of 12 inputs: above the limit, only minterms are considered for the zero value
+        let mut aig = Network::new();
+        let inputs: Vec<_> = (0..12).map(|_| aig.add_input()).collect();
+        let mut lut = Lut::one(12);
+        for i in 0..12 {
+            lut = lut.and(&Lut::nth_var(12, i));
+        }
+        let l = aig.add(Gate::lut(&inputs, lut));
+        aig.add_output(l);
+        let scoap = Scoap::compute(&aig);
+        assert_eq!(scoap.cc1[0], 13);
+        assert!(scoap.cc0[0] >= 2 && scoap.cc0[0] <= 13);
+        assert_eq!(scoap.co[0], 0);
+    }
+
+    #[test]
+    fn test_scoap_seq() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let d0 = aig.dff(i0, i1, Signal::zero());
+        let d1 = aig.dff(d0, Signal::one(), i1);
+        // Flip-flop that can never leave its initial state
+        let d2 = aig.add(Gate::dff(
+            Signal::placeholder(),
+            Signal::one(),
+            Signal::zero(),
+        ));
+        aig.replace(
+            d2.var() as usize,
+            Gate::dff(d2, Signal::one(), Signal::zero()),
+        );
+        let x = aig.and(d1, d2);
+        aig.add_output(x);
+        let scoap = Scoap::compute(&aig);
+        assert_eq!((scoap.sc0[0], scoap.sc1[0]), (1, 1));
+        // Reset sets the second flip-flop to zero directly
+        assert_eq!((scoap.sc0[1], scoap.sc1[1]), (1, 2));
+        assert_eq!((scoap.cc0[1], scoap.cc1[1]), (1, 3));
+        assert_eq!(scoap.cc1[2], SCOAP_INFINITY);
+        // The third flip-flop is stuck at zero and blocks the And: the first two flip-flops are
+        // unobservable, while the third one is observed when the second one is one
+        assert_eq!(scoap.co[1], SCOAP_INFINITY);
+        assert_eq!(scoap.so[0], SCOAP_INFINITY);
+        assert_eq!(scoap.co[2], 4);
+    }
+}
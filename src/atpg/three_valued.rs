@@ -0,0 +1,267 @@
+use crate::equiv::prove;
+use crate::network::{BinaryType, NaryType, TernaryType};
+use crate::sim::{detects_faults_x, Fault};
+use crate::{Gate, Network, Signal};
+
+use super::faulty_network;
+
+/// Dual-rail encoding of a signal in 3-valued logic: whether its value may be 0, and may be 1
+#[derive(Clone, Copy, Debug)]
+struct Rails {
+    zero: Signal,
+    one: Signal,
+}
+
+impl Rails {
+    fn known(s: Signal) -> Rails {
+        Rails { zero: !s, one: s }
+    }
+
+    fn unknown() -> Rails {
+        Rails {
+            zero: Signal::one(),
+            one: Signal::one(),
+        }
+    }
+
+    fn invert(self, inv: bool) -> Rails {
+        if inv {
+            Rails {
+                zero: self.one,
+                one: self.zero,
+            }
+        } else {
+            self
+        }
+    }
+}
+
+fn or(aig: &mut Network, v: Vec<Signal>) -> Signal {
+    aig.add_canonical(Gate::Nary(v.into(), NaryType::Or))
+}
+
+fn and(aig: &mut Network, v: Vec<Signal>) -> Signal {
+    aig.add_canonical(Gate::Nary(v.into(), NaryType::And))
+}
+
+fn and_rails(aig: &mut Network, v: &[Rails]) -> Rails {
+    Rails {
+        zero: or(aig, v.iter().map(|r| r.zero).collect()),
+        one: and(aig, v.iter().map(|r| r.one).collect()),
+    }
+}
+
+fn xor_rails(aig: &mut Network, v: &[Rails]) -> Rails {
+    let mut ret = Rails::known(Signal::zero());
+    for r in v {
+        let same_0 = aig.and(ret.zero, r.zero);
+        let same_1 = aig.and(ret.one, r.one);
+        let diff_0 = aig.and(ret.zero, r.one);
+        let diff_1 = aig.and(ret.one, r.zero);
+        ret = Rails {
+            zero: or(aig, vec![same_0, same_1]),
+            one: or(aig, vec![diff_0, diff_1]),
+        };
+    }
+    ret
+}
+
+/// Rails of a Lut: each value is possible if a row of the truth table with this value may be selected
+fn lut_rails(aig: &mut Network, lut: &volute::Lut, v: &[Rails]) -> Rails {
+    let mut zero = Vec::new();
+    let mut one = Vec::new();
+    for mask in 0..lut.num_bits() {
+        let selected = v
+            .iter()
+            .enumerate()
+            .map(|(i, r)| if (mask >> i) & 1 != 0 { r.one } else { r.zero })
+            .collect();
+        let selected = and(aig, selected);
+        if lut.value(mask) {
+            one.push(selected);
+        } else {
+            zero.push(selected);
+        }
+    }
+    Rails {
+        zero: or(aig, zero),
+        one: or(aig, one),
+    }
+}
+
+/// Add the dual-rail encoding of a combinatorial network, with the X sources as unknown values
+///
+/// Returns the rails of the outputs. This matches the 3-valued simulation of the network.
+fn add_dual_rail(ret: &mut Network, aig: &Network, x_inputs: &[usize]) -> Vec<Rails> {
+    assert!(aig.is_comb());
+    let mut nodes: Vec<Rails> = Vec::new();
+    let rails = |nodes: &Vec<Rails>, s: &Signal| -> Rails {
+        let r = if s.without_inversion() == Signal::zero() {
+            Rails::known(Signal::zero())
+        } else if s.is_input() {
+            if x_inputs.contains(&(s.input() as usize)) {
+                Rails::unknown()
+            } else {
+                Rails::known(Signal::from_input(s.input()))
+            }
+        } else {
+            nodes[s.var() as usize]
+        };
+        r.invert(s.is_inverted())
+    };
+    for i in 0..aig.nb_nodes() {
+        let g = aig.gate(i);
+        let v: Vec<Rails> = g.dependencies().iter().map(|s| rails(&nodes, s)).collect();
+        let r = match g {
+            Gate::Binary(_, BinaryType::And) | Gate::Ternary(_, TernaryType::And) => {
+                and_rails(ret, &v)
+            }
+            Gate::Binary(_, BinaryType::Xor) | Gate::Ternary(_, TernaryType::Xor) => {
+                xor_rails(ret, &v)
+            }
+            Gate::Ternary(_, TernaryType::Maj) => {
+                let [a, b, c] = [v[0], v[1], v[2]];
+                let maj = |aig: &mut Network, a: Signal, b: Signal, c: Signal| {
+                    let ab = aig.and(a, b);
+                    let ac = aig.and(a, c);
+                    let bc = aig.and(b, c);
+                    or(aig, vec![ab, ac, bc])
+                };
+                Rails {
+                    zero: maj(ret, a.zero, b.zero, c.zero),
+                    one: maj(ret, a.one, b.one, c.one),
+                }
+            }
+            Gate::Ternary(_, TernaryType::Mux) => {
+                let [s, a, b] = [v[0], v[1], v[2]];
+                let sa_0 = ret.and(s.one, a.zero);
+                let sb_0 = ret.and(s.zero, b.zero);
+                let sa_1 = ret.and(s.one, a.one);
+                let sb_1 = ret.and(s.zero, b.one);
+                Rails {
+                    zero: or(ret, vec![sa_0, sb_0]),
+                    one: or(ret, vec![sa_1, sb_1]),
+                }
+            }
+            Gate::Nary(_, tp) => {
+                let inv: Vec<Rails> = v.iter().map(|r| r.invert(true)).collect();
+                match tp {
+                    NaryType::And => and_rails(ret, &v),
+                    NaryType::Or => and_rails(ret, &inv).invert(true),
+                    NaryType::Nand => and_rails(ret, &v).invert(true),
+                    NaryType::Nor => and_rails(ret, &inv),
+                    NaryType::Xor => xor_rails(ret, &v),
+                    NaryType::Xnor => xor_rails(ret, &v).invert(true),
+                }
+            }
+            Gate::Buf(_) => v[0],
+            Gate::Lut(lut) => lut_rails(ret, &lut.lut, &v),
+            Gate::Dff(_) => panic!("Flip-flops are not supported in the dual-rail encoding"),
+        };
+        nodes.push(r);
+    }
+    (0..aig.nb_outputs())
+        .map(|o| rails(&nodes, &aig.output(o)))
+        .collect()
+}
+
+/// Find a test pattern for a fault with some inputs unknown, using a SAT solver
+///
+/// The network with and without the fault is encoded in dual-rail form, and an output must take
+/// known and different values. The values returned for the X sources are arbitrary.
+pub(super) fn find_pattern_detecting_fault_x(
+    aig: &Network,
+    fault: Fault,
+    x_inputs: &[usize],
+) -> Option<Vec<bool>> {
+    assert!(aig.is_comb());
+    let fault_aig = faulty_network(aig, fault);
+    let mut miter = Network::new();
+    miter.add_inputs(aig.nb_inputs());
+    let good = add_dual_rail(&mut miter, aig, x_inputs);
+    let bad = add_dual_rail(&mut miter, &fault_aig, x_inputs);
+    let mut detected = Vec::new();
+    for (g, b) in good.iter().zip(&bad) {
+        // Known 0 in one network and known 1 in the other
+        let g0_b1 = and(&mut miter, vec![g.zero, !g.one, b.one, !b.zero]);
+        let g1_b0 = and(&mut miter, vec![g.one, !g.zero, b.zero, !b.one]);
+        detected.push(g0_b1);
+        detected.push(g1_b0);
+    }
+    let o = or(&mut miter, detected);
+    miter.add_output(o);
+    miter.make_canonical();
+    miter.cleanup();
+    let ret = prove(&miter);
+    if let Some(pattern) = &ret {
+        assert_eq!(
+            detects_faults_x(aig, pattern, x_inputs, &[fault]),
+            vec![true]
+        );
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_pattern_detecting_fault_x;
+    use crate::sim::{detects_faults_x, simulate_comb_x, Fault};
+    use crate::{Gate, Network};
+
+    #[test]
+    fn test_x_simulation() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let a = aig.and(i0, i1);
+        let m = aig.add(Gate::mux(i2, i0, i0));
+        let x = aig.xor(i1, i2);
+        aig.add_output(a);
+        aig.add_output(m);
+        aig.add_output(x);
+        // A known 0 controls the And; the Mux is known when both data inputs agree
+        assert_eq!(
+            simulate_comb_x(&aig, &[Some(false), None, None]),
+            vec![Some(false), Some(false), None]
+        );
+        assert_eq!(
+            simulate_comb_x(&aig, &[Some(true), None, Some(true)]),
+            vec![None, Some(true), None]
+        );
+    }
+
+    #[test]
+    fn test_x_atpg() {
+        // The Xor with the X source hides everything behind it
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let a = aig.and(i0, i1);
+        let x = aig.xor(a, i2);
+        let o = aig.and(a, !i2);
+        aig.add_output(x);
+        aig.add_output(o);
+        let x_inputs = [2];
+        let sa0 = Fault::OutputStuckAtFault {
+            gate: 0,
+            value: false,
+        };
+        let xor_sa1 = Fault::OutputStuckAtFault {
+            gate: 1,
+            value: true,
+        };
+        // Detected through the And, which needs i2 to be a known 0
+        assert!(find_pattern_detecting_fault_x(&aig, sa0, &[]).is_some());
+        assert!(find_pattern_detecting_fault_x(&aig, sa0, &x_inputs).is_none());
+        assert!(find_pattern_detecting_fault_x(&aig, xor_sa1, &x_inputs).is_none());
+        // Without X source, the pattern detects it
+        let p = vec![true, false, false];
+        assert_eq!(detects_faults_x(&aig, &p, &[], &[xor_sa1]), vec![true]);
+        assert_eq!(
+            detects_faults_x(&aig, &p, &x_inputs, &[xor_sa1]),
+            vec![false]
+        );
+    }
+}
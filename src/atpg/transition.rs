@@ -0,0 +1,418 @@
+use std::iter::zip;
+
+use crate::equiv::{difference, prove, unroll_with_init, InitialState};
+use crate::network::stats;
+use crate::sim::{simulate_comb, simulate_nodes_multi, Fault, ParallelFaultSimulator};
+use crate::{Gate, Network, Signal};
+
+use super::{expose_dff, find_pattern_detecting_fault, TestPatternGenerator};
+
+/// How the transition is launched when testing delay faults in a scan design
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LaunchMode {
+    /// Launch-on-capture: the second frame starts from the state captured at the end of the first one
+    LaunchOnCapture,
+    /// Launch-on-shift: the second frame starts from the first state, shifted by one flip-flop in the scan chain
+    LaunchOnShift,
+}
+
+/// Two-frame unrolling of a network with exposed flip-flops, used to test transition faults
+///
+/// The inputs are the primary inputs and flip-flop values of the first frame, then the primary
+/// inputs of the second frame, then the scan input for launch-on-shift.
+/// The outputs are the outputs of the second frame, including the flip-flop inputs.
+/// Each gate of the original network has a copy in each frame, which makes fault injection easy.
+pub struct TwoFrameNetwork {
+    network: Network,
+    state_network: Network,
+    nb_inputs: usize,
+    nb_dffs: usize,
+    nb_gates: usize,
+    offset: usize,
+}
+
+impl TwoFrameNetwork {
+    /// Build the two-frame network from a sequential network
+    ///
+    /// The frames are obtained with [`unroll_with_init`] on a copy of the network with exposed
+    /// flip-flops, where each flip-flop output goes through a buffer so that it has a copy in
+    /// each frame. For launch-on-capture, the flip-flops keep their functionality and the second
+    /// state is captured as usual. For launch-on-shift, the second state is not computed by the
+    /// design but by the scan chain, so the flip-flops are rewired as a shift register fed by the
+    /// scan input.
+    pub fn from(aig: &Network, mode: LaunchMode) -> TwoFrameNetwork {
+        let exposed = expose_dff(aig);
+        let dffs = stats::dff_gates(aig);
+        let nb_inputs = aig.nb_inputs();
+        let nb_dffs = dffs.len();
+        let nb_gates = aig.nb_nodes();
+
+        // Sequential network with the flip-flops first, then the gates of the exposed network
+        let t = |s: &Signal| -> Signal {
+            if s.is_input() && s.input() as usize >= nb_inputs {
+                Signal::from_var(s.input() - nb_inputs as u32) ^ s.is_inverted()
+            } else if s.is_var() {
+                Signal::from_var(s.var() + nb_dffs as u32) ^ s.is_inverted()
+            } else {
+                *s
+            }
+        };
+        let mut seq = Network::new();
+        seq.add_inputs(nb_inputs);
+        if mode == LaunchMode::LaunchOnShift {
+            seq.add_input();
+        }
+        for (i, dff) in dffs.iter().enumerate() {
+            let g = match mode {
+                LaunchMode::LaunchOnCapture => aig.gate(*dff).remap(t),
+                LaunchMode::LaunchOnShift => {
+                    let prev = if i == 0 {
+                        seq.input(nb_inputs)
+                    } else {
+                        Signal::from_var(i as u32 - 1)
+                    };
+                    Gate::dff(prev, Signal::one(), Signal::zero())
+                }
+            };
+            seq.add(g);
+        }
+        for i in 0..nb_gates {
+            seq.add(exposed.gate(i).remap(t));
+        }
+        for o in 0..exposed.nb_outputs() {
+            seq.add_output(t(&exposed.output(o)));
+        }
+
+        // Flip-flops become free inputs in the first frame, and are skipped in the numbering of
+        // the gates: the first frame has the same gate indices as the exposed network, and the
+        // second frame comes last
+        let unrolled = unroll_with_init(&seq, 2, &InitialState::Arbitrary);
+        let offset = unrolled.nb_nodes() - nb_gates;
+
+        // Reorder the inputs and only keep the outputs of the second frame
+        let mut ret = Network::new();
+        ret.add_inputs(2 * nb_inputs + nb_dffs);
+        let scan_in = match mode {
+            LaunchMode::LaunchOnCapture => None,
+            LaunchMode::LaunchOnShift => Some(ret.add_input()),
+        };
+        let nb_seq_inputs = seq.nb_inputs();
+        let input_map: Vec<Signal> = (0..unrolled.nb_inputs())
+            .map(|i| {
+                if i >= 2 * nb_seq_inputs {
+                    ret.input(nb_inputs + i - 2 * nb_seq_inputs)
+                } else if i % nb_seq_inputs == nb_inputs {
+                    scan_in.unwrap()
+                } else if i < nb_seq_inputs {
+                    ret.input(i)
+                } else {
+                    ret.input(nb_inputs + nb_dffs + i - nb_seq_inputs)
+                }
+            })
+            .collect();
+        let t = |s: &Signal| -> Signal {
+            if s.is_input() {
+                input_map[s.input() as usize] ^ s.is_inverted()
+            } else {
+                *s
+            }
+        };
+        for i in 0..unrolled.nb_nodes() {
+            ret.add(unrolled.gate(i).remap(t));
+        }
+        for o in exposed.nb_outputs()..unrolled.nb_outputs() {
+            ret.add_output(t(&unrolled.output(o)));
+        }
+
+        // State at the start of the second frame, read from the flip-flop buffers
+        let mut state_network = Network::new();
+        state_network.add_inputs(ret.nb_inputs());
+        for i in 0..offset {
+            state_network.add(ret.gate(i).clone());
+        }
+        for dff in &dffs {
+            let Gate::Buf(s) = ret.gate(dff + offset) else {
+                unreachable!()
+            };
+            state_network.add_output(*s);
+        }
+
+        ret.check();
+        TwoFrameNetwork {
+            network: ret,
+            state_network,
+            nb_inputs,
+            nb_dffs,
+            nb_gates,
+            offset,
+        }
+    }
+
+    /// Combinatorial two-frame network
+    pub fn network(&self) -> &Network {
+        &self.network
+    }
+
+    /// Convert a pattern of the two-frame network to two timesteps for the network with exposed flip-flops
+    pub fn to_patterns(&self, pattern: &Vec<bool>) -> Vec<Vec<bool>> {
+        let nb_first = self.nb_inputs + self.nb_dffs;
+        let first = pattern[..nb_first].to_vec();
+        let mut second = pattern[nb_first..nb_first + self.nb_inputs].to_vec();
+        second.extend(simulate_comb(&self.state_network, pattern));
+        vec![first, second]
+    }
+
+    /// Translate a fault of the original network to its copy in the second frame
+    fn second_frame_fault(&self, fault: Fault) -> Fault {
+        assert!(fault.gates().iter().all(|g| *g < self.nb_gates));
+        match fault {
+            Fault::OutputStuckAtFault { gate, value } => Fault::OutputStuckAtFault {
+                gate: gate + self.offset,
+                value,
+            },
+            Fault::InputStuckAtFault { gate, input, value } => Fault::InputStuckAtFault {
+                gate: gate + self.offset,
+                input,
+                value,
+            },
+            Fault::TransitionFault { gate, value } => Fault::OutputStuckAtFault {
+                gate: gate + self.offset,
+                value: !value,
+            },
+            Fault::BridgingFault { a, b, kind } => Fault::BridgingFault {
+                a: a + self.offset,
+                b: b + self.offset,
+                kind,
+            },
+            Fault::LutBitFlipFault { gate, bit } => Fault::LutBitFlipFault {
+                gate: gate + self.offset,
+                bit,
+            },
+            Fault::DefectFault { gate, table } => Fault::DefectFault {
+                gate: gate + self.offset,
+                table,
+            },
+        }
+    }
+
+    /// Analyze which of a set of patterns detect the given faults of the original network
+    ///
+    /// A transition fault is detected if the first frame sets the gate to its initial value, and
+    /// the second frame detects the corresponding stuck-at fault.
+    ///
+    /// The fault simulator must be built on the two-frame network.
+    pub(crate) fn detects_faults_multi(
+        &self,
+        sim: &mut ParallelFaultSimulator,
+        pattern: &Vec<u64>,
+        faults: &Vec<Fault>,
+    ) -> Vec<u64> {
+        let node_values = simulate_nodes_multi(&self.network, pattern);
+        let translated: Vec<Fault> = faults.iter().map(|f| self.second_frame_fault(*f)).collect();
+        let detections = sim
+            .detects_faults_multi(std::slice::from_ref(pattern), &translated)
+            .pop()
+            .unwrap();
+        zip(faults, detections)
+            .map(|(f, d)| match f {
+                Fault::TransitionFault { gate, value } => {
+                    let v = node_values[*gate];
+                    d & if *value { !v } else { v }
+                }
+                _ => d,
+            })
+            .collect()
+    }
+
+    /// Find a new test pattern for a specific fault of the original network using a SAT solver
+    pub(crate) fn find_pattern_detecting_fault(&self, fault: Fault) -> Option<Vec<bool>> {
+        let Fault::TransitionFault { gate, value } = fault else {
+            return find_pattern_detecting_fault(&self.network, self.second_frame_fault(fault));
+        };
+        let mut fault_aig = self.network.clone();
+        fault_aig.replace(gate + self.offset, Gate::Buf(Signal::from(!value)));
+
+        // The first frame must set the initial value of the transition
+        let mut init = Network::new();
+        init.add_inputs(self.network.nb_inputs());
+        for i in 0..self.nb_gates {
+            init.add(self.network.gate(i).clone());
+        }
+        init.add_output(Signal::from_var(gate as u32) ^ value);
+
+        let mut diff = difference(&self.network, &fault_aig, Some(&init));
+        diff.make_canonical();
+        diff.cleanup();
+        let ret = prove(&diff);
+        if let Some(pattern) = &ret {
+            let multi_pattern = pattern.iter().map(|b| if *b { !0 } else { 0 }).collect();
+            assert_eq!(
+                self.detects_faults_multi(
+                    &mut ParallelFaultSimulator::new(&self.network, 1),
+                    &multi_pattern,
+                    &vec![fault]
+                ),
+                vec![!0]
+            );
+        }
+        ret
+    }
+}
+
+/// Generate test patterns for transition faults
+///
+/// Each pattern has two timesteps, on the network with exposed flip-flops: the first one sets
+/// the initial value, and the second one launches the transition and observes it.
+/// Fault simulation uses the given number of threads, or all available cores for 0.
+pub fn generate_transition_test_patterns(
+    aig: &Network,
+    mode: LaunchMode,
+    seed: u64,
+    nb_threads: usize,
+) -> Vec<Vec<Vec<bool>>> {
+    let faults = Fault::all_transition(aig);
+    let two_frame = TwoFrameNetwork::from(aig, mode);
+
+    println!(
+        "Analyzing network with {} inputs, {} outputs, {} gates, {} transition faults",
+        aig.nb_inputs(),
+        aig.nb_outputs(),
+        aig.nb_nodes(),
+        faults.len(),
+    );
+
+    let mut gen = TestPatternGenerator::from_two_frame(&two_frame, faults, seed);
+    gen.fault_sim = ParallelFaultSimulator::new(two_frame.network(), nb_threads);
+    gen.detect_faults();
+    gen.finish();
+    gen.patterns
+        .iter()
+        .map(|p| two_frame.to_patterns(p))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_transition_test_patterns, LaunchMode, TwoFrameNetwork};
+    use crate::atpg::expose_dff;
+    use crate::sim::{simulate_with_faults, Fault, ParallelFaultSimulator};
+    use crate::{Network, Signal};
+
+    /// Analyze which faults are detected by two timesteps of a network with exposed flip-flops
+    fn detects_faults_two_frames(
+        exposed: &Network,
+        frames: &[Vec<bool>],
+        faults: &Vec<Fault>,
+    ) -> Vec<u64> {
+        // Without flip-flops, the second frame is given by the second timestep
+        let unrolled = TwoFrameNetwork::from(exposed, LaunchMode::LaunchOnCapture);
+        let mut sim = ParallelFaultSimulator::new(unrolled.network(), 1);
+        let pattern = frames[0]
+            .iter()
+            .chain(&frames[1])
+            .map(|b| if *b { !0 } else { 0 })
+            .collect();
+        unrolled.detects_faults_multi(&mut sim, &pattern, faults)
+    }
+
+    /// Shift register of two flip-flops, with an And gate between them
+    fn shift_register() -> Network {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let d0 = aig.dff(i0, Signal::one(), Signal::zero());
+        let x = aig.and(d0, i1);
+        let d1 = aig.dff(x, Signal::one(), Signal::zero());
+        aig.add_output(d1);
+        aig
+    }
+
+    #[test]
+    fn test_transition_simulation() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let x = aig.and(i0, i1);
+        aig.add_output(x);
+        let rise = vec![Fault::TransitionFault {
+            gate: 0,
+            value: true,
+        }];
+        let fall = vec![Fault::TransitionFault {
+            gate: 0,
+            value: false,
+        }];
+        let rising = vec![vec![false, true], vec![true, true]];
+        let falling = vec![vec![true, true], vec![true, false]];
+        assert_eq!(
+            simulate_with_faults(&aig, &rising, &rise),
+            vec![vec![false], vec![false]]
+        );
+        assert_eq!(
+            simulate_with_faults(&aig, &rising, &fall),
+            vec![vec![false], vec![true]]
+        );
+        assert_eq!(
+            simulate_with_faults(&aig, &falling, &fall),
+            vec![vec![true], vec![true]]
+        );
+        assert_eq!(
+            simulate_with_faults(&aig, &falling, &rise),
+            vec![vec![true], vec![false]]
+        );
+    }
+
+    #[test]
+    fn test_launch_on_capture() {
+        let aig = shift_register();
+        let tf = TwoFrameNetwork::from(&aig, LaunchMode::LaunchOnCapture);
+        assert_eq!(tf.network().nb_inputs(), 6);
+        let exposed = expose_dff(&aig);
+        for gate in 0..aig.nb_nodes() {
+            for value in [false, true] {
+                let fault = Fault::TransitionFault { gate, value };
+                let pattern = tf.find_pattern_detecting_fault(fault).unwrap();
+                // The launch state is captured from the first frame
+                let frames = tf.to_patterns(&pattern);
+                assert_eq!(frames[1][2], pattern[0]);
+                let det = detects_faults_two_frames(&exposed, &frames, &vec![fault]);
+                assert_eq!(det, vec![!0]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_launch_on_shift() {
+        let aig = shift_register();
+        let tf = TwoFrameNetwork::from(&aig, LaunchMode::LaunchOnShift);
+        assert_eq!(tf.network().nb_inputs(), 7);
+        let fault = Fault::TransitionFault {
+            gate: 0,
+            value: true,
+        };
+        let pattern = tf.find_pattern_detecting_fault(fault).unwrap();
+        let frames = tf.to_patterns(&pattern);
+        // The second flip-flop takes the value of the first one, and the first one the scan input
+        assert_eq!(frames[1][2], pattern[6]);
+        assert_eq!(frames[1][3], pattern[2]);
+        assert!(!frames[0][2] && frames[1][2]);
+    }
+
+    #[test]
+    fn test_transition_generation() {
+        let aig = shift_register();
+        let exposed = expose_dff(&aig);
+        let faults = Fault::all_transition(&aig);
+        for mode in [LaunchMode::LaunchOnCapture, LaunchMode::LaunchOnShift] {
+            let patterns = generate_transition_test_patterns(&aig, mode, 1, 1);
+            let mut detected = vec![0; faults.len()];
+            for p in &patterns {
+                let det = detects_faults_two_frames(&exposed, p, &faults);
+                for (d, n) in detected.iter_mut().zip(det) {
+                    *d |= n;
+                }
+            }
+            assert!(detected.iter().all(|d| *d != 0));
+        }
+    }
+}
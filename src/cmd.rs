@@ -1,14 +1,31 @@
 //! Command line interface
 
 use crate::atpg::{
-    expose_dff, generate_comb_test_patterns, generate_random_seq_patterns,
-    report_comb_test_patterns,
+    add_compactor, analyze_compaction, best_bist_seed, bist_coverage, bist_patterns,
+    bist_reseeding, diagnose, expose_dff, expose_dff_unobserved, generate_comb_test_patterns,
+    generate_incremental_comb_test_patterns, generate_path_delay_tests,
+    generate_random_seq_patterns, generate_seq_test_patterns, generate_transition_test_patterns,
+    insert_scan_chains, insert_test_points, report_comb_test_patterns, select_test_points,
+    test_point_coverage, AtpgOptions, BistSource, Compaction, Compactor, LaunchMode, PathStatus,
+    ScanOrder, Scoap,
 };
-use crate::equiv::check_equivalence_bounded;
-use crate::io::{read_network_file, read_pattern_file, write_network_file, write_pattern_file};
+use crate::equiv::{
+    check_drat, check_equivalence_bounded_portfolio, check_equivalence_bounded_resolved,
+    check_equivalence_bounded_with_init, check_equivalence_correspondence,
+    check_equivalence_partitioned, explain_counterexample, group_outputs_by_cone,
+    group_outputs_individually, match_registers_by_name, match_registers_by_order,
+    match_registers_by_simulation, InitialState, OutputStatus, SequentialCounterexample, Strategy,
+};
+use crate::io::{
+    read_dff_names_file, read_dimacs, read_drat, read_network_file, read_pattern_file,
+    write_annotated_dot_file, write_dimacs, write_drat, write_network_file, write_pattern_file,
+};
+use crate::network::stats;
 use crate::optim;
-use crate::sim::simulate;
-use clap::{Args, Parser, Subcommand};
+use crate::sim::{analyze_seu, simulate, DefectLibrary, Fault};
+use crate::Network;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::fs::File;
 use std::path::PathBuf;
 
 /// Command line arguments
@@ -44,6 +61,14 @@ pub enum Commands {
     #[clap(visible_alias = "sim")]
     Simulate(SimulateArgs),
 
+    /// Analyze the vulnerability of a logic network to soft errors
+    ///
+    /// Single-event upsets are injected on each node and flip-flop state during random
+    /// simulations. Reports the fraction of upsets that reach an output within a few cycles
+    /// (architectural vulnerability factor), and ranks the flip-flops to harden.
+    #[clap()]
+    Seu(SeuArgs),
+
     /// Test pattern generation for a logic network
     ///
     /// Generate patterns to find all possible faults in a design, assuming
@@ -57,9 +82,53 @@ pub enum Commands {
     Atpg(AtpgArgs),
 
     /// Analyze the results of test pattern generation
-    #[clap(hide = true)]
     AtpgReport(AtpgReportArgs),
 
+    /// Diagnose a failing circuit from its tester responses
+    ///
+    /// Each fault is simulated against the applied patterns and compared to the observed
+    /// responses. Candidates are ranked by how well they match the failing and passing bits.
+    #[clap()]
+    Diagnose(DiagnoseArgs),
+
+    /// Insert scan chains in a sequential logic network
+    ///
+    /// Flip-flops are replaced by mux-scan flip-flops with a shared scan enable, and stitched
+    /// into balanced chains with their own scan-in and scan-out. Test patterns generated on
+    /// the combinational view can be translated into serial scan sequences.
+    #[clap()]
+    Scan(ScanArgs),
+
+    /// Simulate logic built-in self-test with pseudo-random patterns
+    ///
+    /// Patterns come from an LFSR shifted into the inputs or from a cellular automaton. Reports
+    /// the fault coverage as a function of the number of patterns, and optionally selects the
+    /// seed and the reseeding points to detect the faults that random patterns miss.
+    #[clap()]
+    Bist(BistArgs),
+
+    /// Add a test response compactor to a logic network
+    ///
+    /// The outputs are compacted by a Xor space compactor or a multiple-input signature register
+    /// (MISR). Given test patterns, reports the aliasing of the compactor and the faults that
+    /// are no longer detected after compaction.
+    #[clap()]
+    Compactor(CompactorArgs),
+
+    /// Compute SCOAP testability measures for a logic network
+    ///
+    /// Reports the combinational and sequential controllability and observability of the
+    /// nodes that are the hardest to test, without running test pattern generation.
+    #[clap()]
+    Testability(TestabilityArgs),
+
+    /// Insert test points to improve fault coverage with random patterns
+    ///
+    /// Random-pattern-resistant faults are found with COP testability measures. Control points
+    /// (And/Or with a new input) and observation points (new outputs) are inserted to target them.
+    #[clap()]
+    TestPoints(TestPointArgs),
+
     /// Check equivalence between two logic networks
     ///
     /// The command will fail if the two networks are not equivalent, and will output the
@@ -67,6 +136,13 @@ pub enum Commands {
     #[clap(visible_alias = "equiv")]
     CheckEquivalence(EquivArgs),
 
+    /// Check a DRAT proof of unsatisfiability for a DIMACS formula
+    ///
+    /// This validates the proof certificates written by the equivalence checking command,
+    /// without relying on the Sat solver.
+    #[clap()]
+    CheckProof(CheckProofArgs),
+
     /// Read a logic network and write it in another format
     #[clap()]
     Convert(ConvertArgs),
@@ -87,6 +163,213 @@ pub struct EquivArgs {
     /// Use only the Sat solver, skipping internal optimizations
     #[arg(long)]
     sat_only: bool,
+
+    /// Initial state of the first network: zero, arbitrary, or one 0/1 value per flip-flop
+    #[arg(long, default_value = "zero")]
+    init1: String,
+
+    /// Initial state of the second network: zero, arbitrary, or one 0/1 value per flip-flop
+    #[arg(long, default_value = "zero")]
+    init2: String,
+
+    /// Attempt an unbounded proof, based on a correspondence between the flip-flops of the two networks
+    #[arg(long)]
+    correspondence: Option<RegisterMatching>,
+
+    /// Constraint network, with the same inputs and a single output that is true for legal input values
+    #[arg(long)]
+    constraint: Option<PathBuf>,
+
+    /// Write the CNF formula to a file in DIMACS format, and re-solve it with an internal solver
+    ///
+    /// The formula encodes the unrolled networks without any optimization. The internal solver is
+    /// independent from Kissat, which gives the result otherwise, and is much slower: it is only
+    /// practical for small problems.
+    #[arg(long)]
+    resolve_dimacs: Option<PathBuf>,
+
+    /// Write a DRAT proof of equivalence to a file, obtained by re-solving with an internal solver
+    ///
+    /// The proof certifies the result of this independent re-solve, not the result of Kissat,
+    /// which is not used in this mode. The internal solver is much slower than Kissat: it is only
+    /// practical for small problems. Only DRAT proofs are written; LRAT is not supported.
+    #[arg(long)]
+    resolve_proof: Option<PathBuf>,
+
+    /// Check groups of outputs independently, in parallel
+    #[arg(long)]
+    partition: Option<OutputPartition>,
+
+    /// Run several strategies in parallel on the whole problem, and keep the first result
+    ///
+    /// The portfolio runs the Sat solver with and without simplification, and random simulation
+    /// to find counterexamples quickly.
+    #[arg(long, conflicts_with_all = ["partition", "resolve_dimacs", "resolve_proof"])]
+    portfolio: bool,
+
+    /// Number of threads used to check the groups of outputs; 0 uses all available cores
+    #[arg(long, default_value_t = 0, requires = "partition")]
+    threads: usize,
+
+    /// Stop at the first group of outputs that is not equivalent
+    #[arg(long, requires = "partition")]
+    stop_at_first: bool,
+
+    /// Minimize the counterexample, showing inputs that do not matter as '-'
+    #[arg(long)]
+    minimize: bool,
+
+    /// Write the counterexample and its explanation to a file, in JSON format
+    #[arg(long)]
+    counterexample: Option<PathBuf>,
+}
+
+/// How to match the flip-flops of two networks
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum RegisterMatching {
+    /// Match flip-flops in the order they are declared
+    Order,
+    /// Match flip-flops with the same name
+    Name,
+    /// Match flip-flops with the same values in random simulation
+    Simulation,
+}
+
+/// How to group the outputs for partitioned equivalence checking
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum OutputPartition {
+    /// Check each output independently
+    Output,
+    /// Check outputs with shared logic together
+    Cone,
+}
+
+/// Parse the initial state of a network from the command line
+fn parse_initial_state(s: &str, aig: &Network) -> InitialState {
+    match s {
+        "zero" => InitialState::Zero,
+        "arbitrary" => InitialState::Arbitrary,
+        _ => {
+            let values: Vec<bool> = s
+                .chars()
+                .map(|c| match c {
+                    '0' => false,
+                    '1' => true,
+                    _ => {
+                        println!("Invalid initial state {s}: expected zero, arbitrary, or a string of 0 and 1");
+                        std::process::exit(1);
+                    }
+                })
+                .collect();
+            let nb_dff = stats::dff_gates(aig).len();
+            if values.len() != nb_dff {
+                println!(
+                    "Initial state has {} values, but the network has {} flip-flops",
+                    values.len(),
+                    nb_dff
+                );
+                std::process::exit(1);
+            }
+            InitialState::Fixed(values)
+        }
+    }
+}
+
+/// Read a list of gate pairs for bridging faults, one pair of gate indices per line
+fn read_bridge_pairs(path: &PathBuf, aig: &Network) -> Vec<(usize, usize)> {
+    let content = std::fs::read_to_string(path).unwrap();
+    let mut ret = Vec::new();
+    for (i, l) in content.lines().enumerate() {
+        let t = l.trim();
+        if t.is_empty() || t.starts_with('*') || t.starts_with('#') {
+            continue;
+        }
+        let gates: Vec<usize> = t
+            .split_whitespace()
+            .map(|g| g.parse::<usize>())
+            .collect::<Result<_, _>>()
+            .unwrap_or_default();
+        if gates.len() != 2 || gates.iter().any(|g| *g >= aig.nb_nodes()) {
+            println!(
+                "Invalid bridge on line {}: expected two gate indices",
+                i + 1
+            );
+            std::process::exit(1);
+        }
+        ret.push((gates[0], gates[1]));
+    }
+    ret
+}
+
+/// Obtain the gate pairs for bridging faults from the command line options
+fn bridge_pairs(
+    aig: &Network,
+    file: &Option<PathBuf>,
+    levels: Option<usize>,
+    max_pairs: usize,
+) -> Vec<(usize, usize)> {
+    let mut ret = Vec::new();
+    if let Some(path) = file {
+        ret.extend(read_bridge_pairs(path, aig));
+    }
+    if let Some(k) = levels {
+        ret.extend(Fault::bridging_candidates(aig, k, max_pairs));
+    }
+    ret
+}
+
+/// Read a library of cell-internal defects, with a gate type, a number of inputs and a faulty truth table in hexadecimal per line
+fn read_defect_library(path: &PathBuf, aig: &Network) -> DefectLibrary {
+    let content = std::fs::read_to_string(path).unwrap();
+    let mut ret = DefectLibrary::new();
+    for (i, l) in content.lines().enumerate() {
+        let t = l.trim();
+        if t.is_empty() || t.starts_with('*') || t.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = t.split_whitespace().collect();
+        let nb_inputs = fields.get(1).and_then(|f| f.parse::<usize>().ok());
+        let table = fields
+            .get(2)
+            .and_then(|f| u64::from_str_radix(f.trim_start_matches("0x"), 16).ok());
+        match (nb_inputs, table) {
+            (Some(n), Some(table))
+                if fields.len() == 3 && n <= 6 && (n == 6 || table >> (1 << n) == 0) =>
+            {
+                ret.add(fields[0], n, table);
+            }
+            _ => {
+                println!(
+                    "Invalid defect on line {}: expected a gate type, a number of inputs up to 6 and a truth table",
+                    i + 1
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+    let unmatched = ret.unmatched(aig);
+    if !unmatched.is_empty() {
+        for (gate_type, n) in unmatched {
+            println!(
+                "Defects for {} gates with {} inputs match no gate of the network",
+                gate_type, n
+            );
+        }
+        std::process::exit(1);
+    }
+    ret
+}
+
+/// Obtain the cell-internal faults from the command line options
+fn cell_faults(aig: &Network, lut_bit_flips: bool, defects: &Option<PathBuf>) -> Vec<Fault> {
+    let mut ret = Vec::new();
+    if lut_bit_flips {
+        ret.extend(Fault::all_lut_bit_flips(aig));
+    }
+    if let Some(path) = defects {
+        ret.extend(Fault::all_defects(aig, &read_defect_library(path, aig)));
+    }
+    ret
 }
 
 impl EquivArgs {
@@ -109,19 +392,136 @@ impl EquivArgs {
             );
             std::process::exit(1);
         }
-        let res = check_equivalence_bounded(&aig1, &aig2, self.num_cycles, !self.sat_only);
+        let init1 = parse_initial_state(&self.init1, &aig1);
+        let init2 = parse_initial_state(&self.init2, &aig2);
+        let constraint = self.constraint.as_ref().map(read_network_file);
+        if let Some(c) = &constraint {
+            if c.nb_inputs() != aig1.nb_inputs() || c.nb_outputs() != 1 {
+                println!(
+                    "Constraint network should have {} inputs and a single output, but has {} inputs and {} outputs",
+                    aig1.nb_inputs(),
+                    c.nb_inputs(),
+                    c.nb_outputs()
+                );
+                std::process::exit(1);
+            }
+        }
+        let resolve = self.resolve_dimacs.is_some() || self.resolve_proof.is_some();
+        if resolve && self.correspondence.is_some() {
+            println!("Register correspondence does not produce proof certificates; skipping it");
+        }
+        let comb_constraint = constraint.as_ref().map_or(true, |c| c.is_comb());
+        if !comb_constraint && self.correspondence.is_some() {
+            println!(
+                "Register correspondence only supports combinatorial constraints; skipping it"
+            );
+        }
+        if let Some(matching) = self.correspondence.filter(|_| !resolve && comb_constraint) {
+            let correspondence = match matching {
+                RegisterMatching::Order => match_registers_by_order(&aig1, &aig2),
+                RegisterMatching::Name => match_registers_by_name(
+                    &aig1,
+                    &read_dff_names_file(&self.file1),
+                    &aig2,
+                    &read_dff_names_file(&self.file2),
+                ),
+                RegisterMatching::Simulation => {
+                    match_registers_by_simulation(&aig1, &aig2, &init1, &init2, 64, 1)
+                }
+            };
+            let res = check_equivalence_correspondence(
+                &aig1,
+                &aig2,
+                &init1,
+                &init2,
+                &correspondence,
+                constraint.as_ref(),
+                !self.sat_only,
+            );
+            match res {
+                Some(proven) => {
+                    println!(
+                        "Networks are equivalent, with {} corresponding flip-flops",
+                        proven.len()
+                    );
+                    std::process::exit(0);
+                }
+                None => {
+                    println!("Could not prove equivalence by register correspondence; running bounded equivalence checking");
+                }
+            }
+        }
+        if let Some(partition) = self.partition {
+            if init1 != InitialState::Zero
+                || init2 != InitialState::Zero
+                || constraint.is_some()
+                || resolve
+            {
+                println!("Output partitioning only supports zero initial states, without constraints or proofs");
+                std::process::exit(1);
+            }
+            self.run_partitioned(&aig1, &aig2, partition);
+        }
+        let res = if resolve {
+            check_equivalence_bounded_resolved(
+                &aig1,
+                &aig2,
+                self.num_cycles,
+                &init1,
+                &init2,
+                constraint.as_ref(),
+            )
+            .map(|cert| {
+                if let Some(path) = &self.resolve_dimacs {
+                    let mut f = File::create(path).unwrap();
+                    write_dimacs(&mut f, cert.nb_vars, &cert.cnf);
+                }
+                if let Some(path) = &self.resolve_proof {
+                    let mut f = File::create(path).unwrap();
+                    write_drat(&mut f, &cert.proof);
+                }
+            })
+        } else if self.portfolio {
+            let (res, strategy) = check_equivalence_bounded_portfolio(
+                &aig1,
+                &aig2,
+                self.num_cycles,
+                &init1,
+                &init2,
+                constraint.as_ref(),
+                &Strategy::all(),
+            );
+            println!("Solved by strategy {:?}", strategy);
+            res
+        } else {
+            check_equivalence_bounded_with_init(
+                &aig1,
+                &aig2,
+                self.num_cycles,
+                &init1,
+                &init2,
+                constraint.as_ref(),
+                !self.sat_only,
+            )
+        };
         let is_comb = aig1.is_comb() && aig2.is_comb();
         match res {
             Err(err) => {
                 println!("Networks are not equivalent");
-                println!("Test pattern:");
                 // TODO: extract the names here
-                for v in err {
-                    print!("\t");
-                    for b in v {
-                        print!("{}", if b { "0" } else { "1" });
-                    }
-                    println!();
+                let report = explain_counterexample(
+                    &aig1,
+                    &aig2,
+                    &init1,
+                    &init2,
+                    constraint.as_ref(),
+                    &err,
+                    self.minimize,
+                );
+                print!("{}", report);
+                if let Some(path) = &self.counterexample {
+                    let mut f = File::create(path).unwrap();
+                    report.write_json(&mut f);
                 }
                 std::process::exit(1);
             }
@@ -137,6 +537,93 @@ impl EquivArgs {
     }
 }
 
+impl EquivArgs {
+    /// Check equivalence on groups of outputs, and report the result for each group
+    fn run_partitioned(&self, aig1: &Network, aig2: &Network, partition: OutputPartition) -> ! {
+        let groups = match partition {
+            OutputPartition::Output => group_outputs_individually(aig1),
+            OutputPartition::Cone => group_outputs_by_cone(aig1, 16),
+        };
+        let results = check_equivalence_partitioned(
+            aig1,
+            aig2,
+            self.num_cycles,
+            &groups,
+            self.threads,
+            self.stop_at_first,
+            !self.sat_only,
+        );
+        let mut nb_different = 0;
+        for r in &results {
+            let outputs = r
+                .outputs
+                .iter()
+                .map(|o| o.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            match &r.status {
+                OutputStatus::Equivalent => println!("Outputs {}: equivalent", outputs),
+                OutputStatus::Skipped => println!("Outputs {}: skipped", outputs),
+                OutputStatus::Different(pattern) => {
+                    println!("Outputs {}: not equivalent", outputs);
+                    let init = InitialState::Zero;
+                    let cex = SequentialCounterexample {
+                        inputs: pattern.clone(),
+                        init_a: vec![false; stats::dff_gates(aig1).len()],
+                        init_b: vec![false; stats::dff_gates(aig2).len()],
+                    };
+                    let report =
+                        explain_counterexample(aig1, aig2, &init, &init, None, &cex, self.minimize);
+                    print!("{}", report);
+                    if nb_different == 0 {
+                        if let Some(path) = &self.counterexample {
+                            let mut f = File::create(path).unwrap();
+                            report.write_json(&mut f);
+                        }
+                    }
+                    nb_different += 1;
+                }
+            }
+        }
+        if nb_different == 0 {
+            println!("Networks are equivalent");
+            std::process::exit(0);
+        } else {
+            println!(
+                "Networks are not equivalent: {} groups of outputs differ",
+                nb_different
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Command arguments for proof checking
+#[derive(Args)]
+pub struct CheckProofArgs {
+    /// Formula in DIMACS format
+    cnf: PathBuf,
+    /// Proof of unsatisfiability in DRAT format
+    proof: PathBuf,
+}
+
+impl CheckProofArgs {
+    pub fn run(&self) {
+        let (nb_vars, cnf) = read_dimacs(File::open(&self.cnf).unwrap()).unwrap();
+        let proof = read_drat(File::open(&self.proof).unwrap()).unwrap();
+        match check_drat(nb_vars, &cnf, &proof) {
+            Ok(()) => {
+                println!("Proof is valid: the formula is unsatisfiable");
+                std::process::exit(0);
+            }
+            Err(msg) => {
+                println!("Proof is invalid: {}", msg);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
 /// Command arguments for optimization
 #[derive(Args)]
 pub struct OptArgs {
@@ -240,6 +727,71 @@ impl SimulateArgs {
     }
 }
 
+/// Command arguments for soft error analysis
+#[derive(Args)]
+pub struct SeuArgs {
+    /// Network to analyze
+    network: PathBuf,
+
+    /// Number of cycles where upsets are injected
+    #[arg(short = 'c', long, default_value_t = 32)]
+    num_cycles: usize,
+
+    /// Number of cycles after an upset during which the outputs are observed
+    #[arg(short = 'k', long, default_value_t = 8)]
+    window: usize,
+
+    /// Number of random input sequences, rounded up to a multiple of 64
+    #[arg(short = 'r', long, default_value_t = 256)]
+    num_random: usize,
+
+    /// Random seed for the input sequences
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+
+    /// Number of most vulnerable flip-flops to report
+    #[arg(short = 'n', long, default_value_t = 20)]
+    nb_dffs: usize,
+
+    /// Number of threads used for simulation; 0 uses all available cores
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+
+    /// Write the vulnerability of each node to a CSV file
+    #[arg(long)]
+    csv: Option<PathBuf>,
+}
+
+impl SeuArgs {
+    pub fn run(&self) {
+        let aig = read_network_file(&self.network);
+        if self.num_cycles == 0 {
+            println!("The number of cycles should be at least 1");
+            std::process::exit(1);
+        }
+        let report = analyze_seu(
+            &aig,
+            self.num_cycles,
+            self.window,
+            self.num_random,
+            self.seed,
+            self.threads,
+        );
+        print!("{}", report);
+        let dffs = report.ranked_dffs();
+        if !dffs.is_empty() {
+            println!("Flip-flop\tAVF");
+            for (g, avf) in dffs.iter().take(self.nb_dffs) {
+                println!("x{}\t{:.2}%", g, 100.0 * avf);
+            }
+        }
+        if let Some(path) = &self.csv {
+            let mut f = File::create(path).unwrap();
+            report.write_csv(&mut f);
+        }
+    }
+}
+
 /// Command arguments for test pattern generation
 #[derive(Args)]
 pub struct AtpgArgs {
@@ -255,28 +807,255 @@ pub struct AtpgArgs {
     seed: u64,
 
     /// Attempt to generate sequential patterns (random only)
-    #[arg(short = 'c', long)]
+    #[arg(short = 'c', long, conflicts_with_all = ["transition", "sequential", "paths"])]
     num_cycles: Option<usize>,
 
     /// Number of random patterns to generate
-    #[arg(short = 'r', long)]
+    #[arg(short = 'r', long, conflicts_with_all = ["transition", "sequential", "paths"])]
     num_random: Option<usize>,
 
     /// Do not remove redundant faults beforehand
-    #[arg(long, default_value_t = false)]
+    #[arg(long, default_value_t = false, conflicts_with_all = ["transition", "sequential", "paths", "num_cycles", "num_random"])]
     with_redundant_faults: bool,
+
+    /// Generate two-frame patterns for transition faults, with the given launch mode
+    #[arg(long, conflicts_with_all = ["sequential", "paths"])]
+    transition: Option<TransitionLaunch>,
+
+    /// Target bridging faults between the gate pairs listed in this file
+    #[arg(long, conflicts_with_all = ["transition", "sequential", "paths", "num_cycles", "num_random"])]
+    bridges: Option<PathBuf>,
+
+    /// Target bridging faults between gates whose levels differ by at most this value
+    #[arg(long, conflicts_with_all = ["transition", "sequential", "paths", "num_cycles", "num_random"])]
+    bridge_levels: Option<usize>,
+
+    /// Maximum number of gate pairs for bridging faults between levels, keeping the closest gates
+    #[arg(long, default_value_t = 10000, conflicts_with_all = ["transition", "sequential", "paths", "num_cycles", "num_random"])]
+    max_bridges: usize,
+
+    /// Target the faults flipping a single bit of the truth table of a Lut
+    #[arg(long, conflicts_with_all = ["transition", "sequential", "paths", "num_cycles", "num_random"])]
+    lut_bit_flips: bool,
+
+    /// Target the cell-internal defects listed in this file, as faulty truth tables for each gate type
+    #[arg(long, conflicts_with_all = ["transition", "sequential", "paths", "num_cycles", "num_random"])]
+    defects: Option<PathBuf>,
+
+    /// Generate two-timestep path-delay tests for this number of longest paths
+    #[arg(long)]
+    paths: Option<usize>,
+
+    /// Number of threads used for fault simulation; 0 uses all available cores
+    #[arg(long, default_value_t = 0, conflicts_with_all = ["sequential", "paths", "num_cycles", "num_random"])]
+    threads: usize,
+
+    /// Compaction algorithm for the final set of test patterns
+    #[arg(long, value_enum, default_value_t = CompactionMode::Greedy, conflicts_with_all = ["transition", "sequential", "paths", "num_cycles", "num_random"])]
+    compaction: CompactionMode,
+
+    /// Extend each deterministic pattern to detect additional faults
+    #[arg(long, conflicts_with_all = ["transition", "sequential", "paths", "num_cycles", "num_random"])]
+    dynamic_compaction: bool,
+
+    /// Detect each fault with this number of distinct patterns
+    #[arg(long, default_value_t = 1, conflicts_with_all = ["transition", "sequential", "paths"])]
+    n_detect: usize,
+
+    /// Number of backtracks before PODEM gives up on a fault and a SAT solver is used instead
+    #[arg(long, default_value_t = 100, conflicts_with_all = ["transition", "sequential", "paths"])]
+    podem_backtracks: usize,
+
+    /// Indices of the inputs with an unknown value (X sources), such as black box outputs
+    ///
+    /// PODEM and dynamic compaction are disabled with X sources, and a SAT solver is used for all faults.
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["transition", "sequential", "paths"])]
+    x_inputs: Vec<usize>,
+
+    /// Gate indices of the flip-flops with an unknown value, such as uninitialized non-scan flip-flops
+    ///
+    /// Their data, enable and reset are not observed. PODEM and dynamic compaction are disabled
+    /// with X sources, and a SAT solver is used for all faults.
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["transition", "sequential", "paths"])]
+    x_flops: Vec<usize>,
+
+    /// Update the test patterns in this file after a change of the network, instead of starting from scratch
+    #[arg(long, conflicts_with_all = ["transition", "sequential", "paths"])]
+    incremental: Option<PathBuf>,
+
+    /// Generate deterministic multi-cycle patterns from reset, for up to this number of cycles
+    #[arg(long, conflicts_with_all = ["paths"])]
+    sequential: Option<usize>,
+
+    /// Gate indices of the scanned flip-flops for sequential patterns, loaded in the first cycle and observed after the last one
+    #[arg(long, value_delimiter = ',', requires = "sequential")]
+    scan_flops: Vec<usize>,
+}
+
+/// Compaction algorithm for test patterns
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum CompactionMode {
+    /// Greedy set cover
+    Greedy,
+    /// Fault simulation in reverse order
+    Reverse,
+    /// Fault simulation in reverse order, then in random orders
+    Random,
+    /// Exact minimum set cover with a SAT solver
+    Exact,
+}
+
+/// Launch mode for transition fault test patterns
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum TransitionLaunch {
+    /// Launch-on-capture: the transition is launched from the captured state
+    Loc,
+    /// Launch-on-shift: the transition is launched by the last shift of the scan chain
+    Los,
 }
 
 impl AtpgArgs {
     pub fn run(&self) {
         let mut aig = read_network_file(&self.network);
 
-        if self.num_cycles.is_none() && self.num_random.is_none() {
+        if let Some(launch) = self.transition {
+            let mode = match launch {
+                TransitionLaunch::Loc => LaunchMode::LaunchOnCapture,
+                TransitionLaunch::Los => LaunchMode::LaunchOnShift,
+            };
+            let seq_patterns =
+                generate_transition_test_patterns(&aig, mode, self.seed, self.threads);
+            write_pattern_file(&self.output, &seq_patterns);
+        } else if let Some(max_depth) = self.sequential {
+            if max_depth == 0 {
+                println!("The number of cycles for sequential patterns should be at least 1");
+                std::process::exit(1);
+            }
+            let mut scan_flops = self.scan_flops.clone();
+            scan_flops.sort();
+            scan_flops.dedup();
+            if scan_flops.len() != self.scan_flops.len() {
+                println!("Scanned flip-flops should be listed only once");
+                std::process::exit(1);
+            }
+            let seq_patterns =
+                generate_seq_test_patterns(&aig, max_depth, &self.scan_flops, self.seed)
+                    .unwrap_or_else(|e| {
+                        println!("Invalid scanned flip-flop: {}", e);
+                        std::process::exit(1);
+                    });
+            write_pattern_file(&self.output, &seq_patterns);
+        } else if let Some(nb_paths) = self.paths {
             if !aig.is_comb() {
                 println!("Exposing flip-flops for a sequential network");
                 aig = expose_dff(&aig);
             }
-            let patterns = generate_comb_test_patterns(&aig, self.seed, self.with_redundant_faults);
+            let results = generate_path_delay_tests(&aig, nb_paths);
+            let mut seq_patterns = Vec::new();
+            let mut counts = [0; 4];
+            for r in results {
+                let transition = if r.rising { "rising" } else { "falling" };
+                let status = match r.status {
+                    PathStatus::Robust(p) => {
+                        seq_patterns.push(p);
+                        counts[0] += 1;
+                        "robust"
+                    }
+                    PathStatus::NonRobust(p) => {
+                        seq_patterns.push(p);
+                        counts[1] += 1;
+                        "non-robust"
+                    }
+                    PathStatus::Untestable => {
+                        counts[2] += 1;
+                        "untestable"
+                    }
+                    PathStatus::FalsePath => {
+                        counts[3] += 1;
+                        "false path"
+                    }
+                };
+                println!(
+                    "{} ({} gates, {}): {}",
+                    r.path,
+                    r.path.len(),
+                    transition,
+                    status
+                );
+            }
+            println!(
+                "{} robust, {} non-robust, {} untestable, {} false path faults",
+                counts[0], counts[1], counts[2], counts[3]
+            );
+            write_pattern_file(&self.output, &seq_patterns);
+        } else if self.num_cycles.is_none() && self.num_random.is_none() {
+            if self.n_detect == 0 {
+                println!("The number of detections should be at least 1");
+                std::process::exit(1);
+            }
+            let mut x_inputs = self.x_inputs.clone();
+            if let Some(i) = x_inputs.iter().find(|i| **i >= aig.nb_inputs()) {
+                println!("Invalid X source {}: not an input", i);
+                std::process::exit(1);
+            }
+            let dffs = stats::dff_gates(&aig);
+            for g in &self.x_flops {
+                let Some(pos) = dffs.iter().position(|d| d == g) else {
+                    println!("Invalid X source {}: not a flip-flop", g);
+                    std::process::exit(1);
+                };
+                // Flip-flops are exposed as inputs after the primary inputs
+                x_inputs.push(aig.nb_inputs() + pos);
+            }
+            if !x_inputs.is_empty() {
+                println!("PODEM and dynamic compaction are disabled with X sources: faults are targeted with a 3-valued SAT encoding");
+            }
+            if !aig.is_comb() {
+                println!("Exposing flip-flops for a sequential network");
+                // The inputs of the flip-flops with an unknown value are not observed either
+                aig = expose_dff_unobserved(&aig, &self.x_flops);
+            }
+            let options = AtpgOptions {
+                seed: self.seed,
+                with_redundant_faults: self.with_redundant_faults,
+                bridges: bridge_pairs(&aig, &self.bridges, self.bridge_levels, self.max_bridges),
+                cell_faults: cell_faults(&aig, self.lut_bit_flips, &self.defects),
+                nb_threads: self.threads,
+                compaction: match self.compaction {
+                    CompactionMode::Greedy => Compaction::Greedy,
+                    CompactionMode::Reverse => Compaction::ReverseOrder,
+                    CompactionMode::Random => Compaction::RandomOrder,
+                    CompactionMode::Exact => Compaction::Exact,
+                },
+                dynamic_compaction: self.dynamic_compaction,
+                n_detect: self.n_detect,
+                x_inputs,
+                podem_backtrack_limit: self.podem_backtracks,
+            };
+            let patterns = if let Some(path) = &self.incremental {
+                let old_patterns = read_pattern_file(path);
+                if old_patterns.iter().any(|p| p.len() != 1) {
+                    println!("Patterns to update should have exactly one cycle each");
+                    std::process::exit(1);
+                }
+                let old_patterns: Vec<Vec<bool>> = old_patterns
+                    .into_iter()
+                    .map(|mut p| p.pop().unwrap())
+                    .collect();
+                if old_patterns.iter().any(|p| p.len() != aig.nb_inputs()) {
+                    println!(
+                        "Patterns should have one value per input and flip-flop ({} values)",
+                        aig.nb_inputs()
+                    );
+                    std::process::exit(1);
+                }
+                let (patterns, report) =
+                    generate_incremental_comb_test_patterns(&aig, &old_patterns, &options);
+                print!("{}", report);
+                patterns
+            } else {
+                generate_comb_test_patterns(&aig, &options)
+            };
             let seq_patterns = patterns.iter().map(|p| vec![p.clone()]).collect();
             write_pattern_file(&self.output, &seq_patterns);
         } else {
@@ -290,6 +1069,462 @@ impl AtpgArgs {
     }
 }
 
+/// Command arguments for fault diagnosis
+#[derive(Args)]
+pub struct DiagnoseArgs {
+    /// Network to diagnose
+    network: PathBuf,
+
+    /// Test patterns applied on the tester
+    patterns: PathBuf,
+
+    /// Responses observed on the tester, with one value per output for each pattern
+    responses: PathBuf,
+
+    /// Maximum number of faults present at the same time in a hypothesis
+    #[arg(long, default_value_t = 1)]
+    multiple: usize,
+
+    /// Number of candidates to report
+    #[arg(short = 'n', long, default_value_t = 10)]
+    nb_candidates: usize,
+
+    /// Also consider bridging faults between the gate pairs listed in this file
+    #[arg(long)]
+    bridges: Option<PathBuf>,
+
+    /// Also consider bridging faults between gates whose levels differ by at most this value
+    #[arg(long)]
+    bridge_levels: Option<usize>,
+
+    /// Maximum number of gate pairs for bridging faults between levels, keeping the closest gates
+    #[arg(long, default_value_t = 10000)]
+    max_bridges: usize,
+
+    /// Also consider the faults flipping a single bit of the truth table of a Lut
+    #[arg(long)]
+    lut_bit_flips: bool,
+
+    /// Also consider the cell-internal defects listed in this file
+    #[arg(long)]
+    defects: Option<PathBuf>,
+}
+
+impl DiagnoseArgs {
+    pub fn run(&self) {
+        let mut aig = read_network_file(&self.network);
+        if !aig.is_comb() {
+            println!("Exposing flip-flops for a sequential network");
+            aig = expose_dff(&aig);
+        }
+        let first_cycle = |path: &PathBuf| -> Vec<Vec<bool>> {
+            read_pattern_file(path)
+                .into_iter()
+                .map(|p| p[0].clone())
+                .collect()
+        };
+        let patterns = first_cycle(&self.patterns);
+        let responses = first_cycle(&self.responses);
+        if patterns.len() != responses.len() {
+            println!(
+                "Got {} patterns but {} responses",
+                patterns.len(),
+                responses.len()
+            );
+            std::process::exit(1);
+        }
+        if patterns.iter().any(|p| p.len() != aig.nb_inputs())
+            || responses.iter().any(|r| r.len() != aig.nb_outputs())
+        {
+            println!(
+                "Patterns should have {} values and responses {} values",
+                aig.nb_inputs(),
+                aig.nb_outputs()
+            );
+            std::process::exit(1);
+        }
+        let mut faults = Fault::all_collapsed(&aig);
+        let bridges = bridge_pairs(&aig, &self.bridges, self.bridge_levels, self.max_bridges);
+        faults.extend(Fault::all_bridging(&bridges));
+        faults.extend(cell_faults(&aig, self.lut_bit_flips, &self.defects));
+        let candidates = diagnose(
+            &aig,
+            &faults,
+            &patterns,
+            &responses,
+            self.multiple,
+            self.nb_candidates,
+        );
+        if candidates.is_empty() {
+            println!("No failing response to diagnose");
+            return;
+        }
+        println!("Score\tTFSF\tTFSP\tTPSF\tFaults");
+        for c in &candidates {
+            println!("{}", c);
+        }
+    }
+}
+
+/// Command arguments for scan insertion
+#[derive(Args)]
+pub struct ScanArgs {
+    /// Network to insert scan chains into
+    network: PathBuf,
+
+    /// Output file for the network with scan chains
+    #[arg(short = 'o', long)]
+    output: PathBuf,
+
+    /// Number of scan chains
+    #[arg(long, default_value_t = 1)]
+    chains: usize,
+
+    /// Order of the flip-flops along the chains
+    #[arg(long, value_enum, default_value_t = ScanOrderMode::Index)]
+    order: ScanOrderMode,
+
+    /// Output file for the description of the scan chains
+    #[arg(long)]
+    chain_file: Option<PathBuf>,
+
+    /// Test patterns generated on the combinational view of the network
+    #[arg(long, requires = "scan_patterns")]
+    patterns: Option<PathBuf>,
+
+    /// Output file for the patterns translated into serial scan load/unload sequences
+    #[arg(long, requires = "patterns")]
+    scan_patterns: Option<PathBuf>,
+}
+
+/// Order of the flip-flops along the scan chains
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ScanOrderMode {
+    /// Order of the flip-flops in the network
+    Index,
+    /// Alphabetical order of the flip-flop names in the netlist
+    Name,
+}
+
+impl ScanArgs {
+    pub fn run(&self) {
+        let aig = read_network_file(&self.network);
+        if self.chains == 0 {
+            println!("The number of scan chains should be at least 1");
+            std::process::exit(1);
+        }
+        let names = read_dff_names_file(&self.network);
+        let order = match self.order {
+            ScanOrderMode::Index => ScanOrder::Index,
+            ScanOrderMode::Name => ScanOrder::Name(names.clone()),
+        };
+        let (scan, chains) = insert_scan_chains(&aig, self.chains, &order);
+        println!(
+            "Inserted {} scan chains for {} flip-flops, with {} shift cycles",
+            chains.chains.len(),
+            chains.chains.iter().map(|c| c.len()).sum::<usize>(),
+            chains.max_length()
+        );
+        write_network_file(&self.output, &scan);
+        if let Some(path) = &self.chain_file {
+            let mut f = File::create(path).unwrap();
+            chains.write(&mut f, &aig, Some(&names));
+        }
+        if let (Some(input), Some(output)) = (&self.patterns, &self.scan_patterns) {
+            let patterns: Vec<Vec<bool>> = read_pattern_file(input)
+                .into_iter()
+                .map(|p| p[0].clone())
+                .collect();
+            let nb_expected = expose_dff(&aig).nb_inputs();
+            if patterns.iter().any(|p| p.len() != nb_expected) {
+                println!(
+                    "Patterns should have one value per input and flip-flop ({} values)",
+                    nb_expected
+                );
+                std::process::exit(1);
+            }
+            write_pattern_file(output, &chains.scan_patterns(&aig, &patterns));
+        }
+    }
+}
+
+/// Command arguments for logic built-in self-test
+#[derive(Args)]
+pub struct BistArgs {
+    /// Network to test
+    network: PathBuf,
+
+    /// Output file for the generated patterns
+    #[arg(short = 'o', long)]
+    output: Option<PathBuf>,
+
+    /// Pattern generator
+    #[arg(long, value_enum, default_value_t = BistMode::Lfsr)]
+    generator: BistMode,
+
+    /// Width of the LFSR, using a primitive polynomial; 32 by default
+    #[arg(long, conflicts_with = "polynomial")]
+    width: Option<usize>,
+
+    /// Taps of the LFSR feedback polynomial, such as 16,15,13,4, instead of a primitive polynomial
+    #[arg(long, value_delimiter = ',')]
+    polynomial: Vec<usize>,
+
+    /// Number of patterns to apply
+    #[arg(short = 'n', long, default_value_t = 10000)]
+    num_patterns: usize,
+
+    /// Seed of the pattern generator
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+
+    /// Number of random seeds to try, keeping the one with the best coverage
+    #[arg(long, default_value_t = 1)]
+    seed_candidates: usize,
+
+    /// Maximum number of additional seeds, targeting the faults that are not detected
+    #[arg(long)]
+    reseed: Option<usize>,
+
+    /// Number of patterns generated from each additional seed
+    #[arg(long, default_value_t = 64)]
+    patterns_per_seed: usize,
+
+    /// Number of threads used for fault simulation; 0 uses all available cores
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+}
+
+/// Pattern generator for logic built-in self-test
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum BistMode {
+    /// Linear feedback shift register, shifted serially into the inputs
+    Lfsr,
+    /// Cellular automaton with rules 90 and 150, with one cell per input
+    Ca,
+}
+
+impl BistArgs {
+    pub fn run(&self) {
+        let mut aig = read_network_file(&self.network);
+        if !aig.is_comb() {
+            println!("Exposing flip-flops for a sequential network");
+            aig = expose_dff(&aig);
+        }
+        let source = match self.generator {
+            BistMode::Lfsr if self.polynomial.is_empty() => {
+                let width = self.width.unwrap_or(32);
+                if !(2..=32).contains(&width) {
+                    println!("Primitive polynomials are available for widths 2 to 32");
+                    std::process::exit(1);
+                }
+                BistSource::lfsr(width)
+            }
+            BistMode::Lfsr => {
+                let mut taps = self.polynomial.clone();
+                taps.sort_by(|a, b| b.cmp(a));
+                taps.dedup();
+                if taps[0] > 64 || taps.contains(&0) {
+                    println!("Polynomial taps should be between 1 and 64");
+                    std::process::exit(1);
+                }
+                BistSource::Lfsr(taps)
+            }
+            BistMode::Ca => {
+                if self.width.is_some() || !self.polynomial.is_empty() {
+                    println!("The cellular automaton has one cell per input, without width or polynomial");
+                    std::process::exit(1);
+                }
+                BistSource::cellular_automaton()
+            }
+        };
+        let nb_inputs = aig.nb_inputs();
+        let seed = if self.seed_candidates > 1 {
+            best_bist_seed(
+                &aig,
+                &source,
+                self.num_patterns,
+                self.seed_candidates,
+                self.seed,
+                self.threads,
+            )
+        } else {
+            source.seed_from_u64(nb_inputs, self.seed)
+        };
+        let seeds = match self.reseed {
+            Some(max_seeds) => bist_reseeding(
+                &aig,
+                &source,
+                &seed,
+                self.num_patterns,
+                self.patterns_per_seed,
+                max_seeds,
+                self.threads,
+            ),
+            None => vec![(seed, self.num_patterns)],
+        };
+        for (s, n) in &seeds {
+            let bits: String = s.iter().map(|b| if *b { '1' } else { '0' }).collect();
+            println!("Seed {} for {} patterns", bits, n);
+        }
+        let patterns = bist_patterns(&source, nb_inputs, &seeds);
+        print!("{}", bist_coverage(&aig, &patterns, self.threads));
+        if let Some(path) = &self.output {
+            let seq_patterns = patterns.into_iter().map(|p| vec![p]).collect();
+            write_pattern_file(path, &seq_patterns);
+        }
+    }
+}
+
+/// Command arguments for test response compaction
+#[derive(Args)]
+pub struct CompactorArgs {
+    /// Network whose outputs are compacted
+    network: PathBuf,
+
+    /// Output file for the network with its compactor
+    #[arg(short = 'o', long)]
+    output: Option<PathBuf>,
+
+    /// Use a Xor space compactor with this number of outputs, at most the number of network outputs
+    #[arg(long, conflicts_with = "misr", required_unless_present = "misr")]
+    xor: Option<usize>,
+
+    /// Use a MISR of this width, between 2 and 32
+    #[arg(long)]
+    misr: Option<usize>,
+
+    /// Test patterns used to analyze fault detection after compaction
+    #[arg(long)]
+    patterns: Option<PathBuf>,
+
+    /// Number of threads used for fault simulation; 0 uses all available cores
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+}
+
+impl CompactorArgs {
+    pub fn run(&self) {
+        let mut aig = read_network_file(&self.network);
+        let compactor = match (self.xor, self.misr) {
+            (Some(w), _) if (1..=aig.nb_outputs()).contains(&w) => Compactor::Xor(w),
+            (_, Some(w)) if (2..=32).contains(&w) => Compactor::Misr(w),
+            _ => {
+                println!(
+                    "The Xor compactor should have between 1 and {} outputs, and the MISR a width between 2 and 32",
+                    aig.nb_outputs()
+                );
+                std::process::exit(1);
+            }
+        };
+        if let Some(path) = &self.output {
+            write_network_file(path, &add_compactor(&aig, compactor));
+        }
+        if let Some(path) = &self.patterns {
+            if !aig.is_comb() {
+                println!("Exposing flip-flops for a sequential network");
+                aig = expose_dff(&aig);
+            }
+            let patterns: Vec<Vec<bool>> = read_pattern_file(path)
+                .into_iter()
+                .map(|p| p[0].clone())
+                .collect();
+            if patterns.iter().any(|p| p.len() != aig.nb_inputs()) {
+                println!(
+                    "Patterns should have one value per input and flip-flop ({} values)",
+                    aig.nb_inputs()
+                );
+                std::process::exit(1);
+            }
+            let report = analyze_compaction(&aig, compactor, &patterns, self.threads);
+            print!("{}", report);
+        }
+    }
+}
+
+/// Command arguments for testability analysis
+#[derive(Args)]
+pub struct TestabilityArgs {
+    /// Network to analyze
+    network: PathBuf,
+
+    /// Number of hardest nodes to report
+    #[arg(short = 'n', long, default_value_t = 20)]
+    nb_nodes: usize,
+
+    /// Write the network to a DOT file, with the measures of each node
+    #[arg(long)]
+    dot: Option<PathBuf>,
+}
+
+impl TestabilityArgs {
+    pub fn run(&self) {
+        let aig = read_network_file(&self.network);
+        let scoap = Scoap::compute(&aig);
+        scoap.write_report(&mut std::io::stdout(), &aig, self.nb_nodes);
+        if let Some(path) = &self.dot {
+            let annotations: Vec<String> =
+                (0..aig.nb_nodes()).map(|i| scoap.node_summary(i)).collect();
+            write_annotated_dot_file(path, &aig, &annotations);
+        }
+    }
+}
+
+/// Command arguments for test point insertion
+#[derive(Args)]
+pub struct TestPointArgs {
+    /// Network to insert test points into
+    network: PathBuf,
+
+    /// Output file for the network with test points
+    #[arg(short = 'o', long)]
+    output: PathBuf,
+
+    /// Maximum number of test points to insert
+    #[arg(long, default_value_t = 16)]
+    max_points: usize,
+
+    /// Detection probability below which a fault is random-pattern-resistant
+    #[arg(long, default_value_t = 0.001)]
+    threshold: f64,
+
+    /// Number of random patterns used to report the coverage
+    #[arg(short = 'r', long, default_value_t = 16384)]
+    num_random: usize,
+
+    /// Random seed for the patterns
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+
+    /// Number of threads used for fault simulation; 0 uses all available cores
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+}
+
+impl TestPointArgs {
+    pub fn run(&self) {
+        let aig = read_network_file(&self.network);
+        let points = select_test_points(&aig, self.max_points, self.threshold);
+        for p in &points {
+            println!("Inserting {}", p);
+        }
+        let (nb_faults, before, after) =
+            test_point_coverage(&aig, &points, self.num_random, self.seed, self.threads);
+        println!(
+            "Random pattern coverage with {} patterns: {}/{} faults ({:.2}%) before, {}/{} faults ({:.2}%) after {} test points",
+            self.num_random,
+            before,
+            nb_faults,
+            100.0 * before as f64 / nb_faults as f64,
+            after,
+            nb_faults,
+            100.0 * after as f64 / nb_faults as f64,
+            points.len()
+        );
+        write_network_file(&self.output, &insert_test_points(&aig, &points));
+    }
+}
+
 /// Command arguments for test pattern generation report
 #[derive(Args)]
 pub struct AtpgReportArgs {
@@ -302,18 +1537,91 @@ pub struct AtpgReportArgs {
     /// Do not remove redundant faults beforehand
     #[arg(long, default_value_t = false)]
     with_redundant_faults: bool,
+
+    /// Report bridging faults between the gate pairs listed in this file
+    #[arg(long)]
+    bridges: Option<PathBuf>,
+
+    /// Report bridging faults between gates whose levels differ by at most this value
+    #[arg(long)]
+    bridge_levels: Option<usize>,
+
+    /// Maximum number of gate pairs for bridging faults between levels, keeping the closest gates
+    #[arg(long, default_value_t = 10000)]
+    max_bridges: usize,
+
+    /// Report the faults flipping a single bit of the truth table of a Lut
+    #[arg(long)]
+    lut_bit_flips: bool,
+
+    /// Report the cell-internal defects listed in this file
+    #[arg(long)]
+    defects: Option<PathBuf>,
+
+    /// Number of threads used for fault simulation; 0 uses all available cores
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+
+    /// Write the status of each fault to a file, in CSV or JSON format depending on the extension
+    #[arg(long)]
+    fault_list: Option<PathBuf>,
+
+    /// Do not use a SAT solver for faults that PODEM could not classify
+    ///
+    /// PODEM only handles stuck-at faults, and the other faults are left unclassified.
+    #[arg(long)]
+    no_sat: bool,
+
+    /// Number of backtracks before PODEM gives up on a fault
+    #[arg(long, default_value_t = 100)]
+    podem_backtracks: usize,
+
+    /// Launch mode of the two-frame patterns, used to classify the transition faults they do not detect
+    #[arg(long, value_enum, default_value_t = TransitionLaunch::Loc)]
+    launch: TransitionLaunch,
 }
 
 impl AtpgReportArgs {
     pub fn run(&self) {
-        let mut aig = read_network_file(&self.network);
+        let json = self.fault_list.as_ref().map(|path| match path.extension() {
+            Some(s) if s == "csv" => false,
+            Some(s) if s == "json" => true,
+            _ => {
+                println!("Fault list file should have a .csv or .json extension");
+                std::process::exit(1);
+            }
+        });
+        let aig = read_network_file(&self.network);
 
-        if !aig.is_comb() {
+        // Faults are listed on the network with exposed flip-flops
+        let comb = if aig.is_comb() {
+            aig.clone()
+        } else {
             println!("Exposing flip-flops for a sequential network");
-            aig = expose_dff(&aig);
-        }
+            expose_dff(&aig)
+        };
         let seq_patterns = read_pattern_file(&self.patterns);
-        let patterns = seq_patterns.iter().map(|p| p[0].clone()).collect();
-        report_comb_test_patterns(&aig, patterns, self.with_redundant_faults);
+        let options = AtpgOptions {
+            with_redundant_faults: self.with_redundant_faults,
+            bridges: bridge_pairs(&comb, &self.bridges, self.bridge_levels, self.max_bridges),
+            cell_faults: cell_faults(&comb, self.lut_bit_flips, &self.defects),
+            nb_threads: self.threads,
+            podem_backtrack_limit: self.podem_backtracks,
+            ..Default::default()
+        };
+        let launch_mode = match self.launch {
+            TransitionLaunch::Loc => LaunchMode::LaunchOnCapture,
+            TransitionLaunch::Los => LaunchMode::LaunchOnShift,
+        };
+        let report =
+            report_comb_test_patterns(&aig, launch_mode, seq_patterns, !self.no_sat, &options);
+        if let (Some(path), Some(json)) = (&self.fault_list, json) {
+            let mut f = File::create(path).unwrap();
+            if json {
+                report.write_json(&mut f);
+            } else {
+                report.write_csv(&mut f);
+            }
+        }
     }
 }
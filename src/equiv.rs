@@ -1,18 +1,40 @@
 //! Equivalence checking
 
+mod cdcl;
+mod correspondence;
+mod counterexample;
+mod partition;
+mod portfolio;
+mod proof;
+
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+use rustsat::solvers::ControlSignal;
 use rustsat::solvers::Solve;
 use rustsat::solvers::SolverResult;
+use rustsat::solvers::Terminate;
 use rustsat::types::Clause;
 use rustsat::types::Lit;
 use rustsat::types::TernaryVal;
 use rustsat_kissat::Kissat;
 use volute::Lut;
 
-use crate::network::{BinaryType, NaryType, TernaryType};
+use crate::network::{stats, BinaryType, NaryType, TernaryType};
 use crate::{Gate, Network, Signal};
 
+pub use correspondence::{
+    check_equivalence_correspondence, match_registers_by_name, match_registers_by_order,
+    match_registers_by_simulation,
+};
+pub use counterexample::{explain_counterexample, CounterexampleReport, DivergentSignal};
+pub use partition::{
+    check_equivalence_partitioned, group_outputs_by_cone, group_outputs_individually, OutputResult,
+    OutputStatus,
+};
+pub use portfolio::{check_equivalence_bounded_portfolio, Strategy};
+pub use proof::{check_drat, resolve_with_certificate, to_dimacs, Certificate, ProofStep};
+
 // TODO: have clean clause builder object to encapsulate this part
 
 /// Add clauses for And-type n-ary function
@@ -209,27 +231,91 @@ fn extend_aig(a: &mut Network, b: &Network) -> HashMap<Signal, Signal> {
     t
 }
 
+/// Initial state of the flip-flops, used for sequential equivalence checking
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InitialState {
+    /// All flip-flops start at zero
+    Zero,
+    /// Flip-flops start with the given values, in the order of the flip-flops in the network
+    Fixed(Vec<bool>),
+    /// Flip-flops may start with any value
+    Arbitrary,
+}
+
+impl InitialState {
+    /// Number of free inputs necessary to represent the initial state
+    fn nb_free_inputs(&self, aig: &Network) -> usize {
+        match self {
+            InitialState::Arbitrary => stats::dff_gates(aig).len(),
+            _ => 0,
+        }
+    }
+
+    /// Obtain the initial value of each flip-flop, using free inputs starting at the given index if required
+    fn values(&self, aig: &Network, first_input: usize) -> Vec<Signal> {
+        let nb_dff = stats::dff_gates(aig).len();
+        match self {
+            InitialState::Zero => vec![Signal::zero(); nb_dff],
+            InitialState::Fixed(v) => {
+                assert_eq!(
+                    v.len(),
+                    nb_dff,
+                    "Initial state should have one value per flip-flop"
+                );
+                v.iter().map(|b| Signal::from(*b)).collect()
+            }
+            InitialState::Arbitrary => (0..nb_dff)
+                .map(|i| Signal::from_input((first_input + i) as u32))
+                .collect(),
+        }
+    }
+}
+
+/// Counterexample for sequential equivalence checking
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SequentialCounterexample {
+    /// Values of the inputs at each timestep
+    pub inputs: Vec<Vec<bool>>,
+    /// Initial values of the flip-flops of the first network
+    pub init_a: Vec<bool>,
+    /// Initial values of the flip-flops of the second network
+    pub init_b: Vec<bool>,
+}
+
 /// Unroll a sequential network over a fixed number of steps, making a larger combinatorial networks
+///
+/// All flip-flops are initialized to zero.
 pub fn unroll(aig: &Network, nb_steps: usize) -> Network {
+    unroll_with_init(aig, nb_steps, &InitialState::Zero)
+}
+
+/// Unroll a sequential network over a fixed number of steps, with a given initial state
+///
+/// The inputs of the unrolled network are the inputs of each step. If the initial state is
+/// arbitrary, they are followed by one additional input per flip-flop, representing its initial value.
+pub fn unroll_with_init(aig: &Network, nb_steps: usize, init: &InitialState) -> Network {
     use Gate::*;
     let mut ret = Network::new();
+    let init_values = init.values(aig, aig.nb_inputs() * nb_steps);
 
     let mut t_prev = HashMap::new();
     for step in 0..nb_steps {
         let mut t = HashMap::new();
 
         // Convert flip-flops for this step
+        let mut dff_ind = 0;
         for i in 0..aig.nb_nodes() {
             if let Dff([d, en, res]) = aig.gate(i) {
                 let ff = aig.node(i);
                 let unroll_ff = if step == 0 {
-                    Signal::zero()
+                    init_values[dff_ind]
                 } else {
                     let mx = ret.add_canonical(Gate::mux(t_prev[en], t_prev[d], t_prev[&ff]));
                     ret.and(mx, !t_prev[res])
                 };
                 t.insert(ff, unroll_ff);
                 t.insert(!ff, !unroll_ff);
+                dff_ind += 1;
             }
         }
 
@@ -242,12 +328,37 @@ pub fn unroll(aig: &Network, nb_steps: usize) -> Network {
         std::mem::swap(&mut t, &mut t_prev);
     }
     assert_eq!(ret.nb_inputs(), aig.nb_inputs() * nb_steps);
+    ret.add_inputs(init.nb_free_inputs(aig));
     assert_eq!(ret.nb_outputs(), aig.nb_outputs() * nb_steps);
     ret
 }
 
+/// Insert unused inputs in a combinatorial network, at the given position
+fn insert_inputs(aig: &Network, pos: usize, nb: usize) -> Network {
+    assert!(pos <= aig.nb_inputs());
+    let t = |s: &Signal| -> Signal {
+        if s.is_input() && s.input() as usize >= pos {
+            Signal::from_input(s.input() + nb as u32) ^ s.is_inverted()
+        } else {
+            *s
+        }
+    };
+    let mut ret = Network::new();
+    ret.add_inputs(aig.nb_inputs() + nb);
+    for i in 0..aig.nb_nodes() {
+        ret.add(aig.gate(i).remap(t));
+    }
+    for i in 0..aig.nb_outputs() {
+        ret.add_output(t(&aig.output(i)));
+    }
+    ret
+}
+
 /// Create a network with a single output, representing whether two combinatorial networks give different outputs
-pub fn difference(a: &Network, b: &Network) -> Network {
+///
+/// The optional constraint is a combinatorial network with the same inputs and a single output.
+/// Only the input values for which its output is true are considered.
+pub fn difference(a: &Network, b: &Network, constraint: Option<&Network>) -> Network {
     assert!(a.is_comb() && b.is_comb());
     assert_eq!(a.nb_inputs(), b.nb_inputs());
     assert_eq!(a.nb_outputs(), b.nb_outputs());
@@ -265,7 +376,17 @@ pub fn difference(a: &Network, b: &Network) -> Network {
         outputs.push(o);
     }
     let diff = eq.add_canonical(Gate::Nary(outputs.into(), NaryType::Or));
-    eq.add_output(diff);
+    match constraint {
+        None => eq.add_output(diff),
+        Some(c) => {
+            assert!(c.is_comb());
+            assert_eq!(c.nb_inputs(), a.nb_inputs());
+            assert_eq!(c.nb_outputs(), 1);
+            let tc = extend_aig(&mut eq, c);
+            let o = eq.and(diff, tc[&c.output(0)]);
+            eq.add_output(o);
+        }
+    }
     eq
 }
 
@@ -273,6 +394,13 @@ pub fn difference(a: &Network, b: &Network) -> Network {
 ///
 /// Returns the assignment, or None if no such assignment exists.
 pub fn prove(a: &Network) -> Option<Vec<bool>> {
+    prove_until(a, &AtomicBool::new(false)).expect("Sat solver couldn't run to completion")
+}
+
+/// Find an assignment of the inputs that sets the single output to 1, unless interrupted
+///
+/// The solver stops as soon as the flag is set, and returns None in this case.
+fn prove_until(a: &Network, stop: &AtomicBool) -> Option<Option<Vec<bool>>> {
     assert_eq!(a.nb_outputs(), 1);
 
     let clauses = to_cnf(a);
@@ -302,11 +430,18 @@ pub fn prove(a: &Network) -> Option<Vec<bool>> {
     }
     let out = a.output(0);
     if out == Signal::one() {
-        return Some(vec![false; a.nb_inputs()]);
+        return Some(Some(vec![false; a.nb_inputs()]));
     } else if out == Signal::zero() {
-        return None;
+        return Some(None);
     }
     solver.add_unit(t[&out]).unwrap();
+    solver.attach_terminator(|| {
+        if stop.load(Ordering::Relaxed) {
+            ControlSignal::Terminate
+        } else {
+            ControlSignal::Continue
+        }
+    });
 
     let res = solver.solve().unwrap();
     match res {
@@ -320,17 +455,28 @@ pub fn prove(a: &Network) -> Option<Vec<bool>> {
                 };
                 v.push(b);
             }
-            Some(v)
+            Some(Some(v))
         }
-        SolverResult::Unsat => None,
-        SolverResult::Interrupted => panic!("Sat solver couldn't run to completion"),
+        SolverResult::Unsat => Some(None),
+        SolverResult::Interrupted => None,
     }
 }
 
 /// Perform equivalence checking on two combinatorial networks
-pub fn check_equivalence_comb(a: &Network, b: &Network, optimize: bool) -> Result<(), Vec<bool>> {
-    assert!(a.is_comb() && b.is_comb());
-    let mut diff = difference(a, b);
+///
+/// The optional constraint is a combinatorial network with the same inputs and a single output, which
+/// is true for legal input values. The networks may differ for other input values.
+pub fn check_equivalence_comb(
+    a: &Network,
+    b: &Network,
+    constraint: Option<&Network>,
+    optimize: bool,
+) -> Result<(), Vec<bool>> {
+    check_difference(difference(a, b, constraint), optimize)
+}
+
+/// Find an assignment that sets the output of a difference network to 1
+fn check_difference(mut diff: Network, optimize: bool) -> Result<(), Vec<bool>> {
     if optimize {
         diff.make_canonical();
         diff.cleanup();
@@ -343,44 +489,206 @@ pub fn check_equivalence_comb(a: &Network, b: &Network, optimize: bool) -> Resul
 }
 
 /// Perform bounded equivalence checking on two sequential networks
+///
+/// All flip-flops are initialized to zero. The optional constraint is a network with the same
+/// inputs and a single output, which must be true at every cycle up to the one where the outputs differ.
 pub fn check_equivalence_bounded(
     a: &Network,
     b: &Network,
     nb_steps: usize,
+    constraint: Option<&Network>,
     optimize: bool,
 ) -> Result<(), Vec<Vec<bool>>> {
+    let init = InitialState::Zero;
+    check_equivalence_bounded_with_init(a, b, nb_steps, &init, &init, constraint, optimize)
+        .map_err(|c| c.inputs)
+}
+
+/// Unroll two sequential networks with the same inputs, for bounded equivalence checking
+///
+/// The inputs of both unrolled networks are the inputs of each step, followed by the free initial
+/// values of the first network then of the second network.
+fn unroll_pair(
+    a: &Network,
+    b: &Network,
+    nb_steps: usize,
+    init_a: &InitialState,
+    init_b: &InitialState,
+) -> (Network, Network) {
+    let nb_step_inputs = a.nb_inputs() * nb_steps;
+    let nb_free_a = init_a.nb_free_inputs(a);
+    let nb_free_b = init_b.nb_free_inputs(b);
+    let a_u = insert_inputs(
+        &unroll_with_init(a, nb_steps, init_a),
+        nb_step_inputs + nb_free_a,
+        nb_free_b,
+    );
+    let b_u = insert_inputs(
+        &unroll_with_init(b, nb_steps, init_b),
+        nb_step_inputs,
+        nb_free_a,
+    );
+    (a_u, b_u)
+}
+
+/// Create a network with a single output, representing whether two unrolled networks differ at a cycle where the constraint held so far
+///
+/// The unrolled constraint has one output per cycle. It must hold at every cycle up to the first
+/// cycle where the outputs differ, but not after it.
+fn difference_with_prefix_constraint(
+    a: &Network,
+    b: &Network,
+    constraint: &Network,
+    nb_steps: usize,
+) -> Network {
+    assert!(a.is_comb() && b.is_comb() && constraint.is_comb());
     assert_eq!(a.nb_inputs(), b.nb_inputs());
     assert_eq!(a.nb_outputs(), b.nb_outputs());
+    assert_eq!(constraint.nb_outputs(), nb_steps);
+    let nb_outputs = a.nb_outputs() / nb_steps.max(1);
 
-    let a_u = unroll(a, nb_steps);
-    let b_u = unroll(b, nb_steps);
+    let mut eq = Network::new();
+    eq.add_inputs(a.nb_inputs());
+    let ta = extend_aig(&mut eq, a);
+    let tb = extend_aig(&mut eq, b);
+    let tc = extend_aig(&mut eq, constraint);
 
-    let res = check_equivalence_comb(&a_u, &b_u, optimize);
-    match res {
-        Ok(()) => Ok(()),
-        Err(v) => {
-            assert_eq!(v.len(), a.nb_inputs() * nb_steps);
-            let mut assignment = Vec::<Vec<bool>>::new();
-            for step in 0..nb_steps {
-                let b = step * a.nb_inputs();
-                let e = (step + 1) * a.nb_inputs();
-                assignment.push(v[b..e].to_vec());
-            }
-            Err(assignment)
+    let mut valid = Signal::one();
+    let mut diffs = Vec::new();
+    for step in 0..nb_steps {
+        valid = eq.and(valid, tc[&constraint.output(step)]);
+        let mut outputs = Vec::new();
+        for i in step * nb_outputs..(step + 1) * nb_outputs {
+            outputs.push(eq.xor(ta[&a.output(i)], tb[&b.output(i)]));
+        }
+        let diff = eq.add_canonical(Gate::Nary(outputs.into(), NaryType::Or));
+        diffs.push(eq.and(valid, diff));
+    }
+    let o = eq.add_canonical(Gate::Nary(diffs.into(), NaryType::Or));
+    eq.add_output(o);
+    eq
+}
+
+/// Build the difference network for bounded equivalence checking, with the inputs of [`unroll_pair`]
+///
+/// The constraint is unrolled from a zero initial state, and must hold at every cycle up to the
+/// first cycle where the outputs differ.
+fn bounded_difference(
+    a: &Network,
+    b: &Network,
+    nb_steps: usize,
+    init_a: &InitialState,
+    init_b: &InitialState,
+    constraint: Option<&Network>,
+) -> Network {
+    let (a_u, b_u) = unroll_pair(a, b, nb_steps, init_a, init_b);
+    match constraint {
+        None => difference(&a_u, &b_u, None),
+        Some(c) => {
+            assert_eq!(c.nb_inputs(), a.nb_inputs());
+            assert_eq!(c.nb_outputs(), 1);
+            let c_u = insert_inputs(
+                &unroll(c, nb_steps),
+                a.nb_inputs() * nb_steps,
+                init_a.nb_free_inputs(a) + init_b.nb_free_inputs(b),
+            );
+            difference_with_prefix_constraint(&a_u, &b_u, &c_u, nb_steps)
         }
     }
 }
 
+/// Perform bounded equivalence checking on two sequential networks, with given initial states
+///
+/// With arbitrary initial states, the initial values of the flip-flops of the two networks are
+/// independent, and are returned as part of the counterexample.
+/// If a constraint network is given, its output must be true at every cycle of the counterexample,
+/// up to the cycle where the outputs differ.
+pub fn check_equivalence_bounded_with_init(
+    a: &Network,
+    b: &Network,
+    nb_steps: usize,
+    init_a: &InitialState,
+    init_b: &InitialState,
+    constraint: Option<&Network>,
+    optimize: bool,
+) -> Result<(), SequentialCounterexample> {
+    assert_eq!(a.nb_inputs(), b.nb_inputs());
+    assert_eq!(a.nb_outputs(), b.nb_outputs());
+
+    let diff = bounded_difference(a, b, nb_steps, init_a, init_b, constraint);
+    let res = check_difference(diff, optimize);
+    res.map_err(|v| decode_counterexample(a, b, nb_steps, init_a, init_b, &v))
+}
+
+/// Perform bounded equivalence checking on two sequential networks, re-solved with a proof certificate
+///
+/// This is similar to [`check_equivalence_bounded_with_init`], but the problem is solved by a
+/// slower internal solver instead of Kissat, to obtain a certificate of equivalence that can be
+/// checked independently. The certificate is for this independent re-solve.
+/// The certificate is for the unrolled miter of the two networks, without any optimization:
+/// optimizations could simplify it away, and the certificate would not relate to the networks.
+pub fn check_equivalence_bounded_resolved(
+    a: &Network,
+    b: &Network,
+    nb_steps: usize,
+    init_a: &InitialState,
+    init_b: &InitialState,
+    constraint: Option<&Network>,
+) -> Result<Certificate, SequentialCounterexample> {
+    assert_eq!(a.nb_inputs(), b.nb_inputs());
+    assert_eq!(a.nb_outputs(), b.nb_outputs());
+
+    let diff = bounded_difference(a, b, nb_steps, init_a, init_b, constraint);
+    resolve_with_certificate(&diff)
+        .map_err(|v| decode_counterexample(a, b, nb_steps, init_a, init_b, &v))
+}
+
+/// Convert an assignment of the inputs of the bounded difference network to a counterexample
+fn decode_counterexample(
+    a: &Network,
+    b: &Network,
+    nb_steps: usize,
+    init_a: &InitialState,
+    init_b: &InitialState,
+    v: &[bool],
+) -> SequentialCounterexample {
+    let nb_step_inputs = a.nb_inputs() * nb_steps;
+    let nb_free_a = init_a.nb_free_inputs(a);
+    let nb_free_b = init_b.nb_free_inputs(b);
+    assert_eq!(v.len(), nb_step_inputs + nb_free_a + nb_free_b);
+    let mut assignment = Vec::<Vec<bool>>::new();
+    for step in 0..nb_steps {
+        let b = step * a.nb_inputs();
+        let e = (step + 1) * a.nb_inputs();
+        assignment.push(v[b..e].to_vec());
+    }
+    let free_a = &v[nb_step_inputs..nb_step_inputs + nb_free_a];
+    let free_b = &v[nb_step_inputs + nb_free_a..];
+    let init_value = |init: &InitialState, aig: &Network, free: &[bool]| match init {
+        InitialState::Zero => vec![false; stats::dff_gates(aig).len()],
+        InitialState::Fixed(v) => v.clone(),
+        InitialState::Arbitrary => free.to_vec(),
+    };
+    SequentialCounterexample {
+        inputs: assignment,
+        init_a: init_value(init_a, a, free_a),
+        init_b: init_value(init_b, b, free_b),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use volute::Lut;
 
-    use crate::equiv::unroll;
+    use crate::equiv::{unroll, unroll_with_init, InitialState};
     use crate::network::stats::stats;
     use crate::network::NaryType;
     use crate::{Gate, Network, Signal};
 
-    use super::{check_equivalence_comb, prove};
+    use super::{
+        bounded_difference, check_drat, check_equivalence_bounded,
+        check_equivalence_bounded_resolved, check_equivalence_comb, prove, to_dimacs,
+    };
 
     #[test]
     fn test_equiv_and() {
@@ -394,8 +702,8 @@ mod tests {
         b.add_input();
         let ab = b.and(l1, l2);
         b.add_output(ab);
-        check_equivalence_comb(&a, &b, false).unwrap();
-        check_equivalence_comb(&a, &b, true).unwrap();
+        check_equivalence_comb(&a, &b, None, false).unwrap();
+        check_equivalence_comb(&a, &b, None, true).unwrap();
     }
 
     #[test]
@@ -409,7 +717,7 @@ mod tests {
         b.add_input();
         b.add_input();
         b.add_output(Signal::zero());
-        let res = check_equivalence_comb(&a, &b, false);
+        let res = check_equivalence_comb(&a, &b, None, false);
         assert_eq!(res, Err(vec![true, true]));
     }
 
@@ -425,7 +733,7 @@ mod tests {
         b.add_input();
         let ab = !b.and(!l1, !l2);
         b.add_output(ab);
-        let res = check_equivalence_comb(&a, &b, false);
+        let res = check_equivalence_comb(&a, &b, None, false);
         assert_ne!(res, Ok(()));
     }
 
@@ -439,7 +747,7 @@ mod tests {
         b.add_input();
         b.add_input();
         b.add_output(Signal::zero());
-        let res = check_equivalence_comb(&a, &b, false);
+        let res = check_equivalence_comb(&a, &b, None, false);
         assert_ne!(res, Ok(()));
     }
 
@@ -457,8 +765,8 @@ mod tests {
         b.add_input();
         let bx = b.xor(l1, l2);
         b.add_output(bx);
-        check_equivalence_comb(&a, &b, false).unwrap();
-        check_equivalence_comb(&a, &b, true).unwrap();
+        check_equivalence_comb(&a, &b, None, false).unwrap();
+        check_equivalence_comb(&a, &b, None, true).unwrap();
     }
 
     #[test]
@@ -477,8 +785,8 @@ mod tests {
         b.add_input();
         let bx = b.add_canonical(Gate::mux(l1, l2, l3));
         b.add_output(bx);
-        check_equivalence_comb(&a, &b, false).unwrap();
-        check_equivalence_comb(&a, &b, true).unwrap();
+        check_equivalence_comb(&a, &b, None, false).unwrap();
+        check_equivalence_comb(&a, &b, None, true).unwrap();
     }
 
     #[test]
@@ -498,8 +806,8 @@ mod tests {
         b.add_input();
         let bx = b.add(Gate::maj(l1, l2, l3));
         b.add_output(bx);
-        check_equivalence_comb(&a, &b, false).unwrap();
-        check_equivalence_comb(&a, &b, true).unwrap();
+        check_equivalence_comb(&a, &b, None, false).unwrap();
+        check_equivalence_comb(&a, &b, None, true).unwrap();
     }
 
     #[test]
@@ -517,8 +825,8 @@ mod tests {
         b.add_input();
         let b2 = b.add(Gate::and3(l1, l2, l3));
         b.add_output(b2);
-        check_equivalence_comb(&a, &b, false).unwrap();
-        check_equivalence_comb(&a, &b, true).unwrap();
+        check_equivalence_comb(&a, &b, None, false).unwrap();
+        check_equivalence_comb(&a, &b, None, true).unwrap();
     }
 
     #[test]
@@ -536,8 +844,8 @@ mod tests {
         b.add_input();
         let b2 = b.add(Gate::xor3(l1, l2, l3));
         b.add_output(b2);
-        check_equivalence_comb(&a, &b, false).unwrap();
-        check_equivalence_comb(&a, &b, true).unwrap();
+        check_equivalence_comb(&a, &b, None, false).unwrap();
+        check_equivalence_comb(&a, &b, None, true).unwrap();
     }
 
     #[test]
@@ -558,8 +866,8 @@ mod tests {
             }
             let bo = b.add(Gate::Nary(v.into(), NaryType::And));
             b.add_output(bo);
-            check_equivalence_comb(&a, &b, false).unwrap();
-            check_equivalence_comb(&a, &b, true).unwrap();
+            check_equivalence_comb(&a, &b, None, false).unwrap();
+            check_equivalence_comb(&a, &b, None, true).unwrap();
         }
     }
 
@@ -581,8 +889,8 @@ mod tests {
             }
             let bo = b.add(Gate::Nary(v.into(), NaryType::Xor));
             b.add_output(bo);
-            check_equivalence_comb(&a, &b, false).unwrap();
-            check_equivalence_comb(&a, &b, true).unwrap();
+            check_equivalence_comb(&a, &b, None, false).unwrap();
+            check_equivalence_comb(&a, &b, None, true).unwrap();
         }
     }
 
@@ -596,8 +904,8 @@ mod tests {
             let lb = b.add_input();
             b.add_output(lb);
         }
-        check_equivalence_comb(&a, &b, false).unwrap();
-        check_equivalence_comb(&a, &b, true).unwrap();
+        check_equivalence_comb(&a, &b, None, false).unwrap();
+        check_equivalence_comb(&a, &b, None, true).unwrap();
     }
 
     #[test]
@@ -610,7 +918,7 @@ mod tests {
             let lb = b.add_input();
             b.add_output(!lb);
         }
-        let res = check_equivalence_comb(&a, &b, false);
+        let res = check_equivalence_comb(&a, &b, None, false);
         assert_ne!(res, Ok(()));
     }
 
@@ -625,7 +933,7 @@ mod tests {
         let l = Signal::from_input(0);
         a.add_output(l);
         b.add_output(!l);
-        let res = check_equivalence_comb(&a, &b, false);
+        let res = check_equivalence_comb(&a, &b, None, false);
         assert_ne!(res, Ok(()));
     }
 
@@ -666,6 +974,25 @@ mod tests {
         assert_eq!(un.output(0), Signal::zero());
     }
 
+    #[test]
+    fn test_init_unrolling() {
+        let mut a = Network::new();
+        let i0 = a.add_input();
+        let d0 = a.dff(i0, Signal::one(), Signal::zero());
+        let d1 = a.dff(d0, Signal::one(), Signal::zero());
+        a.add_output(d1);
+
+        let un = unroll_with_init(&a, 2, &InitialState::Fixed(vec![true, false]));
+        assert_eq!(un.nb_inputs(), 2);
+        assert_eq!(un.output(0), Signal::zero());
+        assert_eq!(un.output(1), Signal::one());
+
+        let un = unroll_with_init(&a, 2, &InitialState::Arbitrary);
+        assert_eq!(un.nb_inputs(), 4);
+        assert_eq!(un.output(0), un.input(3));
+        assert_eq!(un.output(1), un.input(2));
+    }
+
     #[test]
     fn test_prove_and() {
         let mut a = Network::new();
@@ -697,8 +1024,8 @@ mod tests {
         let lut = Lut::nth_var(3, 0) ^ Lut::nth_var(3, 1) ^ Lut::nth_var(3, 2);
         let b2 = b.add(Gate::lut(&[l1, l2, l3], lut));
         b.add_output(b2);
-        check_equivalence_comb(&a, &b, false).unwrap();
-        check_equivalence_comb(&a, &b, true).unwrap();
+        check_equivalence_comb(&a, &b, None, false).unwrap();
+        check_equivalence_comb(&a, &b, None, true).unwrap();
     }
 
     #[test]
@@ -717,8 +1044,8 @@ mod tests {
         let lut = Lut::nth_var(3, 0) & Lut::nth_var(3, 1) & Lut::nth_var(3, 2);
         let b2 = b.add(Gate::lut(&[l1, l2, l3], lut));
         b.add_output(b2);
-        check_equivalence_comb(&a, &b, false).unwrap();
-        check_equivalence_comb(&a, &b, true).unwrap();
+        check_equivalence_comb(&a, &b, None, false).unwrap();
+        check_equivalence_comb(&a, &b, None, true).unwrap();
     }
 
     #[test]
@@ -736,8 +1063,8 @@ mod tests {
         let lut = !Lut::nth_var(3, 0) & !Lut::nth_var(3, 1) & Lut::nth_var(3, 2);
         let b2 = b.add(Gate::lut(&[l1, l2, l3], lut));
         b.add_output(b2);
-        check_equivalence_comb(&a, &b, false).unwrap();
-        check_equivalence_comb(&a, &b, true).unwrap();
+        check_equivalence_comb(&a, &b, None, false).unwrap();
+        check_equivalence_comb(&a, &b, None, true).unwrap();
     }
 
     #[test]
@@ -755,7 +1082,99 @@ mod tests {
         let lut = Lut::nth_var(3, 0) & Lut::nth_var(3, 1) & Lut::nth_var(3, 2);
         let b2 = b.add(Gate::lut(&[!l1, !l2, !l3], lut));
         b.add_output(b2);
-        check_equivalence_comb(&a, &b, false).unwrap();
-        check_equivalence_comb(&a, &b, true).unwrap();
+        check_equivalence_comb(&a, &b, None, false).unwrap();
+        check_equivalence_comb(&a, &b, None, true).unwrap();
+    }
+
+    #[test]
+    fn test_equiv_constraint() {
+        // And gate vs buffer, equivalent when the mode input is set
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let aa = a.and(l1, l2);
+        a.add_output(aa);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_input();
+        b.add_output(l1);
+        let mut c = Network::new();
+        c.add_input();
+        c.add_input();
+        c.add_output(l2);
+        assert_eq!(
+            check_equivalence_comb(&a, &b, None, false),
+            Err(vec![true, false])
+        );
+        check_equivalence_comb(&a, &b, Some(&c), false).unwrap();
+        check_equivalence_comb(&a, &b, Some(&c), true).unwrap();
+        let mut c_neg = Network::new();
+        c_neg.add_inputs(2);
+        c_neg.add_output(!l2);
+        let res = check_equivalence_comb(&a, &b, Some(&c_neg), false);
+        assert_eq!(res, Err(vec![true, false]));
+    }
+
+    #[test]
+    fn test_equiv_bounded_constraint() {
+        // Delayed input vs zero, equivalent when the input is tied to zero
+        let mut a = Network::new();
+        let l = a.add_input();
+        let d = a.dff(l, Signal::one(), Signal::zero());
+        a.add_output(d);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_output(Signal::zero());
+        let mut c = Network::new();
+        c.add_input();
+        c.add_output(!l);
+        check_equivalence_bounded(&a, &b, 3, Some(&c), false).unwrap();
+        let mut c_one = Network::new();
+        c_one.add_input();
+        c_one.add_output(Signal::one());
+        let res = check_equivalence_bounded(&a, &b, 3, Some(&c_one), false);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_certificate_not_optimized() {
+        // Identical networks: the certificate must still encode both copies of the logic
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let x = a.xor(l1, l2);
+        let d = a.dff(x, Signal::one(), Signal::zero());
+        let o = a.and(d, l1);
+        a.add_output(o);
+        let init = InitialState::Zero;
+        let cert = check_equivalence_bounded_resolved(&a, &a, 2, &init, &init, None).unwrap();
+        let diff = bounded_difference(&a, &a, 2, &init, &init, None);
+        let (nb_vars, cnf, _) = to_dimacs(&diff);
+        assert_eq!(cert.nb_vars, nb_vars);
+        assert_eq!(cert.cnf.len(), cnf.len());
+        let mut optimized = diff.clone();
+        optimized.make_canonical();
+        optimized.cleanup();
+        assert!(to_dimacs(&optimized).1.len() < cnf.len());
+        check_drat(cert.nb_vars, &cert.cnf, &cert.proof).unwrap();
+    }
+
+    #[test]
+    fn test_equiv_bounded_constraint_after_divergence() {
+        // Input at the first cycle vs zero, with a constraint that only fails after the outputs differ
+        let mut a = Network::new();
+        let l = a.add_input();
+        let started = a.dff(Signal::one(), Signal::one(), Signal::zero());
+        let o = a.and(l, !started);
+        a.add_output(o);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_output(Signal::zero());
+        let mut c = Network::new();
+        let lc = c.add_input();
+        let d = c.dff(lc, Signal::one(), Signal::zero());
+        c.add_output(!d);
+        let res = check_equivalence_bounded(&a, &b, 2, Some(&c), false);
+        assert_eq!(res.unwrap_err()[0], vec![true]);
     }
 }
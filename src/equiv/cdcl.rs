@@ -0,0 +1,415 @@
+//! Simple CDCL Sat solver that logs a DRAT proof
+//!
+//! Kissat is much faster, but does not give access to its proof. This solver re-solves the
+//! problems that require a proof certificate, independently from Kissat.
+
+use std::collections::BinaryHeap;
+
+use super::ProofStep;
+
+/// Literal of the solver, as 2 * variable + sign
+type Lit = u32;
+
+fn lit_from_dimacs(l: i32) -> Lit {
+    assert_ne!(l, 0);
+    2 * (l.unsigned_abs() - 1) + (l < 0) as u32
+}
+
+fn lit_to_dimacs(l: Lit) -> i32 {
+    let v = (l / 2 + 1) as i32;
+    if l & 1 != 0 {
+        -v
+    } else {
+        v
+    }
+}
+
+/// Luby sequence, used for restarts
+fn luby(mut i: u64) -> u64 {
+    let mut size = 1;
+    let mut seq = 0;
+    while size < i + 1 {
+        seq += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != i {
+        size = (size - 1) >> 1;
+        seq -= 1;
+        i %= size;
+    }
+    1 << seq
+}
+
+/// CDCL solver with two watched literals, activity-based branching, phase saving and restarts
+pub(super) struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    watches: Vec<Vec<usize>>,
+    value: Vec<Option<bool>>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    phase: Vec<bool>,
+    activity: Vec<f64>,
+    var_inc: f64,
+    heap: BinaryHeap<(u64, u32)>,
+    trail: Vec<Lit>,
+    trail_lim: Vec<usize>,
+    qhead: usize,
+    seen: Vec<bool>,
+    unsat: bool,
+    proof: Vec<ProofStep>,
+}
+
+impl Solver {
+    /// Create a solver with a given number of variables
+    pub fn new(nb_vars: usize) -> Solver {
+        let mut ret = Solver {
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); 2 * nb_vars],
+            value: vec![None; nb_vars],
+            level: vec![0; nb_vars],
+            reason: vec![None; nb_vars],
+            phase: vec![false; nb_vars],
+            activity: vec![0.0; nb_vars],
+            var_inc: 1.0,
+            heap: BinaryHeap::new(),
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+            qhead: 0,
+            seen: vec![false; nb_vars],
+            unsat: false,
+            proof: Vec::new(),
+        };
+        for v in 0..nb_vars {
+            ret.heap.push((0, v as u32));
+        }
+        ret
+    }
+
+    /// Proof obtained so far
+    pub fn proof(self) -> Vec<ProofStep> {
+        self.proof
+    }
+
+    fn lit_value(&self, l: Lit) -> Option<bool> {
+        self.value[(l / 2) as usize].map(|b| b ^ (l & 1 != 0))
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    fn enqueue(&mut self, l: Lit, reason: Option<usize>) {
+        let v = (l / 2) as usize;
+        self.value[v] = Some(l & 1 == 0);
+        self.level[v] = self.decision_level();
+        self.reason[v] = reason;
+        self.trail.push(l);
+    }
+
+    fn attach(&mut self, clause: Vec<Lit>) -> usize {
+        let id = self.clauses.len();
+        self.watches[clause[0] as usize].push(id);
+        self.watches[clause[1] as usize].push(id);
+        self.clauses.push(clause);
+        id
+    }
+
+    /// Add a clause of the original formula, with literals in DIMACS format
+    pub fn add_clause(&mut self, clause: &[i32]) {
+        assert_eq!(self.decision_level(), 0);
+        if self.unsat {
+            return;
+        }
+        let mut lits: Vec<Lit> = clause.iter().map(|l| lit_from_dimacs(*l)).collect();
+        lits.sort();
+        lits.dedup();
+        if lits.windows(2).any(|w| w[0] ^ 1 == w[1]) {
+            return;
+        }
+        lits.retain(|l| self.lit_value(*l) != Some(false) || self.level[(l / 2) as usize] != 0);
+        if lits.iter().any(|l| self.lit_value(*l) == Some(true)) {
+            return;
+        }
+        match lits.len() {
+            0 => self.unsat = true,
+            1 => {
+                self.enqueue(lits[0], None);
+                if self.propagate().is_some() {
+                    self.unsat = true;
+                }
+            }
+            _ => {
+                self.attach(lits);
+            }
+        }
+    }
+
+    /// Propagate the assignments in the queue, and return a conflicting clause if any
+    fn propagate(&mut self) -> Option<usize> {
+        while self.qhead < self.trail.len() {
+            let false_lit = self.trail[self.qhead] ^ 1;
+            self.qhead += 1;
+            let mut watchers = std::mem::take(&mut self.watches[false_lit as usize]);
+            let mut i = 0;
+            let mut conflict = None;
+            while i < watchers.len() {
+                let id = watchers[i];
+                let clause = &mut self.clauses[id];
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                if self.value[(first / 2) as usize].map(|b| b ^ (first & 1 != 0)) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+                // Look for a new literal to watch
+                let mut found = false;
+                for k in 2..clause.len() {
+                    let l = clause[k];
+                    if self.value[(l / 2) as usize].map(|b| b ^ (l & 1 != 0)) != Some(false) {
+                        clause.swap(1, k);
+                        self.watches[clause[1] as usize].push(id);
+                        watchers.swap_remove(i);
+                        found = true;
+                        break;
+                    }
+                }
+                if found {
+                    continue;
+                }
+                i += 1;
+                if self.lit_value(first) == Some(false) {
+                    conflict = Some(id);
+                    break;
+                }
+                self.enqueue(first, Some(id));
+            }
+            self.watches[false_lit as usize].extend(watchers);
+            if conflict.is_some() {
+                self.qhead = self.trail.len();
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn bump(&mut self, v: usize) {
+        self.activity[v] += self.var_inc;
+        if self.activity[v] > 1e100 {
+            for a in self.activity.iter_mut() {
+                *a *= 1e-100;
+            }
+            self.var_inc *= 1e-100;
+            self.heap = (0..self.activity.len())
+                .filter(|v| self.value[*v].is_none())
+                .map(|v| (self.activity[v].to_bits(), v as u32))
+                .collect();
+        } else if self.value[v].is_none() {
+            self.heap.push((self.activity[v].to_bits(), v as u32));
+        }
+    }
+
+    /// Analyze a conflict, and return the learnt clause with its asserting literal first and the backtracking level
+    fn analyze(&mut self, conflict: usize) -> (Vec<Lit>, usize) {
+        let mut learnt = vec![0];
+        let mut nb_pending = 0;
+        let mut index = self.trail.len();
+        let mut clause_id = conflict;
+        let mut pivot: Option<Lit> = None;
+        loop {
+            let clause = self.clauses[clause_id].clone();
+            for &l in &clause {
+                if Some(l) == pivot {
+                    continue;
+                }
+                let v = (l / 2) as usize;
+                if self.seen[v] || self.level[v] == 0 {
+                    continue;
+                }
+                self.seen[v] = true;
+                self.bump(v);
+                if self.level[v] == self.decision_level() {
+                    nb_pending += 1;
+                } else {
+                    learnt.push(l);
+                }
+            }
+            // Next literal of the current level on the trail
+            loop {
+                index -= 1;
+                if self.seen[(self.trail[index] / 2) as usize] {
+                    break;
+                }
+            }
+            let l = self.trail[index];
+            self.seen[(l / 2) as usize] = false;
+            nb_pending -= 1;
+            if nb_pending == 0 {
+                learnt[0] = l ^ 1;
+                break;
+            }
+            pivot = Some(l);
+            clause_id = self.reason[(l / 2) as usize].unwrap();
+        }
+
+        // Remove literals implied by the others
+        let keep: Vec<bool> = learnt
+            .iter()
+            .enumerate()
+            .map(|(i, l)| {
+                i == 0
+                    || match self.reason[(l / 2) as usize] {
+                        None => true,
+                        Some(r) => self.clauses[r].iter().any(|o| {
+                            let v = (o / 2) as usize;
+                            v != (l / 2) as usize && !self.seen[v] && self.level[v] != 0
+                        }),
+                    }
+            })
+            .collect();
+        for l in &learnt {
+            self.seen[(l / 2) as usize] = false;
+        }
+        let mut learnt: Vec<Lit> = learnt
+            .into_iter()
+            .zip(keep)
+            .filter(|(_, k)| *k)
+            .map(|(l, _)| l)
+            .collect();
+
+        // Backtrack to the highest level among the other literals, which becomes the second watch
+        let mut bt_level = 0;
+        if learnt.len() > 1 {
+            let mut max_i = 1;
+            for i in 2..learnt.len() {
+                if self.level[(learnt[i] / 2) as usize] > self.level[(learnt[max_i] / 2) as usize] {
+                    max_i = i;
+                }
+            }
+            learnt.swap(1, max_i);
+            bt_level = self.level[(learnt[1] / 2) as usize];
+        }
+        (learnt, bt_level)
+    }
+
+    fn cancel_until(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let start = self.trail_lim[level];
+        for i in (start..self.trail.len()).rev() {
+            let l = self.trail[i];
+            let v = (l / 2) as usize;
+            self.phase[v] = l & 1 == 0;
+            self.value[v] = None;
+            self.reason[v] = None;
+            self.heap.push((self.activity[v].to_bits(), v as u32));
+        }
+        self.trail.truncate(start);
+        self.trail_lim.truncate(level);
+        self.qhead = start;
+    }
+
+    fn pick_branch(&mut self) -> Option<Lit> {
+        while let Some((_, v)) = self.heap.pop() {
+            let v = v as usize;
+            if self.value[v].is_none() {
+                return Some(2 * v as u32 + (!self.phase[v]) as u32);
+            }
+        }
+        None
+    }
+
+    /// Solve the formula
+    ///
+    /// Returns the value of each variable if it is satisfiable, or None if it is not; in this case,
+    /// the proof ends with the empty clause.
+    pub fn solve(&mut self) -> Option<Vec<bool>> {
+        if !self.unsat && self.propagate().is_some() {
+            self.unsat = true;
+        }
+        if self.unsat {
+            self.proof.push(ProofStep::Add(Vec::new()));
+            return None;
+        }
+        let mut nb_restarts = 0;
+        let mut nb_conflicts = 0;
+        let mut restart_limit = 100 * luby(0);
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.decision_level() == 0 {
+                    self.unsat = true;
+                    self.proof.push(ProofStep::Add(Vec::new()));
+                    return None;
+                }
+                let (learnt, bt_level) = self.analyze(conflict);
+                self.cancel_until(bt_level);
+                self.proof.push(ProofStep::Add(
+                    learnt.iter().map(|l| lit_to_dimacs(*l)).collect(),
+                ));
+                if learnt.len() == 1 {
+                    self.enqueue(learnt[0], None);
+                } else {
+                    let first = learnt[0];
+                    let id = self.attach(learnt);
+                    self.enqueue(first, Some(id));
+                }
+                self.var_inc *= 1.0 / 0.95;
+                nb_conflicts += 1;
+                if nb_conflicts >= restart_limit {
+                    nb_conflicts = 0;
+                    nb_restarts += 1;
+                    restart_limit = 100 * luby(nb_restarts);
+                    self.cancel_until(0);
+                }
+            } else {
+                match self.pick_branch() {
+                    None => {
+                        let model = self.value.iter().map(|v| v.unwrap()).collect();
+                        self.cancel_until(0);
+                        return Some(model);
+                    }
+                    Some(l) => {
+                        self.trail_lim.push(self.trail.len());
+                        self.enqueue(l, None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{luby, Solver};
+
+    #[test]
+    fn test_luby() {
+        let seq: Vec<u64> = (0..15).map(luby).collect();
+        assert_eq!(seq, vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+    }
+
+    #[test]
+    fn test_sat() {
+        let mut s = Solver::new(3);
+        s.add_clause(&[1, 2]);
+        s.add_clause(&[-1, 3]);
+        s.add_clause(&[-2, -3]);
+        let model = s.solve().unwrap();
+        assert!(model[0] || model[1]);
+        assert!(!model[0] || model[2]);
+        assert!(!model[1] || !model[2]);
+    }
+
+    #[test]
+    fn test_unsat() {
+        // All assignments of two variables are excluded
+        let mut s = Solver::new(2);
+        s.add_clause(&[1, 2]);
+        s.add_clause(&[-1, 2]);
+        s.add_clause(&[1, -2]);
+        s.add_clause(&[-1, -2]);
+        assert!(s.solve().is_none());
+    }
+}
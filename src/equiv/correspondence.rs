@@ -0,0 +1,397 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::iter::zip;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::network::stats;
+use crate::sim::{simulate_comb, simulate_multi_from};
+use crate::{Gate, Network, Signal};
+
+use super::{check_equivalence_comb, insert_inputs, InitialState};
+
+/// Match the flip-flops of two networks in order
+///
+/// Flip-flops keep the order in which they are declared in the original files.
+pub fn match_registers_by_order(a: &Network, b: &Network) -> Vec<(usize, usize)> {
+    zip(stats::dff_gates(a), stats::dff_gates(b)).collect()
+}
+
+/// Match the flip-flops of two networks by name
+///
+/// Names are not kept in the network, and are given with one name per flip-flop, in network order,
+/// as returned by [`crate::io::read_dff_names_file`]. Flip-flops without a namesake are left unmatched.
+pub fn match_registers_by_name(
+    a: &Network,
+    names_a: &[String],
+    b: &Network,
+    names_b: &[String],
+) -> Vec<(usize, usize)> {
+    let dffs_a = stats::dff_gates(a);
+    let dffs_b = stats::dff_gates(b);
+    assert_eq!(dffs_a.len(), names_a.len());
+    assert_eq!(dffs_b.len(), names_b.len());
+    let by_name: HashMap<&String, usize> = zip(names_b, dffs_b).collect();
+    zip(names_a, dffs_a)
+        .filter_map(|(n, d)| by_name.get(n).map(|d_b| (d, *d_b)))
+        .collect()
+}
+
+/// Compute the simulation signature of each flip-flop, over random input sequences
+///
+/// Arbitrary initial states are simulated from zero, as there is no way to relate the
+/// initial states of the two networks before the correspondence is known.
+fn register_signatures(
+    aig: &Network,
+    init: &InitialState,
+    inputs: &Vec<Vec<u64>>,
+) -> Vec<Vec<u64>> {
+    let dffs = stats::dff_gates(aig);
+    let mut observed = aig.clone();
+    for d in &dffs {
+        observed.add_output(aig.node(*d));
+    }
+    let init_values: Vec<u64> = match init {
+        InitialState::Fixed(v) => v.iter().map(|b| if *b { !0 } else { 0 }).collect(),
+        _ => vec![0; dffs.len()],
+    };
+    let values = simulate_multi_from(&observed, &init_values, inputs);
+    (0..dffs.len())
+        .map(|i| values.iter().map(|v| v[aig.nb_outputs() + i]).collect())
+        .collect()
+}
+
+/// Match the flip-flops of two networks based on their simulation signatures
+///
+/// Both networks are simulated from their initial state using the same random input sequences,
+/// and flip-flops that take the same values at every cycle are matched.
+/// The resulting correspondence is only a candidate and needs to be proven.
+pub fn match_registers_by_simulation(
+    a: &Network,
+    b: &Network,
+    init_a: &InitialState,
+    init_b: &InitialState,
+    nb_cycles: usize,
+    seed: u64,
+) -> Vec<(usize, usize)> {
+    assert_eq!(a.nb_inputs(), b.nb_inputs());
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let inputs: Vec<Vec<u64>> = (0..nb_cycles)
+        .map(|_| (0..a.nb_inputs()).map(|_| rng.gen()).collect())
+        .collect();
+    let sig_a = register_signatures(a, init_a, &inputs);
+    let sig_b = register_signatures(b, init_b, &inputs);
+
+    let mut candidates = HashMap::<Vec<u64>, Vec<usize>>::new();
+    for (dff, sig) in zip(stats::dff_gates(b), sig_b).rev() {
+        candidates.entry(sig).or_default().push(dff);
+    }
+    let mut ret = Vec::new();
+    for (dff, sig) in zip(stats::dff_gates(a), sig_a) {
+        if let Some(dff_b) = candidates.get_mut(&sig).and_then(|v| v.pop()) {
+            ret.push((dff, dff_b));
+        }
+    }
+    ret
+}
+
+/// Build the combinatorial transition network of a sequential network
+///
+/// Its inputs are the primary inputs followed by state inputs, whose position is given for each flip-flop.
+/// Its outputs are the primary outputs (optionally) followed by the next state of the given flip-flops.
+fn transition_network(
+    aig: &Network,
+    nb_inputs: usize,
+    state_inputs: &HashMap<usize, usize>,
+    next_state: &[usize],
+    with_outputs: bool,
+) -> Network {
+    let mut ret = Network::new();
+    ret.add_inputs(nb_inputs);
+    let mut t = HashMap::<Signal, Signal>::new();
+    t.insert(Signal::zero(), Signal::zero());
+    t.insert(Signal::one(), Signal::one());
+    for i in 0..aig.nb_inputs() {
+        t.insert(aig.input(i), ret.input(i));
+        t.insert(!aig.input(i), !ret.input(i));
+    }
+    for (dff, input) in state_inputs {
+        t.insert(aig.node(*dff), ret.input(*input));
+        t.insert(!aig.node(*dff), !ret.input(*input));
+    }
+    for i in 0..aig.nb_nodes() {
+        if !aig.gate(i).is_comb() {
+            continue;
+        }
+        let s = ret.add(aig.gate(i).remap(|s| t[s]));
+        t.insert(aig.node(i), s);
+        t.insert(!aig.node(i), !s);
+    }
+    if with_outputs {
+        for o in 0..aig.nb_outputs() {
+            ret.add_output(t[&aig.output(o)]);
+        }
+    }
+    for dff in next_state {
+        if let Gate::Dff([d, en, res]) = aig.gate(*dff) {
+            let mx = ret.add_canonical(Gate::mux(t[en], t[d], t[&aig.node(*dff)]));
+            let next = ret.and(mx, !t[res]);
+            ret.add_output(next);
+        }
+    }
+    ret
+}
+
+/// Build the transition networks of both designs, with shared state inputs for corresponding flip-flops
+fn transition_networks(
+    a: &Network,
+    b: &Network,
+    correspondence: &[(usize, usize)],
+    with_outputs: bool,
+) -> (Network, Network) {
+    let nb_pairs = correspondence.len();
+    let mut state_a = HashMap::new();
+    let mut state_b = HashMap::new();
+    for (i, (da, db)) in correspondence.iter().enumerate() {
+        state_a.insert(*da, a.nb_inputs() + i);
+        state_b.insert(*db, a.nb_inputs() + i);
+    }
+    let mut nb_inputs = a.nb_inputs() + nb_pairs;
+    for (aig, state) in [(a, &mut state_a), (b, &mut state_b)] {
+        for dff in stats::dff_gates(aig) {
+            if let Entry::Vacant(e) = state.entry(dff) {
+                e.insert(nb_inputs);
+                nb_inputs += 1;
+            }
+        }
+    }
+    let next_a: Vec<usize> = correspondence.iter().map(|p| p.0).collect();
+    let next_b: Vec<usize> = correspondence.iter().map(|p| p.1).collect();
+    (
+        transition_network(a, nb_inputs, &state_a, &next_a, with_outputs),
+        transition_network(b, nb_inputs, &state_b, &next_b, with_outputs),
+    )
+}
+
+/// Compare two transition networks, for the primary input values allowed by a combinatorial constraint
+fn check_transition(
+    ta: &Network,
+    tb: &Network,
+    constraint: Option<&Network>,
+    optimize: bool,
+) -> Result<(), Vec<bool>> {
+    let c_t = constraint.map(|c| insert_inputs(c, c.nb_inputs(), ta.nb_inputs() - c.nb_inputs()));
+    check_equivalence_comb(ta, tb, c_t.as_ref(), optimize)
+}
+
+/// Perform unbounded equivalence checking on two sequential networks, based on a correspondence between their flip-flops
+///
+/// The candidate correspondence is refined by induction until corresponding flip-flops provably hold
+/// the same value in every reachable state. The outputs are then compared with a combinatorial check,
+/// assuming corresponding flip-flops are equal and the others are unconstrained.
+/// Arbitrary initial states are unrelated between the two networks, as in bounded equivalence
+/// checking: flip-flops with an arbitrary initial value are never assumed to correspond.
+///
+/// If a combinatorial constraint network is given, only the input values for which it is true are
+/// considered, both for the transitions and for the outputs.
+///
+/// Returns the proven correspondence if the networks are equivalent, or None if the equivalence
+/// could not be proven: this does not mean that the networks are not equivalent.
+pub fn check_equivalence_correspondence(
+    a: &Network,
+    b: &Network,
+    init_a: &InitialState,
+    init_b: &InitialState,
+    correspondence: &[(usize, usize)],
+    constraint: Option<&Network>,
+    optimize: bool,
+) -> Option<Vec<(usize, usize)>> {
+    assert_eq!(a.nb_inputs(), b.nb_inputs());
+    assert_eq!(a.nb_outputs(), b.nb_outputs());
+    if let Some(c) = constraint {
+        assert!(
+            c.is_comb(),
+            "Register correspondence requires a combinatorial constraint"
+        );
+        assert_eq!(c.nb_inputs(), a.nb_inputs());
+        assert_eq!(c.nb_outputs(), 1);
+    }
+
+    // Base case: corresponding flip-flops must have the same known initial value
+    let position = |aig: &Network| -> HashMap<usize, usize> {
+        stats::dff_gates(aig)
+            .into_iter()
+            .enumerate()
+            .map(|(i, d)| (d, i))
+            .collect()
+    };
+    let (pos_a, pos_b) = (position(a), position(b));
+    let (val_a, val_b) = (init_a.values(a, 0), init_b.values(b, 0));
+    let mut pairs: Vec<(usize, usize)> = correspondence
+        .iter()
+        .filter(|(da, db)| match (init_a, init_b) {
+            (InitialState::Arbitrary, _) | (_, InitialState::Arbitrary) => false,
+            _ => val_a[pos_a[da]] == val_b[pos_b[db]],
+        })
+        .copied()
+        .collect();
+
+    // Inductive step: remove the pairs that are not preserved by a transition
+    loop {
+        let (ta, tb) = transition_networks(a, b, &pairs, false);
+        match check_transition(&ta, &tb, constraint, optimize) {
+            Ok(()) => break,
+            Err(pattern) => {
+                let next_a = simulate_comb(&ta, &pattern);
+                let next_b = simulate_comb(&tb, &pattern);
+                let nb_before = pairs.len();
+                pairs = zip(pairs, zip(next_a, next_b))
+                    .filter(|(_, (va, vb))| va == vb)
+                    .map(|(p, _)| p)
+                    .collect();
+                assert!(pairs.len() < nb_before);
+            }
+        }
+    }
+
+    // Final check on the outputs
+    let (ta, tb) = transition_networks(a, b, &pairs, true);
+    check_transition(&ta, &tb, constraint, optimize)
+        .ok()
+        .map(|_| pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_equivalence_correspondence, match_registers_by_name, match_registers_by_order,
+        match_registers_by_simulation,
+    };
+    use crate::equiv::{check_equivalence_bounded_with_init, InitialState};
+    use crate::{Gate, Network, Signal};
+
+    /// Two-bit counter, with the flip-flops in a given order
+    fn counter(reversed: bool) -> Network {
+        let mut aig = Network::new();
+        let inc = aig.add_input();
+        let x = aig.dff(Signal::placeholder(), Signal::one(), Signal::zero());
+        let y = aig.dff(Signal::placeholder(), Signal::one(), Signal::zero());
+        let (q0, q1) = if reversed { (y, x) } else { (x, y) };
+        let n0 = aig.xor(q0, inc);
+        let c = aig.and(q0, inc);
+        let n1 = if reversed {
+            aig.add(Gate::mux(c, !q1, q1))
+        } else {
+            aig.xor(q1, c)
+        };
+        aig.replace(
+            q0.var() as usize,
+            Gate::dff(n0, Signal::one(), Signal::zero()),
+        );
+        aig.replace(
+            q1.var() as usize,
+            Gate::dff(n1, Signal::one(), Signal::zero()),
+        );
+        aig.add_output(q1);
+        aig.check();
+        aig
+    }
+
+    #[test]
+    fn test_correspondence_order() {
+        let a = counter(false);
+        let b = counter(false);
+        let init = InitialState::Zero;
+        let corr = match_registers_by_order(&a, &b);
+        assert_eq!(corr, vec![(0, 0), (1, 1)]);
+        let res = check_equivalence_correspondence(&a, &b, &init, &init, &corr, None, false);
+        assert_eq!(res, Some(corr));
+    }
+
+    #[test]
+    fn test_correspondence_simulation() {
+        let a = counter(false);
+        let b = counter(true);
+        let init = InitialState::Zero;
+        let corr = match_registers_by_order(&a, &b);
+        let res = check_equivalence_correspondence(&a, &b, &init, &init, &corr, None, true);
+        assert_eq!(res, None);
+        let corr = match_registers_by_simulation(&a, &b, &init, &init, 8, 1);
+        assert_eq!(corr, vec![(0, 1), (1, 0)]);
+        let res = check_equivalence_correspondence(&a, &b, &init, &init, &corr, None, true);
+        assert_eq!(res, Some(corr));
+    }
+
+    #[test]
+    fn test_correspondence_name() {
+        let a = counter(false);
+        let b = counter(true);
+        let init = InitialState::Zero;
+        let names_a = vec!["q0".to_string(), "q1".to_string()];
+        let names_b = vec!["q1".to_string(), "q0".to_string()];
+        let corr = match_registers_by_name(&a, &names_a, &b, &names_b);
+        assert_eq!(corr, vec![(0, 1), (1, 0)]);
+        let res = check_equivalence_correspondence(&a, &b, &init, &init, &corr, None, true);
+        assert_eq!(res, Some(corr));
+        let names_b = vec!["q1".to_string(), "r".to_string()];
+        let corr = match_registers_by_name(&a, &names_a, &b, &names_b);
+        assert_eq!(corr, vec![(1, 0)]);
+    }
+
+    #[test]
+    fn test_correspondence_arbitrary_init() {
+        let a = counter(false);
+        let b = counter(true);
+        let init = InitialState::Arbitrary;
+        let corr = vec![(0, 1), (1, 0)];
+        // The two arbitrary initial states are unrelated, in both modes
+        let res = check_equivalence_correspondence(&a, &b, &init, &init, &corr, None, false);
+        assert_eq!(res, None);
+        assert!(check_equivalence_bounded_with_init(&a, &b, 2, &init, &init, None, false).is_err());
+    }
+
+    #[test]
+    fn test_correspondence_constraint() {
+        // Counter vs a network that ignores the increment, equivalent when the increment is tied to zero
+        let a = counter(false);
+        let mut b = Network::new();
+        b.add_input();
+        let x = b.dff(Signal::placeholder(), Signal::one(), Signal::zero());
+        let y = b.dff(Signal::placeholder(), Signal::one(), Signal::zero());
+        b.replace(
+            x.var() as usize,
+            Gate::dff(x, Signal::one(), Signal::zero()),
+        );
+        b.replace(
+            y.var() as usize,
+            Gate::dff(y, Signal::one(), Signal::zero()),
+        );
+        b.add_output(y);
+        let mut c = Network::new();
+        let inc = c.add_input();
+        c.add_output(!inc);
+        let init = InitialState::Zero;
+        let corr = match_registers_by_order(&a, &b);
+        let res = check_equivalence_correspondence(&a, &b, &init, &init, &corr, None, false);
+        assert_eq!(res, None);
+        let res = check_equivalence_correspondence(&a, &b, &init, &init, &corr, Some(&c), false);
+        assert_eq!(res, Some(corr));
+    }
+
+    #[test]
+    fn test_correspondence_different_init() {
+        let a = counter(false);
+        let b = counter(true);
+        let init_a = InitialState::Fixed(vec![true, false]);
+        let init_b = InitialState::Zero;
+        let corr = vec![(0, 1), (1, 0)];
+        let res = check_equivalence_correspondence(&a, &b, &init_a, &init_b, &corr, None, false);
+        assert_eq!(res, None);
+        let cex = check_equivalence_bounded_with_init(&a, &b, 2, &init_a, &init_b, None, false)
+            .unwrap_err();
+        assert_eq!(cex.inputs.len(), 2);
+        assert_eq!(cex.init_a, vec![true, false]);
+        assert_eq!(cex.init_b, vec![false, false]);
+    }
+}
@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::sim::{simulate_comb, simulate_from, simulate_multi_from};
+use crate::{Network, Signal};
+
+use super::{bounded_difference, prove, InitialState, SequentialCounterexample};
+
+/// Internal signal whose value diverges from all similar signals of the other network
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DivergentSignal {
+    /// Cycle where the divergence happens
+    pub cycle: usize,
+    /// Gate whose output diverges
+    pub gate: usize,
+    /// Value of the gate in the counterexample
+    pub value: bool,
+}
+
+/// Explanation of a counterexample to equivalence
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CounterexampleReport {
+    /// Values of the inputs at each cycle, None if the value does not matter
+    pub inputs: Vec<Vec<Option<bool>>>,
+    /// Initial values of the flip-flops of the first network
+    pub init_a: Vec<bool>,
+    /// Initial values of the flip-flops of the second network
+    pub init_b: Vec<bool>,
+    /// Outputs that differ, given as (cycle, output)
+    pub differing_outputs: Vec<(usize, usize)>,
+    /// First internal signals of the first network that diverge from the second network
+    pub divergent_a: Vec<DivergentSignal>,
+    /// First internal signals of the second network that diverge from the first network
+    pub divergent_b: Vec<DivergentSignal>,
+}
+
+/// Check whether all completions of a partial assignment set the single output of the network to 1
+fn is_always_true(aig: &Network, assignment: &[Option<bool>]) -> bool {
+    let t = |s: &Signal| -> Signal {
+        if s.is_input() {
+            match assignment[s.input() as usize] {
+                Some(b) => Signal::from(b) ^ s.is_inverted(),
+                None => *s,
+            }
+        } else {
+            *s
+        }
+    };
+    let mut fixed = Network::new();
+    fixed.add_inputs(aig.nb_inputs());
+    for i in 0..aig.nb_nodes() {
+        fixed.add(aig.gate(i).remap(t));
+    }
+    fixed.add_output(!t(&aig.output(0)));
+    fixed.make_canonical();
+    fixed.cleanup();
+    prove(&fixed).is_none()
+}
+
+/// Minimize the number of inputs specified in a counterexample to a difference network
+///
+/// Inputs are removed greedily, as long as all completions of the remaining assignment still
+/// show a difference. Only the first `nb_free` inputs may be removed.
+fn minimize_cube(diff: &Network, assignment: &[bool], nb_free: usize) -> Vec<Option<bool>> {
+    let mut cube: Vec<Option<bool>> = assignment.iter().map(|b| Some(*b)).collect();
+    for i in 0..nb_free {
+        // Quick check by simulation: the opposite value must show a difference too
+        let mut flipped = assignment.to_vec();
+        flipped[i] = !flipped[i];
+        if !simulate_comb(diff, &flipped)[0] {
+            continue;
+        }
+        cube[i] = None;
+        if !is_always_true(diff, &cube) {
+            cube[i] = Some(assignment[i]);
+        }
+    }
+    cube
+}
+
+/// Simulate the network and return the values of all nodes at each cycle
+fn node_values(aig: &Network, init: &[bool], inputs: &Vec<Vec<u64>>) -> Vec<Vec<u64>> {
+    let mut observed = aig.clone();
+    for i in 0..aig.nb_nodes() {
+        observed.add_output(aig.node(i));
+    }
+    let init: Vec<u64> = init.iter().map(|b| if *b { !0 } else { 0 }).collect();
+    simulate_multi_from(&observed, &init, inputs)
+        .into_iter()
+        .map(|v| v[aig.nb_outputs()..].to_vec())
+        .collect()
+}
+
+/// Signature of a value for a single cycle: random simulation in all bits but the first one, which holds the counterexample
+///
+/// Returns the signature normalized to ignore inversion, and the normalized counterexample value.
+fn normalized_signature(value: u64) -> (u64, bool) {
+    let inv = (value >> 1) & 1 != 0;
+    let v = if inv { !value } else { value };
+    (v & !1, v & 1 != 0)
+}
+
+/// Find the first signals of a network that diverge from all similar signals of the reference network
+///
+/// Similar signals are signals that take the same values (up to inversion) under random simulation.
+fn find_divergent(
+    aig: &Network,
+    values: &[Vec<u64>],
+    ref_values: &[Vec<u64>],
+) -> Vec<DivergentSignal> {
+    for (cycle, (cycle_values, cycle_ref)) in values.iter().zip(ref_values).enumerate() {
+        // For each signature of the reference network, which counterexample values are observed
+        let mut observed = HashMap::<u64, [bool; 2]>::new();
+        for v in cycle_ref {
+            let (sig, val) = normalized_signature(*v);
+            observed.entry(sig).or_default()[val as usize] = true;
+        }
+        let is_divergent: Vec<bool> = cycle_values
+            .iter()
+            .map(|v| {
+                let (sig, val) = normalized_signature(*v);
+                observed.get(&sig).is_some_and(|o| !o[val as usize])
+            })
+            .collect();
+        if !is_divergent.contains(&true) {
+            continue;
+        }
+        // Only keep the divergent signals whose combinatorial inputs are not divergent themselves
+        let mut ret = Vec::new();
+        for (gate, divergent) in is_divergent.iter().enumerate() {
+            let g = aig.gate(gate);
+            if *divergent && !(g.is_comb() && g.vars().any(|v| is_divergent[v as usize])) {
+                ret.push(DivergentSignal {
+                    cycle,
+                    gate,
+                    value: cycle_values[gate] & 1 != 0,
+                });
+            }
+        }
+        return ret;
+    }
+    Vec::new()
+}
+
+/// Analyze a counterexample to equivalence between two networks
+///
+/// If required, the counterexample is minimized using a SAT solver, so that unspecified inputs
+/// can take any value. The report gives the outputs that differ, and the first internal signals
+/// of each network that diverge from the other network.
+/// The constraint network, if any, is the one used to obtain the counterexample.
+pub fn explain_counterexample(
+    a: &Network,
+    b: &Network,
+    init_a: &InitialState,
+    init_b: &InitialState,
+    constraint: Option<&Network>,
+    counterexample: &SequentialCounterexample,
+    minimize: bool,
+) -> CounterexampleReport {
+    let nb_steps = counterexample.inputs.len();
+    let nb_step_inputs = a.nb_inputs() * nb_steps;
+
+    // Minimize the counterexample on the miter, with initial values kept as is
+    let inputs = if minimize {
+        let diff = bounded_difference(a, b, nb_steps, init_a, init_b, constraint);
+        let mut assignment: Vec<bool> = counterexample.inputs.concat();
+        if *init_a == InitialState::Arbitrary {
+            assignment.extend(&counterexample.init_a);
+        }
+        if *init_b == InitialState::Arbitrary {
+            assignment.extend(&counterexample.init_b);
+        }
+        let cube = minimize_cube(&diff, &assignment, nb_step_inputs);
+        cube[..nb_step_inputs]
+            .chunks(a.nb_inputs().max(1))
+            .take(nb_steps)
+            .map(|c| c.to_vec())
+            .collect()
+    } else {
+        counterexample
+            .inputs
+            .iter()
+            .map(|v| v.iter().map(|b| Some(*b)).collect())
+            .collect()
+    };
+
+    // Outputs that differ
+    let out_a = simulate_from(a, &counterexample.init_a, &counterexample.inputs);
+    let out_b = simulate_from(b, &counterexample.init_b, &counterexample.inputs);
+    let mut differing_outputs = Vec::new();
+    for (cycle, (va, vb)) in out_a.iter().zip(out_b.iter()).enumerate() {
+        for o in 0..va.len() {
+            if va[o] != vb[o] {
+                differing_outputs.push((cycle, o));
+            }
+        }
+    }
+
+    // Simulate the counterexample on the first bit and random patterns on the others
+    let mut rng = SmallRng::seed_from_u64(1);
+    let multi_inputs: Vec<Vec<u64>> = counterexample
+        .inputs
+        .iter()
+        .map(|v| {
+            v.iter()
+                .map(|b| (rng.gen::<u64>() & !1) | *b as u64)
+                .collect()
+        })
+        .collect();
+    let values_a = node_values(a, &counterexample.init_a, &multi_inputs);
+    let values_b = node_values(b, &counterexample.init_b, &multi_inputs);
+
+    CounterexampleReport {
+        inputs,
+        init_a: counterexample.init_a.clone(),
+        init_b: counterexample.init_b.clone(),
+        differing_outputs,
+        divergent_a: find_divergent(a, &values_a, &values_b),
+        divergent_b: find_divergent(b, &values_b, &values_a),
+    }
+}
+
+/// Ad-hoc string representation of a partial assignment
+fn cube_to_string(v: &[Option<bool>]) -> String {
+    v.iter()
+        .map(|b| match b {
+            Some(true) => '1',
+            Some(false) => '0',
+            None => '-',
+        })
+        .collect()
+}
+
+/// Ad-hoc string representation of an assignment
+fn values_to_string(v: &[bool]) -> String {
+    v.iter().map(|b| if *b { '1' } else { '0' }).collect()
+}
+
+impl CounterexampleReport {
+    /// Write the report in JSON format
+    pub fn write_json<W: Write>(&self, w: &mut W) {
+        let quoted = |v: Vec<String>| -> String {
+            v.iter()
+                .map(|s| format!("\"{}\"", s))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let divergent = |v: &Vec<DivergentSignal>| -> String {
+            v.iter()
+                .map(|d| {
+                    format!(
+                        "{{\"cycle\": {}, \"gate\": {}, \"value\": {}}}",
+                        d.cycle,
+                        d.gate,
+                        i32::from(d.value)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let inputs = self.inputs.iter().map(|v| cube_to_string(v)).collect();
+        let outputs = self
+            .differing_outputs
+            .iter()
+            .map(|(c, o)| format!("{{\"cycle\": {}, \"output\": {}}}", c, o))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(w, "{{").unwrap();
+        writeln!(w, "  \"inputs\": [{}],", quoted(inputs)).unwrap();
+        writeln!(w, "  \"init_a\": \"{}\",", values_to_string(&self.init_a)).unwrap();
+        writeln!(w, "  \"init_b\": \"{}\",", values_to_string(&self.init_b)).unwrap();
+        writeln!(w, "  \"differing_outputs\": [{}],", outputs).unwrap();
+        writeln!(w, "  \"divergent_a\": [{}],", divergent(&self.divergent_a)).unwrap();
+        writeln!(w, "  \"divergent_b\": [{}]", divergent(&self.divergent_b)).unwrap();
+        writeln!(w, "}}").unwrap();
+    }
+}
+
+impl fmt::Display for CounterexampleReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Test pattern:")?;
+        for v in &self.inputs {
+            writeln!(f, "\t{}", cube_to_string(v))?;
+        }
+        if !self.init_a.is_empty() || !self.init_b.is_empty() {
+            writeln!(f, "Initial state:")?;
+            writeln!(f, "\t{}", values_to_string(&self.init_a))?;
+            writeln!(f, "\t{}", values_to_string(&self.init_b))?;
+        }
+        writeln!(f, "Differing outputs:")?;
+        for (cycle, o) in &self.differing_outputs {
+            writeln!(f, "\tcycle {}: output {}", cycle, o)?;
+        }
+        for (name, divergent) in [("first", &self.divergent_a), ("second", &self.divergent_b)] {
+            if divergent.is_empty() {
+                continue;
+            }
+            writeln!(f, "First divergent signals in the {} network:", name)?;
+            for d in divergent {
+                writeln!(
+                    f,
+                    "\tcycle {}: {} = {}",
+                    d.cycle,
+                    Signal::from_var(d.gate as u32),
+                    i32::from(d.value)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::explain_counterexample;
+    use crate::equiv::{check_equivalence_bounded_with_init, InitialState};
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_minimize_and() {
+        let mut a = Network::new();
+        let i0 = a.add_input();
+        let i1 = a.add_input();
+        a.add_input();
+        let x = a.and(i0, i1);
+        a.add_output(x);
+        let mut b = Network::new();
+        b.add_inputs(3);
+        b.add_output(Signal::zero());
+
+        let init = InitialState::Zero;
+        let cex =
+            check_equivalence_bounded_with_init(&a, &b, 1, &init, &init, None, false).unwrap_err();
+        let report = explain_counterexample(&a, &b, &init, &init, None, &cex, true);
+        assert_eq!(report.inputs, vec![vec![Some(true), Some(true), None]]);
+        assert_eq!(report.differing_outputs, vec![(0, 0)]);
+    }
+
+    #[test]
+    fn test_minimize_seq() {
+        // Output is the input delayed by one cycle, or stuck at zero
+        let mut a = Network::new();
+        let i0 = a.add_input();
+        let d = a.dff(i0, Signal::one(), Signal::zero());
+        a.add_output(d);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_output(Signal::zero());
+
+        let init = InitialState::Zero;
+        let cex =
+            check_equivalence_bounded_with_init(&a, &b, 3, &init, &init, None, false).unwrap_err();
+        let report = explain_counterexample(&a, &b, &init, &init, None, &cex, true);
+        let nb_specified = report
+            .inputs
+            .concat()
+            .iter()
+            .filter(|b| b.is_some())
+            .count();
+        assert_eq!(nb_specified, 1);
+    }
+
+    #[test]
+    fn test_divergent() {
+        // Two large And trees that differ only when all inputs are set
+        let nb = 12;
+        let mut a = Network::new();
+        let mut b = Network::new();
+        let mut xa = Signal::one();
+        let mut xb = Signal::one();
+        for i in 0..nb {
+            let ia = a.add_input();
+            let ib = b.add_input();
+            xa = a.and(xa, ia);
+            xb = if i == nb - 1 {
+                b.add(Gate::and(xb, !ib))
+            } else {
+                b.and(xb, ib)
+            };
+        }
+        a.add_output(xa);
+        b.add_output(xb);
+
+        let init = InitialState::Zero;
+        let cex =
+            check_equivalence_bounded_with_init(&a, &b, 1, &init, &init, None, false).unwrap_err();
+        let report = explain_counterexample(&a, &b, &init, &init, None, &cex, true);
+        let mut expected = vec![Some(true); nb];
+        expected[nb - 1] = None;
+        assert_eq!(report.inputs, vec![expected]);
+        // Depending on the counterexample, the divergence is visible in either network
+        let divergent = [report.divergent_a, report.divergent_b].concat();
+        assert_eq!(divergent.len(), 1);
+        assert_eq!(divergent[0].gate, b.nb_nodes() - 1);
+    }
+}
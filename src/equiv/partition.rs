@@ -0,0 +1,288 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::{Network, Signal};
+
+use super::{difference, prove_until, unroll};
+
+/// Result of equivalence checking for a group of outputs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OutputStatus {
+    /// The outputs are equivalent
+    Equivalent,
+    /// The outputs are not equivalent, with a failing test pattern for each cycle
+    Different(Vec<Vec<bool>>),
+    /// The outputs were not fully checked, because a counterexample was found elsewhere
+    Skipped,
+}
+
+/// Result of equivalence checking for a group of outputs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputResult {
+    /// Outputs checked together
+    pub outputs: Vec<usize>,
+    /// Result for these outputs
+    pub status: OutputStatus,
+}
+
+/// Extract the logic cone of some outputs of a network
+fn output_cone(aig: &Network, outputs: &[usize]) -> Network {
+    let mut ret = Network::new();
+    ret.add_inputs(aig.nb_inputs());
+    for i in 0..aig.nb_nodes() {
+        ret.add(aig.gate(i).clone());
+    }
+    for o in outputs {
+        ret.add_output(aig.output(*o));
+    }
+    ret.cleanup();
+    ret
+}
+
+/// Group outputs whose logic cones share gates, up to a maximum group size
+///
+/// Outputs with a shared logic benefit from being checked together, while independent outputs
+/// can be checked in parallel.
+pub fn group_outputs_by_cone(aig: &Network, max_group_size: usize) -> Vec<Vec<usize>> {
+    let nb_outputs = aig.nb_outputs();
+    let mut parent: Vec<usize> = (0..nb_outputs).collect();
+    let mut size = vec![1; nb_outputs];
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    // Owner of each gate: the first output whose cone contains it
+    let mut owner: Vec<Option<usize>> = vec![None; aig.nb_nodes()];
+    for o in 0..nb_outputs {
+        let mut to_visit: Vec<Signal> = vec![aig.output(o)];
+        while let Some(s) = to_visit.pop() {
+            if !s.is_var() {
+                continue;
+            }
+            let v = s.var() as usize;
+            match owner[v] {
+                Some(other) if other == o => continue,
+                Some(other) => {
+                    let ro = find(&mut parent, o);
+                    let rother = find(&mut parent, other);
+                    if ro != rother && size[ro] + size[rother] <= max_group_size {
+                        parent[ro] = rother;
+                        size[rother] += size[ro];
+                    }
+                    continue;
+                }
+                None => {
+                    owner[v] = Some(o);
+                    to_visit.extend(aig.gate(v).dependencies().iter().copied());
+                }
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = vec![Vec::new(); nb_outputs];
+    for o in 0..nb_outputs {
+        let r = find(&mut parent, o);
+        groups[r].push(o);
+    }
+    groups.retain(|g| !g.is_empty());
+    groups
+}
+
+/// Group outputs individually
+pub fn group_outputs_individually(aig: &Network) -> Vec<Vec<usize>> {
+    (0..aig.nb_outputs()).map(|o| vec![o]).collect()
+}
+
+/// Perform bounded equivalence checking on two networks, with groups of outputs checked independently
+///
+/// Each group of outputs is checked on its own logic cone, and the groups are distributed over
+/// a pool of threads. All flip-flops are initialized to zero. If required, the first
+/// counterexample stops the check: the Sat solvers that are running are interrupted, and these
+/// groups are skipped as well as the groups that were not checked yet.
+/// A number of threads of 0 uses all available cores.
+pub fn check_equivalence_partitioned(
+    a: &Network,
+    b: &Network,
+    nb_steps: usize,
+    groups: &[Vec<usize>],
+    nb_threads: usize,
+    stop_at_first: bool,
+    optimize: bool,
+) -> Vec<OutputResult> {
+    assert_eq!(a.nb_inputs(), b.nb_inputs());
+    assert_eq!(a.nb_outputs(), b.nb_outputs());
+    let nb_threads = if nb_threads == 0 {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    } else {
+        nb_threads
+    };
+    let a_u = unroll(a, nb_steps);
+    let b_u = unroll(b, nb_steps);
+
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let results = Mutex::new(vec![OutputStatus::Skipped; groups.len()]);
+    thread::scope(|s| {
+        for _ in 0..nb_threads {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= groups.len() || stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                // Outputs of the group at every cycle
+                let outputs: Vec<usize> = (0..nb_steps)
+                    .flat_map(|step| groups[i].iter().map(move |o| step * a.nb_outputs() + o))
+                    .collect();
+                let cone_a = output_cone(&a_u, &outputs);
+                let cone_b = output_cone(&b_u, &outputs);
+                let mut diff = difference(&cone_a, &cone_b, None);
+                if optimize {
+                    diff.make_canonical();
+                    diff.cleanup();
+                }
+                let status = match prove_until(&diff, &stop) {
+                    None => OutputStatus::Skipped,
+                    Some(None) => OutputStatus::Equivalent,
+                    Some(Some(v)) => {
+                        if stop_at_first {
+                            stop.store(true, Ordering::Relaxed);
+                        }
+                        let pattern = v.chunks(a.nb_inputs().max(1)).map(|c| c.to_vec());
+                        OutputStatus::Different(pattern.take(nb_steps).collect())
+                    }
+                };
+                results.lock().unwrap()[i] = status;
+            });
+        }
+    });
+    groups
+        .iter()
+        .cloned()
+        .zip(results.into_inner().unwrap())
+        .map(|(outputs, status)| OutputResult { outputs, status })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_equivalence_partitioned, group_outputs_by_cone, group_outputs_individually,
+        OutputStatus,
+    };
+    use crate::{Network, Signal};
+
+    /// Network with three outputs: two sharing an And gate, and an independent Xor
+    fn three_outputs(faulty: bool) -> Network {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let x = aig.and(i0, i1);
+        let y = aig.and(x, i2);
+        let z = if faulty {
+            aig.and(i1, i2)
+        } else {
+            aig.xor(i1, i2)
+        };
+        aig.add_output(x);
+        aig.add_output(y);
+        aig.add_output(z);
+        aig
+    }
+
+    #[test]
+    fn test_grouping() {
+        let aig = three_outputs(false);
+        assert_eq!(group_outputs_by_cone(&aig, 4), vec![vec![0, 1], vec![2]]);
+        assert_eq!(
+            group_outputs_by_cone(&aig, 1),
+            group_outputs_individually(&aig)
+        );
+    }
+
+    #[test]
+    fn test_partitioned() {
+        let a = three_outputs(false);
+        let b = three_outputs(true);
+        let groups = group_outputs_individually(&a);
+        let res = check_equivalence_partitioned(&a, &b, 1, &groups, 2, false, true);
+        assert_eq!(res[0].status, OutputStatus::Equivalent);
+        assert_eq!(res[1].status, OutputStatus::Equivalent);
+        match &res[2].status {
+            OutputStatus::Different(p) => {
+                assert_eq!(p.len(), 1);
+                assert!(p[0][1] || p[0][2]);
+            }
+            _ => panic!("Output should be different"),
+        }
+    }
+
+    #[test]
+    fn test_partitioned_stop() {
+        let a = three_outputs(true);
+        let mut b = Network::new();
+        b.add_inputs(3);
+        for _ in 0..3 {
+            b.add_output(Signal::zero());
+        }
+        let groups = group_outputs_individually(&a);
+        let res = check_equivalence_partitioned(&a, &b, 2, &groups, 1, true, false);
+        assert!(matches!(res[0].status, OutputStatus::Different(_)));
+        assert_eq!(res[1].status, OutputStatus::Skipped);
+        assert_eq!(res[2].status, OutputStatus::Skipped);
+        let res = check_equivalence_partitioned(&a, &b, 2, &groups, 0, false, false);
+        assert!(res
+            .iter()
+            .all(|r| matches!(r.status, OutputStatus::Different(_))));
+    }
+
+    /// Add a multiplier to the network, returning the bits of the product
+    fn multiplier(aig: &mut Network, x: &[Signal], y: &[Signal]) -> Vec<Signal> {
+        let mut acc = vec![Signal::zero(); x.len() + y.len()];
+        for (i, yi) in y.iter().enumerate() {
+            let mut carry = Signal::zero();
+            for (j, xj) in x.iter().enumerate() {
+                let p = aig.and(*xj, *yi);
+                let t = aig.xor(acc[i + j], p);
+                let c1 = aig.and(acc[i + j], p);
+                let c2 = aig.and(t, carry);
+                acc[i + j] = aig.xor(t, carry);
+                carry = !aig.and(!c1, !c2);
+            }
+            acc[i + x.len()] = carry;
+        }
+        acc
+    }
+
+    #[test]
+    fn test_partitioned_interrupt() {
+        // Commutativity of a multiplier is very hard for a Sat solver, while the last output
+        // differs trivially: its counterexample interrupts the check of the multiplier
+        let build = |swap: bool| {
+            let mut aig = Network::new();
+            let x: Vec<Signal> = (0..16).map(|_| aig.add_input()).collect();
+            let y: Vec<Signal> = (0..16).map(|_| aig.add_input()).collect();
+            let product = if swap {
+                multiplier(&mut aig, &y, &x)
+            } else {
+                multiplier(&mut aig, &x, &y)
+            };
+            for p in product {
+                aig.add_output(p);
+            }
+            aig.add_output(if swap { x[0] } else { !x[0] });
+            aig
+        };
+        let a = build(false);
+        let b = build(true);
+        let groups = vec![(0..32).collect(), vec![32]];
+        let res = check_equivalence_partitioned(&a, &b, 1, &groups, 2, true, false);
+        assert_eq!(res[0].status, OutputStatus::Skipped);
+        assert!(matches!(res[1].status, OutputStatus::Different(_)));
+    }
+}
@@ -0,0 +1,168 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::sim::simulate_multi;
+use crate::Network;
+
+use super::{
+    bounded_difference, decode_counterexample, prove_until, InitialState, SequentialCounterexample,
+};
+
+/// Strategy used to solve an equivalence checking problem in a portfolio
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Sat solver on the difference network, after simplification
+    OptimizedSat,
+    /// Sat solver on the difference network as is
+    Sat,
+    /// Random simulation, that can only find counterexamples
+    RandomSimulation,
+}
+
+impl Strategy {
+    /// All available strategies
+    pub fn all() -> Vec<Strategy> {
+        vec![
+            Strategy::OptimizedSat,
+            Strategy::Sat,
+            Strategy::RandomSimulation,
+        ]
+    }
+
+    /// Find an assignment that sets the output of a difference network to 1, unless interrupted
+    fn run(self, diff: &Network, stop: &AtomicBool, seed: u64) -> Option<Result<(), Vec<bool>>> {
+        let res = match self {
+            Strategy::OptimizedSat => {
+                let mut diff = diff.clone();
+                diff.make_canonical();
+                diff.cleanup();
+                prove_until(&diff, stop)?
+            }
+            Strategy::Sat => prove_until(diff, stop)?,
+            Strategy::RandomSimulation => Some(random_counterexample(diff, stop, seed)?),
+        };
+        Some(match res {
+            None => Ok(()),
+            Some(v) => Err(v),
+        })
+    }
+}
+
+/// Search for an assignment that sets the output of a difference network to 1 by random simulation
+///
+/// Returns None when interrupted, as random simulation cannot prove that none exists.
+fn random_counterexample(diff: &Network, stop: &AtomicBool, seed: u64) -> Option<Vec<bool>> {
+    let mut rng = SmallRng::seed_from_u64(seed);
+    while !stop.load(Ordering::Relaxed) {
+        let inputs: Vec<u64> = (0..diff.nb_inputs()).map(|_| rng.gen()).collect();
+        let out = simulate_multi(diff, &vec![inputs.clone()])[0][0];
+        if out != 0 {
+            let b = out.trailing_zeros();
+            return Some(inputs.iter().map(|v| (v >> b) & 1 != 0).collect());
+        }
+    }
+    None
+}
+
+/// Find an assignment that sets the output of a difference network to 1, with a portfolio of strategies
+///
+/// Each strategy runs in its own thread, and the first one to reach a conclusion stops the others.
+/// Returns the result and the strategy that obtained it.
+fn check_difference_portfolio(
+    diff: &Network,
+    strategies: &[Strategy],
+    seed: u64,
+) -> (Result<(), Vec<bool>>, Strategy) {
+    assert!(
+        strategies.iter().any(|s| *s != Strategy::RandomSimulation),
+        "A portfolio needs a complete strategy"
+    );
+    let stop = AtomicBool::new(false);
+    let result = Mutex::new(None);
+    thread::scope(|s| {
+        for strategy in strategies {
+            s.spawn(|| {
+                if let Some(res) = strategy.run(diff, &stop, seed) {
+                    let mut result = result.lock().unwrap();
+                    if result.is_none() {
+                        *result = Some((res, *strategy));
+                    }
+                    stop.store(true, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+    result.into_inner().unwrap().unwrap()
+}
+
+/// Perform bounded equivalence checking on two sequential networks, with a portfolio of strategies
+///
+/// This is similar to [`check_equivalence_bounded_with_init`](super::check_equivalence_bounded_with_init),
+/// but runs several strategies in parallel on the same problem and keeps the first result.
+/// Returns the result and the strategy that obtained it.
+pub fn check_equivalence_bounded_portfolio(
+    a: &Network,
+    b: &Network,
+    nb_steps: usize,
+    init_a: &InitialState,
+    init_b: &InitialState,
+    constraint: Option<&Network>,
+    strategies: &[Strategy],
+) -> (Result<(), SequentialCounterexample>, Strategy) {
+    assert_eq!(a.nb_inputs(), b.nb_inputs());
+    assert_eq!(a.nb_outputs(), b.nb_outputs());
+
+    let diff = bounded_difference(a, b, nb_steps, init_a, init_b, constraint);
+    let (res, strategy) = check_difference_portfolio(&diff, strategies, 1);
+    (
+        res.map_err(|v| decode_counterexample(a, b, nb_steps, init_a, init_b, &v)),
+        strategy,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_equivalence_bounded_portfolio, Strategy};
+    use crate::equiv::InitialState;
+    use crate::{Network, Signal};
+
+    /// Delayed And gate, or a delayed Or gate if required
+    fn delayed_gate(or: bool) -> Network {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let x = if or {
+            !aig.and(!i0, !i1)
+        } else {
+            aig.and(i0, i1)
+        };
+        let d = aig.dff(x, Signal::one(), Signal::zero());
+        aig.add_output(d);
+        aig
+    }
+
+    #[test]
+    fn test_portfolio() {
+        let a = delayed_gate(false);
+        let b = delayed_gate(true);
+        let init = InitialState::Zero;
+        for strategies in [
+            Strategy::all(),
+            vec![Strategy::Sat],
+            vec![Strategy::OptimizedSat],
+        ] {
+            let (res, _) =
+                check_equivalence_bounded_portfolio(&a, &a, 3, &init, &init, None, &strategies);
+            assert_eq!(res, Ok(()));
+            let (res, _) =
+                check_equivalence_bounded_portfolio(&a, &b, 3, &init, &init, None, &strategies);
+            let cex = res.unwrap_err();
+            assert_eq!(cex.inputs.len(), 3);
+            assert!(cex.inputs[0][0] != cex.inputs[0][1] || cex.inputs[1][0] != cex.inputs[1][1]);
+        }
+    }
+}
@@ -0,0 +1,498 @@
+use std::collections::HashMap;
+
+use crate::sim::simulate_comb;
+use crate::{Network, Signal};
+
+use super::cdcl::Solver;
+use super::to_cnf;
+
+/// Number of clauses above which the internal solver is expected to be very slow
+const LARGE_FORMULA_CLAUSES: usize = 100_000;
+
+/// Step of a DRAT proof, with literals in DIMACS format
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProofStep {
+    /// Addition of a lemma, implied by the current formula
+    Add(Vec<i32>),
+    /// Deletion of a clause from the current formula
+    Delete(Vec<i32>),
+}
+
+/// Certificate that a network can never set its output to 1
+///
+/// It is made of the CNF formula encoding the network and its output, and of a DRAT proof that
+/// the formula is unsatisfiable. Both can be written to files and checked independently.
+#[derive(Clone, Debug)]
+pub struct Certificate {
+    /// Number of variables of the formula
+    pub nb_vars: usize,
+    /// Clauses of the formula, with literals in DIMACS format
+    pub cnf: Vec<Vec<i32>>,
+    /// DRAT proof of unsatisfiability
+    pub proof: Vec<ProofStep>,
+}
+
+/// Export a combinatorial network with a single output to a CNF formula in DIMACS format
+///
+/// The formula is satisfiable if and only if the output can be set to 1. Returns the number of
+/// variables, the clauses and the variable associated with each input.
+pub fn to_dimacs(a: &Network) -> (usize, Vec<Vec<i32>>, Vec<i32>) {
+    assert_eq!(a.nb_outputs(), 1);
+    let clauses = to_cnf(a);
+
+    let mut all_vars: Vec<Signal> = clauses
+        .iter()
+        .flatten()
+        .map(|s| s.without_inversion())
+        .collect();
+    for i in 0..a.nb_inputs() {
+        all_vars.push(Signal::from_input(i as u32));
+    }
+    let out = a.output(0);
+    if !out.is_constant() {
+        all_vars.push(out.without_inversion());
+    }
+    all_vars.sort();
+    all_vars.dedup();
+    let mut t = HashMap::new();
+    for (i, s) in all_vars.iter().enumerate() {
+        t.insert(*s, i as i32 + 1);
+        t.insert(!*s, -(i as i32 + 1));
+    }
+
+    let mut cnf: Vec<Vec<i32>> = clauses
+        .iter()
+        .map(|c| c.iter().map(|s| t[s]).collect())
+        .collect();
+    if out == Signal::zero() {
+        cnf.push(Vec::new());
+    } else if out != Signal::one() {
+        cnf.push(vec![t[&out]]);
+    }
+    let inputs = (0..a.nb_inputs())
+        .map(|i| t[&Signal::from_input(i as u32)])
+        .collect();
+    (all_vars.len(), cnf, inputs)
+}
+
+/// Find an assignment of the inputs that sets the single output to 1, or a certificate that none exists
+///
+/// This re-solves the problem with an internal solver that logs its proof, independently from
+/// [`prove`](super::prove): the certificate is for this run, not for a result given by Kissat.
+/// The internal solver is much slower, and a warning is shown for large formulas.
+/// The assignment is checked by simulation before being returned.
+pub fn resolve_with_certificate(a: &Network) -> Result<Certificate, Vec<bool>> {
+    let (nb_vars, cnf, inputs) = to_dimacs(a);
+    if cnf.len() > LARGE_FORMULA_CLAUSES {
+        println!(
+            "Warning: re-solving a formula with {} clauses with the internal solver, which may be very slow",
+            cnf.len()
+        );
+    }
+    let mut solver = Solver::new(nb_vars);
+    for c in &cnf {
+        solver.add_clause(c);
+    }
+    match solver.solve() {
+        Some(model) => {
+            let pattern: Vec<bool> = inputs.iter().map(|v| model[(v - 1) as usize]).collect();
+            assert!(
+                simulate_comb(a, &pattern)[0],
+                "Solver returned an assignment that does not set the output"
+            );
+            Err(pattern)
+        }
+        None => Ok(Certificate {
+            nb_vars,
+            cnf,
+            proof: solver.proof(),
+        }),
+    }
+}
+
+/// Forward DRAT checker, based on unit propagation with two watched literals
+struct Checker {
+    clauses: Vec<Vec<i32>>,
+    active: Vec<bool>,
+    watches: HashMap<i32, Vec<usize>>,
+    units: Vec<usize>,
+    index: HashMap<Vec<i32>, Vec<usize>>,
+    value: Vec<Option<bool>>,
+    trail: Vec<i32>,
+    has_empty: bool,
+}
+
+impl Checker {
+    fn new(nb_vars: usize) -> Checker {
+        Checker {
+            clauses: Vec::new(),
+            active: Vec::new(),
+            watches: HashMap::new(),
+            units: Vec::new(),
+            index: HashMap::new(),
+            value: vec![None; nb_vars + 1],
+            trail: Vec::new(),
+            has_empty: false,
+        }
+    }
+
+    fn normalize(clause: &[i32]) -> Vec<i32> {
+        let mut c = clause.to_vec();
+        c.sort();
+        c.dedup();
+        c
+    }
+
+    fn lit_value(&self, l: i32) -> Option<bool> {
+        self.value[l.unsigned_abs() as usize].map(|b| b ^ (l < 0))
+    }
+
+    fn check_var(&self, clause: &[i32]) -> Result<(), String> {
+        for l in clause {
+            if l.unsigned_abs() as usize >= self.value.len() {
+                return Err(format!("Literal {} is out of range", l));
+            }
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, clause: &[i32]) {
+        let c = Checker::normalize(clause);
+        let id = self.clauses.len();
+        match c.len() {
+            0 => self.has_empty = true,
+            1 => self.units.push(id),
+            _ => {
+                self.watches.entry(c[0]).or_default().push(id);
+                self.watches.entry(c[1]).or_default().push(id);
+            }
+        }
+        self.index.entry(c.clone()).or_default().push(id);
+        self.clauses.push(c);
+        self.active.push(true);
+    }
+
+    fn delete(&mut self, clause: &[i32]) -> Result<(), String> {
+        let c = Checker::normalize(clause);
+        let id = self
+            .index
+            .get_mut(&c)
+            .and_then(|v| v.pop())
+            .ok_or(format!("Deleted clause {:?} is not in the formula", clause))?;
+        self.active[id] = false;
+        self.units.retain(|u| *u != id);
+        if c.is_empty() {
+            self.has_empty = self.index.get(&c).is_some_and(|v| !v.is_empty());
+        }
+        Ok(())
+    }
+
+    /// Assign a literal, returning false in case of conflict
+    fn assign(&mut self, l: i32) -> bool {
+        match self.lit_value(l) {
+            Some(b) => b,
+            None => {
+                self.value[l.unsigned_abs() as usize] = Some(l > 0);
+                self.trail.push(l);
+                true
+            }
+        }
+    }
+
+    /// Propagate the current assignment, returning false in case of conflict
+    fn propagate(&mut self) -> bool {
+        let mut qhead = 0;
+        while qhead < self.trail.len() {
+            let false_lit = -self.trail[qhead];
+            qhead += 1;
+            let Some(mut watchers) = self.watches.remove(&false_lit) else {
+                continue;
+            };
+            let mut i = 0;
+            let mut ok = true;
+            while i < watchers.len() {
+                let id = watchers[i];
+                if !self.active[id] {
+                    watchers.swap_remove(i);
+                    continue;
+                }
+                if self.clauses[id][0] == false_lit {
+                    self.clauses[id].swap(0, 1);
+                }
+                let first = self.clauses[id][0];
+                if self.lit_value(first) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+                let new_watch = (2..self.clauses[id].len())
+                    .find(|k| self.lit_value(self.clauses[id][*k]) != Some(false));
+                if let Some(k) = new_watch {
+                    self.clauses[id].swap(1, k);
+                    let w = self.clauses[id][1];
+                    self.watches.entry(w).or_default().push(id);
+                    watchers.swap_remove(i);
+                    continue;
+                }
+                i += 1;
+                if !self.assign(first) {
+                    ok = false;
+                    break;
+                }
+            }
+            self.watches.entry(false_lit).or_default().extend(watchers);
+            if !ok {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Check whether a clause is implied by unit propagation (reverse unit propagation)
+    fn is_rup(&mut self, clause: &[i32]) -> bool {
+        let mut conflict = self.has_empty;
+        for l in clause {
+            if !conflict && !self.assign(-l) {
+                conflict = true;
+            }
+        }
+        for i in 0..self.units.len() {
+            let l = self.clauses[self.units[i]][0];
+            if !conflict && !self.assign(l) {
+                conflict = true;
+            }
+        }
+        if !conflict {
+            conflict = !self.propagate();
+        }
+        for l in self.trail.drain(..) {
+            self.value[l.unsigned_abs() as usize] = None;
+        }
+        conflict
+    }
+
+    /// Check whether a clause has the resolution asymmetric tautology property on its first literal
+    fn is_rat(&mut self, clause: &[i32]) -> bool {
+        let Some(&pivot) = clause.first() else {
+            return false;
+        };
+        let candidates: Vec<usize> = (0..self.clauses.len())
+            .filter(|i| self.active[*i] && self.clauses[*i].contains(&-pivot))
+            .collect();
+        for i in candidates {
+            let mut resolvent = clause.to_vec();
+            resolvent.extend(self.clauses[i].iter().filter(|l| **l != -pivot));
+            if !self.is_rup(&resolvent) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Check a DRAT proof of unsatisfiability for a CNF formula
+///
+/// Each lemma must be implied by reverse unit propagation, or have the resolution asymmetric
+/// tautology property on its first literal. The proof must derive the empty clause, or
+/// lead to a conflict by unit propagation alone.
+pub fn check_drat(nb_vars: usize, cnf: &[Vec<i32>], proof: &[ProofStep]) -> Result<(), String> {
+    let mut checker = Checker::new(nb_vars);
+    for c in cnf {
+        checker.check_var(c)?;
+        checker.add(c);
+    }
+    for (i, step) in proof.iter().enumerate() {
+        match step {
+            ProofStep::Add(c) => {
+                checker.check_var(c)?;
+                if !checker.is_rup(c) && !checker.is_rat(c) {
+                    return Err(format!(
+                        "Lemma {} at step {} is not implied",
+                        fmt_clause(c),
+                        i + 1
+                    ));
+                }
+                if c.is_empty() {
+                    return Ok(());
+                }
+                checker.add(c);
+            }
+            ProofStep::Delete(c) => checker.delete(c)?,
+        }
+    }
+    if checker.is_rup(&[]) {
+        Ok(())
+    } else {
+        Err("The proof does not derive the empty clause".to_owned())
+    }
+}
+
+fn fmt_clause(c: &[i32]) -> String {
+    let mut s: String = c.iter().map(|l| format!("{} ", l)).collect();
+    s.push('0');
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::{check_drat, resolve_with_certificate, Checker, ProofStep};
+    use crate::equiv::{difference, prove};
+    use crate::network::generators::adder;
+    use crate::optim::share_logic;
+    use crate::{Network, Signal};
+
+    /// Random network of And and Xor gates, with one of the gates inverted if required
+    fn random_network(seed: u64, inverted_gate: Option<usize>) -> Network {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let mut aig = Network::new();
+        let mut signals: Vec<Signal> = (0..10).map(|_| aig.add_input()).collect();
+        for i in 0..120 {
+            let a = signals[rng.gen_range(0..signals.len())];
+            let b = signals[rng.gen_range(0..signals.len())];
+            let x = if rng.gen_range(0..4) == 0 {
+                aig.xor(a, b)
+            } else {
+                aig.and(a ^ rng.gen::<bool>(), b ^ rng.gen::<bool>())
+            };
+            signals.push(x ^ (inverted_gate == Some(i)));
+        }
+        // Gates without fanout are outputs
+        let mut used = vec![false; aig.nb_nodes()];
+        for i in 0..aig.nb_nodes() {
+            for v in aig.gate(i).vars() {
+                used[v as usize] = true;
+            }
+        }
+        for (i, u) in used.iter().enumerate() {
+            if !u {
+                aig.add_output(Signal::from_var(i as u32));
+            }
+        }
+        aig
+    }
+
+    #[test]
+    fn test_check_drat() {
+        let cnf = vec![vec![1, 2], vec![-1, 2], vec![1, -2], vec![-1, -2]];
+        let proof = vec![ProofStep::Add(vec![1]), ProofStep::Add(vec![])];
+        check_drat(2, &cnf, &proof).unwrap();
+        // Missing lemma: the empty clause is not implied by unit propagation
+        let proof = vec![ProofStep::Add(vec![])];
+        assert!(check_drat(2, &cnf, &proof).is_err());
+        // Deleting the clauses makes the proof invalid
+        let proof = vec![
+            ProofStep::Delete(vec![-1, 2]),
+            ProofStep::Add(vec![1]),
+            ProofStep::Add(vec![]),
+        ];
+        assert!(check_drat(2, &cnf, &proof).is_err());
+    }
+
+    #[test]
+    fn test_check_rat() {
+        // Definition of a fresh variable is RAT, but not RUP
+        let mut checker = Checker::new(3);
+        checker.add(&[1, 2]);
+        checker.add(&[-1, -2]);
+        assert!(!checker.is_rup(&[-3, 1]));
+        assert!(checker.is_rat(&[-3, 1]));
+        assert!(!checker.is_rup(&[1]));
+        assert!(!checker.is_rat(&[1]));
+    }
+
+    #[test]
+    fn test_check_invalid_proofs() {
+        // Pigeonhole with 2 holes and 3 pigeons: variable 2 * p + h + 1 for pigeon p in hole h
+        let mut pigeonhole: Vec<Vec<i32>> = (0..3).map(|p| vec![2 * p + 1, 2 * p + 2]).collect();
+        for h in 1..=2 {
+            for p in 0..3 {
+                for q in p + 1..3 {
+                    pigeonhole.push(vec![-(2 * p + h), -(2 * q + h)]);
+                }
+            }
+        }
+        let valid = vec![
+            ProofStep::Add(vec![-1, -4]),
+            ProofStep::Add(vec![-1]),
+            ProofStep::Add(vec![]),
+        ];
+        check_drat(6, &pigeonhole, &valid).unwrap();
+
+        // Lemma that is neither RUP nor RAT
+        let cnf = vec![vec![1, 2], vec![-1, -2]];
+        let err = check_drat(3, &cnf, &[ProofStep::Add(vec![1])]).unwrap_err();
+        assert!(err.contains("not implied"), "{}", err);
+
+        // Lemma that is RAT on its first literal only: the pivot must come first
+        let err = check_drat(3, &cnf, &[ProofStep::Add(vec![-3, 1])]).unwrap_err();
+        assert!(!err.contains("not implied"), "{}", err);
+        let err = check_drat(3, &cnf, &[ProofStep::Add(vec![1, -3])]).unwrap_err();
+        assert!(err.contains("not implied"), "{}", err);
+
+        // Deleting a clause needed by a later lemma
+        let mut invalid = vec![ProofStep::Delete(vec![-1, -3])];
+        invalid.extend(valid);
+        assert!(check_drat(6, &pigeonhole, &invalid).is_err());
+    }
+
+    #[test]
+    fn test_certificate_equiv() {
+        // Ripple-carry adder, with majority and xor gates decomposed into 2-input gates
+        let a = adder::ripple_carry(4);
+        let mut b = Network::new();
+        let mut c = Signal::zero();
+        for _ in 0..4 {
+            let x = b.add_input();
+            let y = b.add_input();
+            let p = b.xor(x, y);
+            let g = b.and(x, y);
+            let o = b.xor(p, c);
+            let pc = b.and(p, c);
+            b.add_output(o);
+            c = !b.and(!g, !pc);
+        }
+        b.add_output(c);
+        let diff = difference(&a, &b, None);
+        let cert = resolve_with_certificate(&diff).unwrap();
+        check_drat(cert.nb_vars, &cert.cnf, &cert.proof).unwrap();
+        assert!(check_drat(cert.nb_vars, &cert.cnf, &[]).is_err());
+    }
+
+    #[test]
+    fn test_certificate_not_equiv() {
+        let mut a = Network::new();
+        let i0 = a.add_input();
+        let i1 = a.add_input();
+        let x = a.and(i0, !i1);
+        a.add_output(x);
+        assert_eq!(resolve_with_certificate(&a).unwrap_err(), vec![true, false]);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_output(Signal::zero());
+        let cert = resolve_with_certificate(&b).unwrap();
+        check_drat(cert.nb_vars, &cert.cnf, &cert.proof).unwrap();
+    }
+
+    #[test]
+    fn test_certificate_matches_kissat() {
+        // The internal solver must agree with Kissat on miters of restructured networks
+        for seed in 0..20 {
+            let a = random_network(seed, None);
+            let mut b = random_network(seed, (seed % 2 == 1).then_some(60 + seed as usize));
+            share_logic(&mut b, 64);
+            let diff = difference(&a, &b, None);
+            match (resolve_with_certificate(&diff), prove(&diff)) {
+                (Ok(cert), None) => check_drat(cert.nb_vars, &cert.cnf, &cert.proof).unwrap(),
+                (Err(_), Some(_)) => (),
+                (cert, kissat) => panic!(
+                    "Solvers disagree on seed {}: certificate {}, Kissat {}",
+                    seed,
+                    if cert.is_ok() { "unsat" } else { "sat" },
+                    if kissat.is_none() { "unsat" } else { "sat" }
+                ),
+            }
+        }
+    }
+}
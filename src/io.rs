@@ -2,6 +2,7 @@
 
 mod bench;
 mod blif;
+mod dimacs;
 mod dot;
 mod patterns;
 mod utils;
@@ -9,9 +10,10 @@ mod utils;
 use std::fs::File;
 use std::path::PathBuf;
 
-pub use bench::{read_bench, write_bench};
-pub use blif::{read_blif, write_blif};
-pub use dot::write_dot;
+pub use bench::{read_bench, read_bench_dff_names, write_bench};
+pub use blif::{read_blif, read_blif_dff_names, write_blif};
+pub use dimacs::{read_dimacs, read_drat, write_dimacs, write_drat};
+pub use dot::{write_dot, write_dot_annotated};
 pub use patterns::{read_patterns, write_patterns};
 
 use crate::Network;
@@ -36,6 +38,26 @@ pub fn read_network_file(path: &PathBuf) -> Network {
     }
 }
 
+/// Read the names of the flip-flops of a logic network from a file
+///
+/// The names are given in the order of the flip-flops in the network returned by [`read_network_file`]
+pub fn read_dff_names_file(path: &PathBuf) -> Vec<String> {
+    let ext = path.extension();
+    let f = File::open(path).unwrap();
+    match ext {
+        None => panic!("No extension given"),
+        Some(s) => {
+            if s == "bench" {
+                read_bench_dff_names(f).unwrap()
+            } else if s == "blif" {
+                read_blif_dff_names(f).unwrap()
+            } else {
+                panic!("Unknown extension {}", s.to_string_lossy());
+            }
+        }
+    }
+}
+
 /// Write a logic network to a file
 ///
 /// .bench and .blif formats are supported
@@ -83,3 +105,9 @@ pub fn write_dot_file(path: &PathBuf, aig: &Network) {
     let mut f = File::create(path).unwrap();
     write_dot(&mut f, aig);
 }
+
+/// Write a logic network to a DOT graph file, with an annotation for each node
+pub fn write_annotated_dot_file(path: &PathBuf, aig: &Network, annotations: &[String]) {
+    let mut f = File::create(path).unwrap();
+    write_dot_annotated(&mut f, aig, annotations);
+}
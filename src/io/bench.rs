@@ -169,6 +169,23 @@ fn network_from_statements(
 ///     OUTPUT(x0)
 /// ```
 pub fn read_bench<R: Read>(r: R) -> Result<Network, String> {
+    let (statements, inputs, outputs) = read_statements(r)?;
+    network_from_statements(&statements, &inputs, &outputs)
+}
+
+/// Read the names of the flip-flops in a .bench file, in the order of their gates in the network
+pub fn read_bench_dff_names<R: Read>(r: R) -> Result<Vec<String>, String> {
+    let (statements, _, _) = read_statements(r)?;
+    Ok(statements
+        .into_iter()
+        .filter(|s| ["DFF", "DFFRSE"].contains(&s[1].to_uppercase().as_str()))
+        .map(|s| s[0].clone())
+        .collect())
+}
+
+/// Read the statements, inputs and outputs of a .bench file
+#[allow(clippy::type_complexity)]
+fn read_statements<R: Read>(r: R) -> Result<(Vec<Vec<String>>, Vec<String>, Vec<String>), String> {
     let mut statements = Vec::new();
     let mut inputs = Vec::new();
     let mut outputs = Vec::new();
@@ -205,7 +222,7 @@ pub fn read_bench<R: Read>(r: R) -> Result<Network, String> {
             return Err("Error during file IO".to_string());
         }
     }
-    network_from_statements(&statements, &inputs, &outputs)
+    Ok((statements, inputs, outputs))
 }
 
 /// Write a network in .bench format, as used by the ISCAS benchmarks
@@ -299,6 +299,20 @@ pub fn read_blif<R: std::io::Read>(r: R) -> Result<Network, String> {
     build_network(&statements, &name_to_sig)
 }
 
+/// Read the names of the flip-flops in a .blif file, in the order of their gates in the network
+pub fn read_blif_dff_names<R: std::io::Read>(r: R) -> Result<Vec<String>, String> {
+    let statements = read_statements(r)?;
+    let mut ret = Vec::new();
+    for statement in statements {
+        match statement {
+            Statement::Latch { input: _, output } => ret.push(output),
+            Statement::Exdc => break,
+            _ => (),
+        }
+    }
+    Ok(ret)
+}
+
 pub fn write_blif_cube<W: Write>(w: &mut W, mask: usize, num_vars: usize, val: bool) {
     for i in 0..num_vars {
         let val_i = (mask >> i) & 1 != 0;
@@ -0,0 +1,141 @@
+//! IO for DIMACS CNF formulas and DRAT proofs
+
+use std::io::{BufRead, BufReader, Read, Write};
+
+use crate::equiv::ProofStep;
+
+/// Parse the literals of a clause, terminated by zero
+fn parse_clause(tokens: &[&str], line_ind: usize) -> Result<Vec<i32>, String> {
+    let mut ret = Vec::new();
+    for t in tokens {
+        let l = t
+            .parse::<i32>()
+            .map_err(|_| format!("Invalid literal {} on line {}", t, line_ind))?;
+        if l == 0 {
+            return Ok(ret);
+        }
+        ret.push(l);
+    }
+    Err(format!(
+        "Clause on line {} is not terminated by 0",
+        line_ind
+    ))
+}
+
+/// Read a CNF formula in DIMACS format
+///
+/// Returns the number of variables and the clauses. Each clause must be on a single line.
+pub fn read_dimacs<R: Read>(r: R) -> Result<(usize, Vec<Vec<i32>>), String> {
+    let mut nb_vars = None;
+    let mut clauses = Vec::new();
+    for (i, l) in BufReader::new(r).lines().enumerate() {
+        let s = l.map_err(|e| e.to_string())?;
+        let tokens: Vec<&str> = s.split_whitespace().collect();
+        if tokens.is_empty() || tokens[0] == "c" {
+            continue;
+        }
+        if tokens[0] == "p" {
+            if tokens.len() != 4 || tokens[1] != "cnf" {
+                return Err(format!("Invalid header on line {}", i + 1));
+            }
+            let nb = tokens[2]
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid number of variables on line {}", i + 1))?;
+            nb_vars = Some(nb);
+            continue;
+        }
+        clauses.push(parse_clause(&tokens, i + 1)?);
+    }
+    match nb_vars {
+        Some(nb) => Ok((nb, clauses)),
+        None => Err("Missing p cnf header".to_owned()),
+    }
+}
+
+/// Write a CNF formula in DIMACS format
+pub fn write_dimacs<W: Write>(w: &mut W, nb_vars: usize, clauses: &[Vec<i32>]) {
+    writeln!(w, "c Generated by quaigh").unwrap();
+    writeln!(w, "p cnf {} {}", nb_vars, clauses.len()).unwrap();
+    for c in clauses {
+        for l in c {
+            write!(w, "{} ", l).unwrap();
+        }
+        writeln!(w, "0").unwrap();
+    }
+}
+
+/// Read a proof in textual DRAT format
+///
+/// Each lemma is given on a single line, and deletions are prefixed by `d`.
+pub fn read_drat<R: Read>(r: R) -> Result<Vec<ProofStep>, String> {
+    let mut ret = Vec::new();
+    for (i, l) in BufReader::new(r).lines().enumerate() {
+        let s = l.map_err(|e| e.to_string())?;
+        let tokens: Vec<&str> = s.split_whitespace().collect();
+        if tokens.is_empty() || tokens[0] == "c" {
+            continue;
+        }
+        if tokens[0] == "d" {
+            ret.push(ProofStep::Delete(parse_clause(&tokens[1..], i + 1)?));
+        } else {
+            ret.push(ProofStep::Add(parse_clause(&tokens, i + 1)?));
+        }
+    }
+    Ok(ret)
+}
+
+/// Write a proof in textual DRAT format
+pub fn write_drat<W: Write>(w: &mut W, proof: &[ProofStep]) {
+    for step in proof {
+        let c = match step {
+            ProofStep::Add(c) => c,
+            ProofStep::Delete(c) => {
+                write!(w, "d ").unwrap();
+                c
+            }
+        };
+        for l in c {
+            write!(w, "{} ", l).unwrap();
+        }
+        writeln!(w, "0").unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_dimacs, read_drat, write_dimacs, write_drat};
+    use crate::equiv::ProofStep;
+
+    #[test]
+    fn test_dimacs_roundtrip() {
+        let clauses = vec![vec![1, -2], vec![2, 3, -1], vec![]];
+        let mut buf = Vec::new();
+        write_dimacs(&mut buf, 3, &clauses);
+        let (nb_vars, read) = read_dimacs(buf.as_slice()).unwrap();
+        assert_eq!(nb_vars, 3);
+        assert_eq!(read, clauses);
+    }
+
+    #[test]
+    fn test_drat_roundtrip() {
+        let proof = vec![
+            ProofStep::Add(vec![1, 2]),
+            ProofStep::Delete(vec![-1, 2]),
+            ProofStep::Add(vec![]),
+        ];
+        let mut buf = Vec::new();
+        write_drat(&mut buf, &proof);
+        assert_eq!(
+            String::from_utf8(buf.clone()).unwrap(),
+            "1 2 0\nd -1 2 0\n0\n"
+        );
+        assert_eq!(read_drat(buf.as_slice()).unwrap(), proof);
+    }
+
+    #[test]
+    fn test_dimacs_errors() {
+        assert!(read_dimacs("1 2 0\n".as_bytes()).is_err());
+        assert!(read_dimacs("p cnf 2 1\n1 2\n".as_bytes()).is_err());
+        assert!(read_dimacs("p cnf 2 1\n1 x 0\n".as_bytes()).is_err());
+    }
+}
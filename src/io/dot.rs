@@ -63,6 +63,14 @@ fn signal_source_id(s: &Signal) -> String {
 /// - Primary inputs use up triangle shape (▲)
 /// - Primary outputs use down triangle shape (▼)
 pub fn write_dot<W: Write>(w: &mut W, aig: &Network) {
+    write_dot_annotated(w, aig, &[]);
+}
+
+/// Write a network in DOT graph format, with an additional line in the label of each node
+///
+/// The annotations are given for each node, or may be empty to omit them.
+pub fn write_dot_annotated<W: Write>(w: &mut W, aig: &Network, annotations: &[String]) {
+    assert!(annotations.is_empty() || annotations.len() == aig.nb_nodes());
     writeln!(w, "digraph network {{").unwrap();
     writeln!(w, "    rankdir=TB;").unwrap();
     writeln!(w, "    node [fontname=\"Helvetica\"];").unwrap();
@@ -125,7 +133,10 @@ pub fn write_dot<W: Write>(w: &mut W, aig: &Network) {
     writeln!(w, "    // Internal nodes").unwrap();
     for i in 0..aig.nb_nodes() {
         let gate = aig.gate(i);
-        let label = gate_type_label(gate);
+        let mut label = gate_type_label(gate);
+        if let Some(a) = annotations.get(i) {
+            label = format!("{}\\n{}", label, a);
+        }
         let shape = if matches!(gate, Gate::Dff(_)) {
             "box"
         } else {
@@ -228,4 +239,18 @@ mod tests {
         assert!(dot.contains("And2")); // gate type
         assert!(dot.contains("style=dashed")); // inverted edges
     }
+
+    #[test]
+    fn test_write_dot_annotated() {
+        let mut aig = Network::default();
+        let x1 = aig.add_input();
+        let x2 = aig.add_input();
+        let f = aig.and(x1, x2);
+        aig.add_output(f);
+
+        let mut buf = BufWriter::new(Vec::new());
+        write_dot_annotated(&mut buf, &aig, &["CC 2/3".to_string()]);
+        let dot = String::from_utf8(buf.into_inner().unwrap()).unwrap();
+        assert!(dot.contains("And2\\nCC 2/3"));
+    }
 }
@@ -10,6 +10,7 @@ pub mod io;
 pub mod network;
 pub mod optim;
 pub mod sim;
+pub mod utils;
 
 use clap::Parser;
 pub use network::{Gate, NaryType, Network, Signal};
@@ -20,11 +21,19 @@ fn main() {
 
     match cli.command {
         cmd::Commands::CheckEquivalence(a) => a.run(),
+        cmd::Commands::CheckProof(a) => a.run(),
         cmd::Commands::Optimize(a) => a.run(),
         cmd::Commands::Show(a) => a.run(),
         cmd::Commands::Simulate(a) => a.run(),
+        cmd::Commands::Seu(a) => a.run(),
         cmd::Commands::Atpg(a) => a.run(),
         cmd::Commands::AtpgReport(a) => a.run(),
+        cmd::Commands::Diagnose(a) => a.run(),
+        cmd::Commands::Scan(a) => a.run(),
+        cmd::Commands::Bist(a) => a.run(),
+        cmd::Commands::Compactor(a) => a.run(),
+        cmd::Commands::Testability(a) => a.run(),
+        cmd::Commands::TestPoints(a) => a.run(),
         cmd::Commands::Convert(a) => a.run(),
     }
 }
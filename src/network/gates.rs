@@ -204,6 +204,34 @@ impl Gate {
         }
     }
 
+    /// Name of the type of the gate, for reporting
+    pub(crate) fn type_name(&self) -> &'static str {
+        use Gate::*;
+        match self {
+            Binary(_, BinaryType::And) | Ternary(_, TernaryType::And) | Nary(_, NaryType::And) => {
+                "And"
+            }
+            Nary(_, NaryType::Or) => "Or",
+            Nary(_, NaryType::Nand) => "Nand",
+            Nary(_, NaryType::Nor) => "Nor",
+            Binary(_, BinaryType::Xor) | Ternary(_, TernaryType::Xor) | Nary(_, NaryType::Xor) => {
+                "Xor"
+            }
+            Nary(_, NaryType::Xnor) => "Xnor",
+            Ternary(_, TernaryType::Mux) => "Mux",
+            Ternary(_, TernaryType::Maj) => "Maj",
+            Buf(s) => {
+                if s.is_inverted() {
+                    "Not"
+                } else {
+                    "Buf"
+                }
+            }
+            Dff(_) => "Dff",
+            Lut(_) => "Lut",
+        }
+    }
+
     /// Obtain all internal variables feeding this gate (not inputs or constants)
     pub fn vars(&self) -> impl Iterator<Item = u32> + '_ {
         // TODO: return a concrete iterator instead
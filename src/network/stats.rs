@@ -244,3 +244,10 @@ pub fn gate_is_output(aig: &Network) -> Vec<bool> {
     }
     ret
 }
+
+/// List the flip-flops of the network, in order
+pub fn dff_gates(aig: &Network) -> Vec<usize> {
+    (0..aig.nb_nodes())
+        .filter(|i| !aig.gate(*i).is_comb())
+        .collect()
+}
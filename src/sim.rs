@@ -2,12 +2,19 @@
 
 mod fault;
 mod incremental_sim;
+mod seu;
 mod simple_sim;
 
+use std::iter::zip;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
 use crate::sim::incremental_sim::IncrementalSimulator;
 use crate::Network;
 
-pub use fault::Fault;
+pub use fault::{BridgeType, DefectLibrary, Fault};
+pub use seu::{analyze_seu, VulnerabilityReport};
 
 /// Simple conversion to 64b format
 fn bool_to_multi(values: &Vec<Vec<bool>>) -> Vec<Vec<u64>> {
@@ -27,6 +34,36 @@ fn multi_to_bool(values: &Vec<Vec<u64>>) -> Vec<Vec<bool>> {
     ret
 }
 
+/// Mask of the valid bits of a 64b word holding the given number of patterns
+pub(crate) fn pattern_mask(nb_patterns: usize) -> u64 {
+    if nb_patterns >= 64 {
+        !0
+    } else {
+        (1 << nb_patterns) - 1
+    }
+}
+
+/// Pack single-cycle patterns 64 at a time, with the mask of the valid bits of each word
+pub(crate) fn pack_patterns<P: AsRef<[bool]>>(
+    patterns: &[P],
+    width: usize,
+) -> (Vec<Vec<u64>>, Vec<u64>) {
+    let mut packed = Vec::new();
+    let mut masks = Vec::new();
+    for chunk in patterns.chunks(64) {
+        let mut words = vec![0u64; width];
+        for (b, p) in chunk.iter().enumerate() {
+            assert_eq!(p.as_ref().len(), width);
+            for (w, v) in words.iter_mut().zip(p.as_ref()) {
+                *w |= u64::from(*v) << b;
+            }
+        }
+        packed.push(words);
+        masks.push(pattern_mask(chunk.len()));
+    }
+    (packed, masks)
+}
+
 /// Simulate a network over multiple timesteps; return the output values
 pub fn simulate(a: &Network, input_values: &Vec<Vec<bool>>) -> Vec<Vec<bool>> {
     let multi_input = bool_to_multi(input_values);
@@ -34,6 +71,20 @@ pub fn simulate(a: &Network, input_values: &Vec<Vec<bool>>) -> Vec<Vec<bool>> {
     multi_to_bool(&multi_ret)
 }
 
+/// Simulate a network over multiple timesteps, starting from the given flip-flop values; return the output values
+///
+/// The flip-flop values are given in the order of the flip-flops in the network.
+pub fn simulate_from(
+    a: &Network,
+    dff_values: &[bool],
+    input_values: &Vec<Vec<bool>>,
+) -> Vec<Vec<bool>> {
+    let multi_dff: Vec<u64> = dff_values.iter().map(|b| if *b { !0 } else { 0 }).collect();
+    let multi_input = bool_to_multi(input_values);
+    let multi_ret = simulate_multi_from(a, &multi_dff, &multi_input);
+    multi_to_bool(&multi_ret)
+}
+
 /// Simulate a combinatorial network; return the output values
 pub fn simulate_comb(a: &Network, input_values: &Vec<bool>) -> Vec<bool> {
     assert!(a.is_comb());
@@ -65,6 +116,39 @@ pub fn simulate_comb_with_faults(
     output[0].clone()
 }
 
+/// Simulate a network over multiple timesteps with 3-valued logic; return the output values
+///
+/// Unknown (X) values are represented by None. Flip-flops start in an unknown state, and
+/// become known once they are reset or load a known value.
+pub fn simulate_x(a: &Network, input_values: &[Vec<Option<bool>>]) -> Vec<Vec<Option<bool>>> {
+    use simple_sim::{SimpleSimulator, XWord};
+    let to_word = |v: &Option<bool>| match v {
+        None => XWord::unknown(),
+        Some(b) => XWord::known(if *b { !0 } else { 0 }),
+    };
+    let multi_input: Vec<Vec<XWord>> = input_values
+        .iter()
+        .map(|v| v.iter().map(to_word).collect())
+        .collect();
+    let mut sim = SimpleSimulator::from_aig(a);
+    sim.run_x(&multi_input)
+        .iter()
+        .map(|v| {
+            v.iter()
+                .map(|w| (w.is_known() != 0).then_some(w.one != 0))
+                .collect()
+        })
+        .collect()
+}
+
+/// Simulate a combinatorial network with 3-valued logic; return the output values
+pub fn simulate_comb_x(a: &Network, input_values: &[Option<bool>]) -> Vec<Option<bool>> {
+    assert!(a.is_comb());
+    let input = vec![input_values.to_vec()];
+    let output = simulate_x(a, &input);
+    output[0].clone()
+}
+
 /// Simulate a network over multiple timesteps with 64b inputs; return the output values
 pub(crate) fn simulate_multi(a: &Network, input_values: &Vec<Vec<u64>>) -> Vec<Vec<u64>> {
     use simple_sim::SimpleSimulator;
@@ -72,6 +156,17 @@ pub(crate) fn simulate_multi(a: &Network, input_values: &Vec<Vec<u64>>) -> Vec<V
     sim.run(input_values)
 }
 
+/// Simulate a network over multiple timesteps with 64b inputs, starting from the given flip-flop values; return the output values
+pub(crate) fn simulate_multi_from(
+    a: &Network,
+    dff_values: &[u64],
+    input_values: &Vec<Vec<u64>>,
+) -> Vec<Vec<u64>> {
+    use simple_sim::SimpleSimulator;
+    let mut sim = SimpleSimulator::from_aig(a);
+    sim.run_from(dff_values, input_values)
+}
+
 /// Simulate a network over multiple timesteps with 64b inputs; return the output values
 pub(crate) fn simulate_multi_with_faults(
     a: &Network,
@@ -100,6 +195,157 @@ pub(crate) fn detects_faults_multi(
     detections
 }
 
+/// Minimum number of fault simulations, as faults times words of patterns, to use multiple threads
+const PARALLEL_MIN_WORK: usize = 1024;
+
+/// Number of threads to use, where 0 means all available cores
+pub(crate) fn nb_threads_or_available(nb_threads: usize) -> usize {
+    if nb_threads == 0 {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    } else {
+        nb_threads
+    }
+}
+
+/// Run tasks on a pool of threads, one per worker, and return the result of each task
+///
+/// The workers take the next task in order until all are done, and each task is run with the
+/// state of its worker, such as a simulator. A single worker runs on the calling thread.
+pub(crate) fn run_task_pool<W, R, F>(workers: &mut [W], nb_tasks: usize, run: F) -> Vec<R>
+where
+    W: Send,
+    R: Send,
+    F: Fn(&mut W, usize) -> R + Sync,
+{
+    assert!(!workers.is_empty());
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..nb_tasks).map(|_| None).collect::<Vec<Option<R>>>());
+    let work = |worker: &mut W| loop {
+        let t = next.fetch_add(1, Ordering::Relaxed);
+        if t >= nb_tasks {
+            break;
+        }
+        let r = run(worker, t);
+        results.lock().unwrap()[t] = Some(r);
+    };
+    if workers.len() == 1 {
+        work(&mut workers[0]);
+    } else {
+        let work = &work;
+        thread::scope(|s| {
+            for worker in workers.iter_mut() {
+                s.spawn(move || work(worker));
+            }
+        });
+    }
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.unwrap())
+        .collect()
+}
+
+/// Fault simulator distributing the work over a pool of threads
+///
+/// Each 64b word of patterns is simulated separately, and the faults are split in chunks that are
+/// distributed over the threads. The result does not depend on the number of threads.
+/// The simulators of the threads are kept between calls, so that repeated calls on the same network,
+/// such as one per word of patterns during test generation, do not rebuild them. Small amounts of
+/// work are simulated on the calling thread.
+pub(crate) struct ParallelFaultSimulator<'a> {
+    aig: &'a Network,
+    nb_threads: usize,
+    /// Simulator of each thread, created on first use
+    sims: Vec<IncrementalSimulator<'a>>,
+}
+
+impl<'a> ParallelFaultSimulator<'a> {
+    /// Create a simulator for a network; a number of threads of 0 uses all available cores
+    pub(crate) fn new(aig: &'a Network, nb_threads: usize) -> ParallelFaultSimulator<'a> {
+        assert!(aig.is_comb());
+        assert!(aig.is_topo_sorted());
+        ParallelFaultSimulator {
+            aig,
+            nb_threads: nb_threads_or_available(nb_threads),
+            sims: Vec::new(),
+        }
+    }
+
+    /// Number of threads used for fault simulation
+    pub(crate) fn nb_threads(&self) -> usize {
+        self.nb_threads
+    }
+
+    /// Analyze which of a set of patterns detect the given faults
+    pub(crate) fn detects_faults_multi(
+        &mut self,
+        patterns: &[Vec<u64>],
+        faults: &[Fault],
+    ) -> Vec<Vec<u64>> {
+        // Tasks are a word of patterns and a chunk of faults, ordered by word
+        let chunk_size = faults.len().div_ceil(4 * self.nb_threads).max(64);
+        let nb_chunks = faults.len().div_ceil(chunk_size);
+        let nb_tasks = patterns.len() * nb_chunks;
+        let nb_workers = if patterns.len() * faults.len() < PARALLEL_MIN_WORK {
+            1
+        } else {
+            self.nb_threads.min(nb_tasks).max(1)
+        };
+        while self.sims.len() < nb_workers {
+            self.sims.push(IncrementalSimulator::from_aig(self.aig));
+        }
+        // Each worker keeps the word of patterns it simulated last
+        let mut workers: Vec<(&mut IncrementalSimulator<'a>, Option<usize>)> = self
+            .sims
+            .iter_mut()
+            .take(nb_workers)
+            .map(|sim| (sim, None))
+            .collect();
+        let detections = run_task_pool(&mut workers, nb_tasks, |(incr_sim, current_word), t| {
+            let w = t / nb_chunks;
+            if *current_word != Some(w) {
+                incr_sim.run_initial(&patterns[w]);
+                *current_word = Some(w);
+            }
+            let begin = (t % nb_chunks) * chunk_size;
+            let end = faults.len().min(begin + chunk_size);
+            faults[begin..end]
+                .iter()
+                .map(|f| incr_sim.detects_fault(*f))
+                .collect::<Vec<u64>>()
+        });
+        let mut ret = vec![Vec::with_capacity(faults.len()); patterns.len()];
+        for (t, d) in detections.into_iter().enumerate() {
+            ret[t / nb_chunks].extend(d);
+        }
+        ret
+    }
+}
+
+/// Analyze which of a set of patterns detect the given faults, using multiple threads
+///
+/// This is a single use of a [`ParallelFaultSimulator`]. A number of threads of 0 uses all
+/// available cores.
+pub(crate) fn detects_faults_multi_parallel(
+    aig: &Network,
+    patterns: &[Vec<u64>],
+    faults: &[Fault],
+    nb_threads: usize,
+) -> Vec<Vec<u64>> {
+    ParallelFaultSimulator::new(aig, nb_threads).detects_faults_multi(patterns, faults)
+}
+
+/// Compute the values of all nodes of a combinatorial network for 64b inputs
+pub(crate) fn simulate_nodes_multi(aig: &Network, pattern: &[u64]) -> Vec<u64> {
+    use simple_sim::SimpleSimulator;
+    assert!(aig.is_comb());
+    let mut sim = SimpleSimulator::from_aig(aig);
+    sim.copy_inputs(pattern);
+    sim.run_comb();
+    sim.node_values
+}
+
 /// Analyze whether a pattern detects a given fault
 pub(crate) fn detects_faults(aig: &Network, pattern: &Vec<bool>, faults: &Vec<Fault>) -> Vec<bool> {
     let multi_pattern = pattern
@@ -116,15 +362,96 @@ pub(crate) fn detects_faults(aig: &Network, pattern: &Vec<bool>, faults: &Vec<Fa
         .collect()
 }
 
+/// Analyze which of a set of patterns detect the given faults, with some inputs unknown
+///
+/// The inputs in `x_inputs` are X sources, and take an unknown value instead of the pattern's.
+/// With 3-valued simulation, a fault is only detected when an output takes known and different
+/// values with and without the fault. Each fault only simulates the gates from its first faulty
+/// gate, and the faults are split in chunks distributed over the given number of threads, or all
+/// available cores for 0.
+pub(crate) fn detects_faults_x_multi(
+    aig: &Network,
+    pattern: &[u64],
+    x_inputs: &[usize],
+    faults: &[Fault],
+    nb_threads: usize,
+) -> Vec<u64> {
+    use simple_sim::{SimpleSimulator, XWord};
+    assert!(aig.is_comb());
+    let inputs: Vec<XWord> = pattern
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            if x_inputs.contains(&i) {
+                XWord::unknown()
+            } else {
+                XWord::known(*v)
+            }
+        })
+        .collect();
+    let mut sim = SimpleSimulator::from_aig(aig);
+    sim.reset_x();
+    sim.copy_inputs_x(&inputs);
+    sim.run_comb_x();
+    let expected = sim.get_output_values_x();
+    let expected_nodes = sim.x_node_values.clone();
+
+    let nb_threads = nb_threads_or_available(nb_threads);
+    let chunk_size = faults.len().div_ceil(4 * nb_threads).max(64);
+    let nb_chunks = faults.len().div_ceil(chunk_size);
+    let nb_workers = if faults.len() < PARALLEL_MIN_WORK {
+        1
+    } else {
+        nb_threads.min(nb_chunks)
+    };
+    let mut sims = vec![sim; nb_workers];
+    let detections = run_task_pool(&mut sims, nb_chunks, |sim, c| {
+        let begin = c * chunk_size;
+        let end = faults.len().min(begin + chunk_size);
+        faults[begin..end]
+            .iter()
+            .map(|f| {
+                let start = f.gates().into_iter().min().unwrap();
+                sim.run_comb_x_with_faults(&[*f], start);
+                let d = zip(&expected, sim.get_output_values_x()).fold(0, |d, (a, b)| {
+                    d | (a.is_known() & b.is_known() & (a.one ^ b.one))
+                });
+                // Restore the fault-free values for the next fault
+                sim.x_node_values[start..].copy_from_slice(&expected_nodes[start..]);
+                d
+            })
+            .collect::<Vec<u64>>()
+    });
+    detections.concat()
+}
+
+/// Analyze whether a pattern detects the given faults, with some inputs unknown
+pub(crate) fn detects_faults_x(
+    aig: &Network,
+    pattern: &[bool],
+    x_inputs: &[usize],
+    faults: &[Fault],
+) -> Vec<bool> {
+    let multi_pattern: Vec<u64> = pattern.iter().map(|b| if *b { !0 } else { 0 }).collect();
+    detects_faults_x_multi(aig, &multi_pattern, x_inputs, faults, 1)
+        .iter()
+        .map(|d| *d != 0)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use volute::{Lut3, Lut5};
+    use volute::{Lut2, Lut3, Lut5};
 
     use crate::network::NaryType;
     use crate::sim::simulate_multi;
     use crate::{Gate, Network, Signal};
 
-    use super::simulate;
+    use super::{
+        detects_faults, detects_faults_multi, detects_faults_multi_parallel,
+        detects_faults_x_multi, simulate, simulate_comb, simulate_comb_with_faults, simulate_from,
+        BridgeType, DefectLibrary, Fault, ParallelFaultSimulator,
+    };
 
     #[test]
     fn test_basic() {
@@ -183,6 +510,19 @@ mod tests {
         assert_eq!(simulate(&aig, &pattern), expected);
     }
 
+    #[test]
+    fn test_dff_init() {
+        let mut aig = Network::default();
+        let d = aig.add_input();
+        let x0 = aig.dff(d, Signal::one(), Signal::zero());
+        let x1 = aig.dff(x0, Signal::one(), Signal::zero());
+        aig.add_output(x0);
+        aig.add_output(x1);
+        let pattern = vec![vec![false], vec![false], vec![false]];
+        let expected = vec![vec![true, false], vec![false, true], vec![false, false]];
+        assert_eq!(simulate_from(&aig, &[true, false], &pattern), expected);
+    }
+
     #[test]
     fn test_nary() {
         let mut aig = Network::default();
@@ -357,4 +697,304 @@ mod tests {
 
         assert_eq!(simulate_multi(&aig, &pattern), expected);
     }
+
+    #[test]
+    fn test_bridging() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let x = aig.and(i0, i1);
+        let y = aig.xor(i1, i2);
+        let z = aig.and(x, !i2);
+        aig.add_output(y);
+        aig.add_output(z);
+
+        let pattern = vec![false, true, false];
+        let wired_and = Fault::BridgingFault {
+            a: 0,
+            b: 1,
+            kind: BridgeType::WiredAnd,
+        };
+        let dominant_b = Fault::BridgingFault {
+            a: 0,
+            b: 1,
+            kind: BridgeType::DominantB,
+        };
+        assert_eq!(simulate_comb(&aig, &pattern), vec![true, false]);
+        assert_eq!(
+            simulate_comb_with_faults(&aig, &pattern, &vec![wired_and]),
+            vec![false, false]
+        );
+        assert_eq!(
+            simulate_comb_with_faults(&aig, &pattern, &vec![dominant_b]),
+            vec![true, true]
+        );
+
+        // Incremental and full fault simulation agree, including for the feedback bridge
+        let faults = Fault::all_bridging(&[(0, 1), (0, 2), (1, 2)]);
+        assert_eq!(faults.len(), 12);
+        for p in 0..8 {
+            let pattern: Vec<bool> = (0..3).map(|i| (p >> i) & 1 != 0).collect();
+            let expected = simulate_comb(&aig, &pattern);
+            let detected = detects_faults(&aig, &pattern, &faults);
+            for (f, d) in faults.iter().zip(detected) {
+                let faulty = simulate_comb_with_faults(&aig, &pattern, &vec![*f]);
+                assert_eq!(d, faulty != expected, "{} on pattern {:?}", f, pattern);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cell_faults() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let l = aig.add(Gate::lut(&[i0, i1], Lut2::nth_var(0).into()));
+        let x = aig.and(l, !i2);
+        aig.add_output(x);
+
+        // Each bit-flip changes the output for the corresponding input values only
+        let flips = Fault::all_lut_bit_flips(&aig);
+        assert_eq!(flips.len(), 4);
+        for (bit, f) in flips.iter().enumerate() {
+            for p in 0..4 {
+                let pattern = vec![p & 1 != 0, p & 2 != 0, false];
+                let faulty = simulate_comb_with_faults(&aig, &pattern, &vec![*f]);
+                assert_eq!(faulty[0], (p & 1 != 0) ^ (p == bit));
+            }
+        }
+
+        // The And gate computes a Xor of its inputs; the fault-free function is ignored
+        let mut library = DefectLibrary::new();
+        library.add("And", 2, 0x6);
+        library.add("And", 2, 0x8);
+        library.add("Xor", 2, 0x0);
+        library.add("And", 3, 0x0);
+        assert_eq!(
+            library.unmatched(&aig),
+            vec![("And".to_string(), 3), ("Xor".to_string(), 2)]
+        );
+        let defects = Fault::all_defects(&aig, &library);
+        assert_eq!(
+            defects,
+            vec![Fault::DefectFault {
+                gate: 1,
+                table: 0x6
+            }]
+        );
+        for p in 0..8 {
+            let pattern: Vec<bool> = (0..3).map(|i| (p >> i) & 1 != 0).collect();
+            let faulty = simulate_comb_with_faults(&aig, &pattern, &defects);
+            assert_eq!(faulty[0], pattern[0] ^ !pattern[2]);
+        }
+
+        // Incremental and full fault simulation agree
+        let faults: Vec<Fault> = flips.iter().chain(&defects).copied().collect();
+        for p in 0..8 {
+            let pattern: Vec<bool> = (0..3).map(|i| (p >> i) & 1 != 0).collect();
+            let expected = simulate_comb(&aig, &pattern);
+            let detected = detects_faults(&aig, &pattern, &faults);
+            for (f, d) in faults.iter().zip(detected) {
+                let faulty = simulate_comb_with_faults(&aig, &pattern, &vec![*f]);
+                assert_eq!(d, faulty != expected, "{} on pattern {:?}", f, pattern);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fault_collapsing() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let i3 = aig.add_input();
+        let a = aig.and(i0, !i1);
+        let o = aig.add(Gate::Nary(Box::new([a, i2]), NaryType::Or));
+        let n = aig.add(Gate::Nary(Box::new([i1, i3]), NaryType::Nand));
+        let x = aig.xor(o, n);
+        let m = aig.add(Gate::mux(i0, x, i2));
+        let j = aig.add(Gate::maj(m, i3, a));
+        let l = aig.add(Gate::lut(
+            &[j, n],
+            (Lut2::nth_var(0) & Lut2::nth_var(1)).into(),
+        ));
+        let b = aig.add(Gate::Buf(!l));
+        aig.add_output(b);
+        aig.add_output(x);
+
+        let faults = Fault::all(&aig);
+        let patterns: Vec<Vec<bool>> = (0..16)
+            .map(|p| (0..4).map(|i| (p >> i) & 1 != 0).collect())
+            .collect();
+        let detections = |f: &Fault| -> Vec<bool> {
+            patterns
+                .iter()
+                .map(|p| detects_faults(&aig, p, &vec![*f])[0])
+                .collect()
+        };
+
+        let classes = Fault::equivalence_classes(&aig);
+        assert!(classes.len() < faults.len());
+        assert_eq!(classes.iter().map(|c| c.len()).sum::<usize>(), faults.len());
+        for c in &classes {
+            let d = detections(&c[0]);
+            for f in c {
+                assert_eq!(detections(f), d, "{} and {} are not equivalent", f, c[0]);
+            }
+        }
+
+        let collapsed = Fault::all_collapsed(&aig);
+        let dominance = Fault::all_dominance_collapsed(&aig);
+        assert_eq!(collapsed.len(), classes.len());
+        assert!(dominance.len() < collapsed.len());
+        // Each dropped fault is detected by all the tests of a remaining fault
+        let kept: Vec<Vec<bool>> = dominance.iter().map(&detections).collect();
+        for f in &collapsed {
+            let d = detections(f);
+            assert!(kept
+                .iter()
+                .any(|k| k.iter().zip(&d).all(|(a, b)| !*a || *b)));
+        }
+    }
+
+    #[test]
+    fn test_dominance_collapsing() {
+        let output = |value| Fault::OutputStuckAtFault { gate: 0, value };
+        let input = |input, value| Fault::InputStuckAtFault {
+            gate: 0,
+            input,
+            value,
+        };
+        let single_gate = |g: fn(&[Signal]) -> Gate| {
+            let mut aig = Network::default();
+            let inputs: Vec<Signal> = (0..3).map(|_| aig.add_input()).collect();
+            let x = aig.add(g(&inputs));
+            aig.add_output(x);
+            Fault::all_dominance_collapsed(&aig)
+        };
+
+        // Mux: the output faults dominate the faults on the data inputs, but not on the selector
+        let mux = single_gate(|i| Gate::mux(i[0], i[1], i[2]));
+        assert_eq!(
+            mux,
+            vec![
+                input(0, false),
+                input(0, true),
+                input(1, false),
+                input(1, true),
+                input(2, false),
+                input(2, true),
+            ]
+        );
+
+        // Maj: the output faults dominate the input faults
+        let maj = single_gate(|i| Gate::maj(i[0], i[1], i[2]));
+        assert_eq!(maj, mux);
+
+        // And as a Lut: input s-a-0 is equivalent to output s-a-0, and output s-a-1 dominates input s-a-1
+        let and = single_gate(|i| Gate::lut(&i[..2], (Lut2::nth_var(0) & Lut2::nth_var(1)).into()));
+        assert_eq!(and, vec![output(false), input(0, true), input(1, true)]);
+
+        // Xor as a Lut: no equivalence nor dominance
+        let xor = single_gate(|i| Gate::lut(&i[..2], (Lut2::nth_var(0) ^ Lut2::nth_var(1)).into()));
+        assert_eq!(
+            xor,
+            vec![
+                output(false),
+                output(true),
+                input(0, false),
+                input(0, true),
+                input(1, false),
+                input(1, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parallel_fault_simulation() {
+        let mut aig = Network::default();
+        let mut signals: Vec<Signal> = (0..8).map(|_| aig.add_input()).collect();
+        for i in 0..100 {
+            let a = signals[(7 * i + 3) % signals.len()];
+            let b = signals[(13 * i + 5) % signals.len()];
+            let s = if i % 3 == 0 {
+                aig.xor(a, b)
+            } else {
+                aig.and(a, !b)
+            };
+            signals.push(s);
+        }
+        for s in &signals[100..] {
+            aig.add_output(*s);
+        }
+        let faults = Fault::all(&aig);
+        let mut state = 0x9e3779b97f4a7c15u64;
+        let patterns: Vec<Vec<u64>> = (0..5)
+            .map(|_| {
+                (0..8)
+                    .map(|_| {
+                        state = state
+                            .wrapping_mul(6364136223846793005)
+                            .wrapping_add(1442695040888963407);
+                        state
+                    })
+                    .collect()
+            })
+            .collect();
+        let expected: Vec<Vec<u64>> = patterns
+            .iter()
+            .map(|p| detects_faults_multi(&aig, p, &faults))
+            .collect();
+        for nb_threads in [1, 2, 3, 8] {
+            assert_eq!(
+                detects_faults_multi_parallel(&aig, &patterns, &faults, nb_threads),
+                expected
+            );
+            // The simulators are reused from one call to the next
+            let mut sim = ParallelFaultSimulator::new(&aig, nb_threads);
+            for (p, e) in patterns.iter().zip(&expected) {
+                let d = sim.detects_faults_multi(std::slice::from_ref(p), &faults);
+                assert_eq!(&d[0], e);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_x_fault_simulation() {
+        let mut aig = Network::default();
+        let mut signals: Vec<Signal> = (0..8).map(|_| aig.add_input()).collect();
+        for i in 0..250 {
+            let a = signals[(7 * i + 3) % signals.len()];
+            let b = signals[(13 * i + 5) % signals.len()];
+            let s = if i % 3 == 0 {
+                aig.xor(a, b)
+            } else {
+                aig.and(a, !b)
+            };
+            signals.push(s);
+        }
+        for s in &signals[200..] {
+            aig.add_output(*s);
+        }
+        let mut faults = Fault::all(&aig);
+        faults.extend(Fault::all_bridging(&[(20, 50), (100, 101)]));
+        assert!(faults.len() >= 1024);
+        let pattern: Vec<u64> = (0..8)
+            .map(|i| 0x9e3779b97f4a7c15u64.wrapping_mul(i + 1))
+            .collect();
+        let x_inputs = [0, 5];
+        // Each fault simulated on its own, from the fault-free values
+        let expected: Vec<u64> = faults
+            .iter()
+            .map(|f| detects_faults_x_multi(&aig, &pattern, &x_inputs, &[*f], 1)[0])
+            .collect();
+        for nb_threads in [1, 2, 3, 8] {
+            assert_eq!(
+                detects_faults_x_multi(&aig, &pattern, &x_inputs, &faults, nb_threads),
+                expected
+            );
+        }
+    }
 }
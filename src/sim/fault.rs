@@ -1,8 +1,131 @@
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
-use crate::network::{stats, NaryType};
+use volute::Lut;
+
+use crate::network::{stats, BinaryType, NaryType, TernaryType};
+use crate::sim::simulate_multi;
+use crate::utils::compute_levels;
 use crate::{Gate, Network, Signal};
 
+/// Number of gates considered at each level for bridging faults with a given gate
+const BRIDGE_NEIGHBOURS: usize = 4;
+
+/// Behaviour of a bridge between two nets
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BridgeType {
+    /// Both nets take the And of their values
+    WiredAnd,
+    /// Both nets take the Or of their values
+    WiredOr,
+    /// The first net drives the second one
+    DominantA,
+    /// The second net drives the first one
+    DominantB,
+}
+
+impl BridgeType {
+    /// All bridge types
+    pub fn all() -> [BridgeType; 4] {
+        [
+            BridgeType::WiredAnd,
+            BridgeType::WiredOr,
+            BridgeType::DominantA,
+            BridgeType::DominantB,
+        ]
+    }
+
+    /// Values taken by the two nets, from their fault-free values
+    pub fn apply(&self, a: u64, b: u64) -> (u64, u64) {
+        match self {
+            BridgeType::WiredAnd => (a & b, a & b),
+            BridgeType::WiredOr => (a | b, a | b),
+            BridgeType::DominantA => (a, a),
+            BridgeType::DominantB => (b, b),
+        }
+    }
+}
+
+/// Value forced on the output of a gate when an input is stuck at a value, if any
+fn forced_output(g: &Gate, input: usize, value: bool) -> Option<bool> {
+    match g {
+        Gate::Binary(_, BinaryType::And)
+        | Gate::Ternary(_, TernaryType::And)
+        | Gate::Nary(_, NaryType::And) => (!value).then_some(false),
+        Gate::Nary(_, NaryType::Nand) => (!value).then_some(true),
+        Gate::Nary(_, NaryType::Or) => value.then_some(true),
+        Gate::Nary(_, NaryType::Nor) => value.then_some(false),
+        Gate::Buf(_) => Some(value),
+        Gate::Lut(lut) => {
+            let nb_vars = lut.lut.num_vars();
+            let (c0, c1) = lut.lut.cofactors(input);
+            let c = if value { c1 } else { c0 };
+            if c == Lut::zero(nb_vars) {
+                Some(false)
+            } else if c == Lut::one(nb_vars) {
+                Some(true)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Value of the output stuck-at fault that dominates an input stuck-at fault, if any
+///
+/// Every test for the input fault also detects the output fault: the faulty output takes the
+/// same value in both cases.
+fn dominating_output(g: &Gate, input: usize, value: bool) -> Option<bool> {
+    match g {
+        // A test for a data input s-a-v selects it and sets it to !v, so the output is also !v
+        Gate::Ternary(_, TernaryType::Mux) => (input != 0).then_some(value),
+        // A test for an input s-a-v sets it to !v and the two other inputs to different values
+        Gate::Ternary(_, TernaryType::Maj) => Some(value),
+        // Xor gates have no dominance: a test for an input fault may set the output to any value.
+        // Otherwise, if the input at !v forces the output to c, output s-a-!c dominates input s-a-v
+        _ => forced_output(g, input, !value).map(|out| !out),
+    }
+}
+
+/// Simple union-find structure to build equivalence classes
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(n: usize) -> UnionFind {
+        UnionFind {
+            parent: (0..n).collect(),
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let ra = self.find(a);
+        let rb = self.find(b);
+        self.parent[ra] = rb;
+    }
+}
+
+impl fmt::Display for BridgeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BridgeType::WiredAnd => write!(f, "wired-AND"),
+            BridgeType::WiredOr => write!(f, "wired-OR"),
+            BridgeType::DominantA => write!(f, "dominant-A"),
+            BridgeType::DominantB => write!(f, "dominant-B"),
+        }
+    }
+}
+
 /// Representation of a fault, with its type and location
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Fault {
@@ -22,6 +145,138 @@ pub enum Fault {
         /// Fault value
         value: bool,
     },
+    /// Transition fault: the output of the given gate is slow to switch to a value, and keeps its previous value for one cycle
+    TransitionFault {
+        /// Gate where the fault is located
+        gate: usize,
+        /// Value the gate is slow to switch to: true for slow-to-rise, false for slow-to-fall
+        value: bool,
+    },
+    /// Bridging fault: the outputs of two gates are shorted together
+    ///
+    /// The values of the bridged nets are computed from their fault-free drivers. A bridge where
+    /// the second gate depends on the first one (feedback bridge) is only propagated once.
+    BridgingFault {
+        /// First gate of the bridge
+        a: usize,
+        /// Second gate of the bridge, after the first one
+        b: usize,
+        /// Behaviour of the bridge
+        kind: BridgeType,
+    },
+    /// Lut bit-flip fault: one bit of the truth table of a Lut gate is inverted
+    ///
+    /// This models an upset in the configuration memory of an FPGA.
+    LutBitFlipFault {
+        /// Gate where the fault is located
+        gate: usize,
+        /// Index of the flipped bit in the truth table
+        bit: usize,
+    },
+    /// Cell-internal defect: the gate computes another function of its inputs
+    ///
+    /// The faulty function is a truth table over the values on the inputs of the gate, with the
+    /// first input as the least significant bit of the index. Gates have at most 6 inputs.
+    DefectFault {
+        /// Gate where the fault is located
+        gate: usize,
+        /// Truth table of the faulty gate
+        table: u64,
+    },
+}
+
+/// Library of cell-internal defects, as faulty truth tables for each type of gate
+///
+/// Gate types are named as in fault reports (And, Xor, Mux, Maj, Buf, Not, Lut, ...), and
+/// defects apply to the gates of the network after normalization, where Or gates are usually
+/// represented as And gates with inverted inputs.
+#[derive(Clone, Debug, Default)]
+pub struct DefectLibrary {
+    defects: HashMap<(String, usize), Vec<u64>>,
+}
+
+impl DefectLibrary {
+    /// Create an empty library
+    pub fn new() -> DefectLibrary {
+        DefectLibrary::default()
+    }
+
+    /// Add a defect for the gates of this type and number of inputs, as the truth table of the faulty gate
+    pub fn add(&mut self, gate_type: &str, nb_inputs: usize, table: u64) {
+        assert!(
+            nb_inputs <= 6,
+            "Defects are supported for gates with up to 6 inputs"
+        );
+        assert!(
+            table & !table_mask(nb_inputs) == 0,
+            "Truth table too large for the number of inputs"
+        );
+        let v = self
+            .defects
+            .entry((gate_type.to_string(), nb_inputs))
+            .or_default();
+        if !v.contains(&table) {
+            v.push(table);
+        }
+    }
+
+    /// Returns whether the library has no defect
+    pub fn is_empty(&self) -> bool {
+        self.defects.is_empty()
+    }
+
+    /// Faulty truth tables for the gates of this type and number of inputs
+    pub fn defects(&self, gate_type: &str, nb_inputs: usize) -> &[u64] {
+        self.defects
+            .get(&(gate_type.to_string(), nb_inputs))
+            .map(|v| v.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Gate types and numbers of inputs of the library that match no gate of the network
+    ///
+    /// This happens for misspelled gate types, for a wrong number of inputs, or for gate types
+    /// that do not appear in the network, for example after normalization.
+    pub fn unmatched(&self, aig: &Network) -> Vec<(String, usize)> {
+        let present: HashSet<(&str, usize)> = (0..aig.nb_nodes())
+            .map(|i| aig.gate(i))
+            .filter(|g| gate_function(g).is_some())
+            .map(|g| (g.type_name(), g.dependencies().len()))
+            .collect();
+        let mut ret: Vec<(String, usize)> = self
+            .defects
+            .keys()
+            .filter(|(t, n)| !present.contains(&(t.as_str(), *n)))
+            .cloned()
+            .collect();
+        ret.sort();
+        ret
+    }
+}
+
+/// Mask of the valid bits of a truth table with this number of inputs
+fn table_mask(nb_inputs: usize) -> u64 {
+    if nb_inputs >= 6 {
+        !0
+    } else {
+        (1 << (1 << nb_inputs)) - 1
+    }
+}
+
+/// Truth table of a combinatorial gate over the values on its inputs, for gates with up to 6 inputs
+fn gate_function(g: &Gate) -> Option<u64> {
+    let nb_inputs = g.dependencies().len();
+    if !g.is_comb() || nb_inputs > 6 {
+        return None;
+    }
+    let mut aig = Network::new();
+    aig.add_inputs(nb_inputs);
+    let s = aig.add(g.remap_with_ind(|_, i| Signal::from_input(i as u32)));
+    aig.add_output(s);
+    let inputs = (0..nb_inputs)
+        .map(|i| Lut::nth_var(6, i).blocks()[0])
+        .collect();
+    Some(simulate_multi(&aig, &vec![inputs])[0][0] & table_mask(nb_inputs))
 }
 
 impl Fault {
@@ -41,6 +296,157 @@ impl Fault {
         ret
     }
 
+    /// Get all possible transition faults in a network
+    pub fn all_transition(aig: &Network) -> Vec<Fault> {
+        let mut ret = Vec::new();
+        for gate in 0..aig.nb_nodes() {
+            for value in [false, true] {
+                ret.push(Fault::TransitionFault { gate, value });
+            }
+        }
+        ret
+    }
+
+    /// Get all Lut bit-flip faults in a network
+    pub fn all_lut_bit_flips(aig: &Network) -> Vec<Fault> {
+        let mut ret = Vec::new();
+        for gate in 0..aig.nb_nodes() {
+            if let Gate::Lut(lut) = aig.gate(gate) {
+                for bit in 0..lut.lut.num_bits() {
+                    ret.push(Fault::LutBitFlipFault { gate, bit });
+                }
+            }
+        }
+        ret
+    }
+
+    /// Get all cell-internal defects of the library in a network
+    ///
+    /// Defects that do not change the function of a gate are ignored.
+    pub fn all_defects(aig: &Network, library: &DefectLibrary) -> Vec<Fault> {
+        let mut ret = Vec::new();
+        for gate in 0..aig.nb_nodes() {
+            let g = aig.gate(gate);
+            let Some(function) = gate_function(g) else {
+                continue;
+            };
+            for table in library.defects(g.type_name(), g.dependencies().len()) {
+                if *table != function {
+                    ret.push(Fault::DefectFault {
+                        gate,
+                        table: *table,
+                    });
+                }
+            }
+        }
+        ret
+    }
+
+    /// Get all bridging faults between the given pairs of gates
+    pub fn all_bridging(pairs: &[(usize, usize)]) -> Vec<Fault> {
+        let mut ret = Vec::new();
+        for (x, y) in pairs {
+            if x == y {
+                continue;
+            }
+            let (a, b) = (*x.min(y), *x.max(y));
+            for kind in BridgeType::all() {
+                ret.push(Fault::BridgingFault { a, b, kind });
+            }
+        }
+        ret.sort();
+        ret.dedup();
+        ret
+    }
+
+    /// List candidate pairs for bridging faults, as nearby gates whose logic levels differ by at most k
+    ///
+    /// This is a structural heuristic for nets likely to be routed close to each other: at each
+    /// level of the window, a gate is paired with the few next gates in topological order.
+    /// Gates that are directly connected are not considered.
+    /// At most `max_pairs` pairs are returned, keeping the gates closest in topological order.
+    pub fn bridging_candidates(aig: &Network, k: usize, max_pairs: usize) -> Vec<(usize, usize)> {
+        let levels = compute_levels(aig, false);
+        let max_level = levels.iter().copied().max().unwrap_or(0).max(0) as usize;
+        let mut by_level = vec![Vec::new(); max_level + 1];
+        for (gate, l) in levels.iter().enumerate() {
+            by_level[*l as usize].push(gate);
+        }
+        let connected = |a: usize, b: usize| {
+            let uses = |x: usize, y: usize| {
+                aig.gate(x)
+                    .dependencies()
+                    .iter()
+                    .any(|s| s.is_var() && s.var() as usize == y)
+            };
+            uses(a, b) || uses(b, a)
+        };
+        let mut ret = Vec::new();
+        for (a, l) in levels.iter().enumerate() {
+            let l = *l as usize;
+            let lo = l.saturating_sub(k);
+            let hi = (l + k).min(max_level);
+            for gates in &by_level[lo..=hi] {
+                // Gates are sorted by index at each level
+                let start = gates.partition_point(|b| *b <= a);
+                for b in gates[start..].iter().take(BRIDGE_NEIGHBOURS) {
+                    if !connected(a, *b) {
+                        ret.push((a, *b));
+                    }
+                }
+            }
+        }
+        if ret.len() > max_pairs {
+            ret.sort_by_key(|(a, b)| b - a);
+            ret.truncate(max_pairs);
+        }
+        ret.sort();
+        ret
+    }
+
+    /// Gate where the fault is located; for bridging faults, the first gate of the bridge
+    pub fn gate(&self) -> usize {
+        match self {
+            Fault::OutputStuckAtFault { gate, .. } => *gate,
+            Fault::InputStuckAtFault { gate, .. } => *gate,
+            Fault::TransitionFault { gate, .. } => *gate,
+            Fault::BridgingFault { a, .. } => *a,
+            Fault::LutBitFlipFault { gate, .. } => *gate,
+            Fault::DefectFault { gate, .. } => *gate,
+        }
+    }
+
+    /// Function computed by the faulty gate over its inputs, for Lut bit-flip and cell-internal defects
+    pub fn faulty_function(&self, aig: &Network) -> Option<Lut> {
+        match self {
+            Fault::LutBitFlipFault { gate, bit } => {
+                let Gate::Lut(lut) = aig.gate(*gate) else {
+                    panic!("Lut bit-flip fault on a gate that is not a Lut");
+                };
+                let mut ret = lut.lut.clone();
+                ret.set_value(*bit, !ret.value(*bit));
+                Some(ret)
+            }
+            Fault::DefectFault { gate, table } => {
+                let nb_inputs = aig.gate(*gate).dependencies().len();
+                assert!(nb_inputs <= 6);
+                Some(Lut::from_blocks(
+                    nb_inputs,
+                    &[*table & table_mask(nb_inputs)],
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// All gates affected by the fault
+    pub fn gates(&self) -> Vec<usize> {
+        match self {
+            Fault::BridgingFault { a, b, .. } => vec![*a, *b],
+            _ => vec![self.gate()],
+        }
+    }
+
     /// Get all possible non-redundant faults in a network
     pub fn all_unique(aig: &Network) -> Vec<Fault> {
         let mut ret = Fault::all(aig);
@@ -102,15 +508,124 @@ impl Fault {
         ret
     }
 
-    /// Return true if there are two faults with the same gate in the vector
-    pub fn has_duplicate_gate(faults: &Vec<Fault>) -> bool {
-        let mut gates = Vec::new();
-        for f in faults {
-            match f {
-                Fault::OutputStuckAtFault { gate, .. } => gates.push(*gate),
-                Fault::InputStuckAtFault { gate, .. } => gates.push(*gate),
+    /// Group the faults of the network into structurally equivalent classes
+    ///
+    /// Two faults are equivalent if they are detected by the same patterns. We use two rules:
+    ///     * an input stuck-at fault that forces the output of the gate is equivalent to the
+    ///       corresponding output stuck-at fault (e.g. And input s-a-0 and output s-a-0);
+    ///     * the output stuck-at fault of a gate with a single fanout is equivalent to the
+    ///       stuck-at fault on the input it drives.
+    /// Flip-flops are not traversed.
+    pub fn equivalence_classes(aig: &Network) -> Vec<Vec<Fault>> {
+        let mut faults = Fault::all(aig);
+        faults.sort();
+        let index = |f: &Fault| faults.binary_search(f).unwrap();
+        let mut uf = UnionFind::new(faults.len());
+
+        let usage = stats::count_gate_usage(aig);
+        let is_output = stats::gate_is_output(aig);
+        for gate in 0..aig.nb_nodes() {
+            let g = aig.gate(gate);
+            if !g.is_comb() {
+                continue;
+            }
+            for (input, s) in g.dependencies().iter().enumerate() {
+                for value in [false, true] {
+                    let f = index(&Fault::InputStuckAtFault { gate, input, value });
+                    if let Some(out) = forced_output(g, input, value) {
+                        let o = index(&Fault::OutputStuckAtFault { gate, value: out });
+                        uf.union(f, o);
+                    }
+                    if s.is_var() && usage[s.var() as usize] == 1 && !is_output[s.var() as usize] {
+                        let o = index(&Fault::OutputStuckAtFault {
+                            gate: s.var() as usize,
+                            value: value ^ s.is_inverted(),
+                        });
+                        uf.union(f, o);
+                    }
+                }
+            }
+        }
+
+        let mut classes = vec![Vec::new(); faults.len()];
+        for (i, f) in faults.iter().enumerate() {
+            classes[uf.find(i)].push(*f);
+        }
+        classes.retain(|c| !c.is_empty());
+        classes
+    }
+
+    /// Representative of an equivalence class: the last output stuck-at fault, or the last fault
+    fn representative(class: &[Fault]) -> Fault {
+        class
+            .iter()
+            .filter(|f| matches!(f, Fault::OutputStuckAtFault { .. }))
+            .max()
+            .or(class.iter().max())
+            .copied()
+            .unwrap()
+    }
+
+    /// Get the faults of the network, collapsed by structural equivalence
+    pub fn all_collapsed(aig: &Network) -> Vec<Fault> {
+        let mut ret: Vec<Fault> = Fault::equivalence_classes(aig)
+            .iter()
+            .map(|c| Fault::representative(c))
+            .collect();
+        ret.sort();
+        ret
+    }
+
+    /// Get the faults of the network, collapsed by structural equivalence and dominance
+    ///
+    /// A fault dominates another if every test for the other also detects it, so that it does
+    /// not need to be targeted. If an input stuck-at-v fault forces the output of the gate to c,
+    /// the output stuck-at-!c fault dominates the input stuck-at-!v fault (e.g. And output s-a-1
+    /// dominates its input s-a-1). The output stuck-at-v fault of a Mux dominates its data inputs
+    /// stuck-at-v, and the output stuck-at-v fault of a Maj dominates its inputs stuck-at-v.
+    /// Coverage on this list is only meaningful for test generation, and a dominating fault may
+    /// still be testable if the fault it dominates is redundant.
+    pub fn all_dominance_collapsed(aig: &Network) -> Vec<Fault> {
+        let classes = Fault::equivalence_classes(aig);
+        let mut class_of = HashMap::new();
+        for (i, c) in classes.iter().enumerate() {
+            for f in c {
+                class_of.insert(*f, i);
+            }
+        }
+        let mut dropped = vec![false; classes.len()];
+        // Dominated faults are on earlier gates, so that dominance chains are followed in order
+        for gate in (0..aig.nb_nodes()).rev() {
+            let g = aig.gate(gate);
+            if !g.is_comb() {
+                continue;
+            }
+            for input in 0..g.dependencies().len() {
+                for value in [false, true] {
+                    let Some(out) = dominating_output(g, input, value) else {
+                        continue;
+                    };
+                    let dominating = class_of[&Fault::OutputStuckAtFault { gate, value: out }];
+                    let dominated = class_of[&Fault::InputStuckAtFault { gate, input, value }];
+                    if dominating != dominated && !dropped[dominated] {
+                        dropped[dominating] = true;
+                    }
+                }
             }
         }
+        let mut ret: Vec<Fault> = classes
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !dropped[*i])
+            .map(|(_, c)| Fault::representative(c))
+            .collect();
+        ret.sort();
+        ret
+    }
+
+    /// Return true if there are two faults with the same gate in the vector
+    pub fn has_duplicate_gate(faults: &[Fault]) -> bool {
+        let mut gates: Vec<usize> = faults.iter().flat_map(|f| f.gates()).collect();
         gates.sort();
         for i in 1..gates.len() {
             if gates[i - 1] == gates[i] {
@@ -136,6 +651,22 @@ impl fmt::Display for Fault {
                     i32::from(*value)
                 )
             }
+            Fault::TransitionFault { gate, value } => {
+                if *value {
+                    write!(f, "Gate {} output slow to rise", gate)
+                } else {
+                    write!(f, "Gate {} output slow to fall", gate)
+                }
+            }
+            Fault::BridgingFault { a, b, kind } => {
+                write!(f, "Gates {} and {} bridged ({})", a, b, kind)
+            }
+            Fault::LutBitFlipFault { gate, bit } => {
+                write!(f, "Gate {} Lut bit {} flipped", gate, bit)
+            }
+            Fault::DefectFault { gate, table } => {
+                write!(f, "Gate {} defect with function {:x}", gate, table)
+            }
         }
     }
 }
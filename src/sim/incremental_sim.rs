@@ -9,6 +9,8 @@ use super::Fault;
 
 /// Structure for simulation that only touches the values that were modified
 pub struct IncrementalSimulator<'a> {
+    /// The network
+    aig: &'a Network,
     /// Whether a gate is an output
     is_output: Vec<bool>,
     /// Gates that use each gate
@@ -32,6 +34,7 @@ impl<'a> IncrementalSimulator<'a> {
         let sim = SimpleSimulator::from_aig(aig);
         let incr_sim = sim.clone();
         IncrementalSimulator {
+            aig,
             is_output: stats::gate_is_output(aig),
             gate_users: stats::gate_users(aig),
             sim,
@@ -83,6 +86,8 @@ impl<'a> IncrementalSimulator<'a> {
 
     /// Run the simulation from a fault
     fn run_incremental(&mut self, fault: Fault) {
+        // Bridged nets keep their value, even if they depend on each other
+        let mut bridge = None;
         match fault {
             Fault::OutputStuckAtFault { gate, value } => {
                 self.update_gate(gate, if value { !0 } else { 0 });
@@ -91,8 +96,25 @@ impl<'a> IncrementalSimulator<'a> {
                 let value = self.incr_sim.run_gate_with_input_stuck(gate, input, value);
                 self.update_gate(gate, value);
             }
+            Fault::TransitionFault { .. } => {
+                // Single timestep: the gate has no previous value to keep
+            }
+            Fault::BridgingFault { a, b, kind } => {
+                let (va, vb) = kind.apply(self.sim.node_values[a], self.sim.node_values[b]);
+                self.update_gate(a, va);
+                self.update_gate(b, vb);
+                bridge = Some((a, b));
+            }
+            Fault::LutBitFlipFault { gate, .. } | Fault::DefectFault { gate, .. } => {
+                let lut = fault.faulty_function(self.aig).unwrap();
+                let value = self.incr_sim.run_gate_with_function(gate, &lut);
+                self.update_gate(gate, value);
+            }
         }
         while let Some(Reverse(i)) = self.update_queue.pop() {
+            if bridge.is_some_and(|(a, b)| i == a || i == b) {
+                continue;
+            }
             let v = self.incr_sim.run_gate(i);
             self.update_gate(i, v);
         }
@@ -110,6 +132,8 @@ impl<'a> IncrementalSimulator<'a> {
     }
 
     /// Whether the given fault is detected by the pattern
+    ///
+    /// Transition faults require two timesteps, and are never detected by a single pattern.
     pub fn detects_fault(&mut self, fault: Fault) -> u64 {
         self.run_incremental(fault);
         let ret = self.output_modified();
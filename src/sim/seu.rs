@@ -0,0 +1,273 @@
+use std::fmt;
+use std::io::Write;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::network::stats;
+use crate::Network;
+
+use super::simple_sim::SimpleSimulator;
+use super::{nb_threads_or_available, run_task_pool};
+
+/// Vulnerability of the nodes of a network to single-event upsets
+#[derive(Clone, Debug)]
+pub struct VulnerabilityReport {
+    /// Number of cycles after the upset during which the outputs are observed
+    pub window: usize,
+    /// Number of upsets injected on each node
+    pub nb_injections: usize,
+    /// Number of upsets on each node that reached an output within the window
+    pub nb_failures: Vec<usize>,
+    /// Gate indices of the flip-flops
+    pub dffs: Vec<usize>,
+}
+
+impl VulnerabilityReport {
+    /// Architectural vulnerability factor of a node: the fraction of its upsets that reach an output
+    pub fn avf(&self, gate: usize) -> f64 {
+        if self.nb_injections == 0 {
+            0.0
+        } else {
+            self.nb_failures[gate] as f64 / self.nb_injections as f64
+        }
+    }
+
+    /// Average vulnerability factor of the given nodes
+    fn mean_avf(&self, gates: impl Iterator<Item = usize>) -> f64 {
+        let (sum, nb) = gates.fold((0.0, 0), |(s, n), g| (s + self.avf(g), n + 1));
+        if nb == 0 {
+            0.0
+        } else {
+            sum / nb as f64
+        }
+    }
+
+    /// Rank the nodes from most to least vulnerable, with their vulnerability factor
+    pub fn ranked_nodes(&self) -> Vec<(usize, f64)> {
+        let mut ret: Vec<(usize, f64)> = (0..self.nb_failures.len())
+            .map(|g| (g, self.avf(g)))
+            .collect();
+        ret.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        ret
+    }
+
+    /// Rank the flip-flops from most to least vulnerable, with their vulnerability factor
+    pub fn ranked_dffs(&self) -> Vec<(usize, f64)> {
+        let mut ret = self.ranked_nodes();
+        ret.retain(|(g, _)| self.dffs.binary_search(g).is_ok());
+        ret
+    }
+
+    /// Write the vulnerability of each node in CSV format
+    pub fn write_csv<W: Write>(&self, w: &mut W) {
+        writeln!(w, "gate,dff,injections,failures,avf").unwrap();
+        for (g, f) in self.nb_failures.iter().enumerate() {
+            writeln!(
+                w,
+                "x{},{},{},{},{:.6}",
+                g,
+                self.dffs.binary_search(&g).is_ok(),
+                self.nb_injections,
+                f,
+                self.avf(g)
+            )
+            .unwrap();
+        }
+    }
+}
+
+impl fmt::Display for VulnerabilityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nb_nodes = self.nb_failures.len();
+        writeln!(
+            f,
+            "Injected {} upsets on each of {} nodes, observed for {} cycles",
+            self.nb_injections, nb_nodes, self.window
+        )?;
+        writeln!(
+            f,
+            "Average vulnerability factor: {:.2}% over all nodes",
+            100.0 * self.mean_avf(0..nb_nodes)
+        )?;
+        if !self.dffs.is_empty() {
+            writeln!(
+                f,
+                "Average vulnerability factor: {:.2}% over {} flip-flops",
+                100.0 * self.mean_avf(self.dffs.iter().copied()),
+                self.dffs.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Fault-free simulation of a random workload, 64 sequences at a time
+struct Workload {
+    /// Input values at each cycle
+    inputs: Vec<Vec<u64>>,
+    /// Node values at the start of each cycle, with the new flip-flop states
+    states: Vec<Vec<u64>>,
+    /// Node values at the end of each cycle
+    nodes: Vec<Vec<u64>>,
+    /// Output values at each cycle
+    outputs: Vec<Vec<u64>>,
+}
+
+impl Workload {
+    fn simulate(aig: &Network, inputs: Vec<Vec<u64>>) -> Workload {
+        let mut sim = SimpleSimulator::from_aig(aig);
+        let mut states = Vec::new();
+        let mut nodes = Vec::new();
+        let mut outputs = Vec::new();
+        for (t, v) in inputs.iter().enumerate() {
+            if t != 0 {
+                sim.run_dff();
+            }
+            sim.copy_inputs(v);
+            states.push(sim.node_values.clone());
+            sim.run_comb();
+            nodes.push(sim.node_values.clone());
+            outputs.push(sim.get_output_values());
+        }
+        Workload {
+            inputs,
+            states,
+            nodes,
+            outputs,
+        }
+    }
+
+    /// Sequences where an upset on the node at a given cycle reaches an output within the window
+    fn failures(&self, sim: &mut SimpleSimulator, gate: usize, cycle: usize, window: usize) -> u64 {
+        sim.node_values.copy_from_slice(&self.states[cycle]);
+        sim.copy_inputs(&self.inputs[cycle]);
+        // The upset flips the state of a flip-flop, or the output of a gate for this cycle
+        for i in 0..sim.node_values.len() {
+            sim.node_values[i] = sim.run_gate(i);
+            if i == gate {
+                sim.node_values[i] = !sim.node_values[i];
+            }
+        }
+        let last = (cycle + window).min(self.inputs.len() - 1);
+        let mut ret = 0;
+        for t in cycle..=last {
+            if t != cycle {
+                sim.run_dff();
+                sim.copy_inputs(&self.inputs[t]);
+                sim.run_comb();
+            }
+            for (a, b) in sim.get_output_values().iter().zip(&self.outputs[t]) {
+                ret |= a ^ b;
+            }
+            // Stop once the upset is observed everywhere, or has vanished
+            if ret == !0 || sim.node_values == self.nodes[t] {
+                break;
+            }
+        }
+        ret
+    }
+}
+
+/// Analyze the vulnerability of a network to single-event upsets (soft errors)
+///
+/// Random input sequences are simulated from reset, 64 at a time with the bit-parallel simulator,
+/// and the number of sequences is rounded up to a multiple of 64. At each of the first
+/// `nb_cycles` cycles, an upset is injected on each node in turn: for a flip-flop it flips the
+/// stored state, for another gate it flips its output for the duration of the cycle. An upset
+/// causes a failure if it changes an output during this cycle or the `window` next cycles.
+/// Simulation uses the given number of threads, or all available cores for 0.
+pub fn analyze_seu(
+    aig: &Network,
+    nb_cycles: usize,
+    window: usize,
+    nb_sequences: usize,
+    seed: u64,
+    nb_threads: usize,
+) -> VulnerabilityReport {
+    assert!(aig.is_topo_sorted());
+    assert!(nb_cycles >= 1);
+    let nb_words = nb_sequences.div_ceil(64).max(1);
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let workloads: Vec<Workload> = (0..nb_words)
+        .map(|_| {
+            let inputs = (0..nb_cycles + window)
+                .map(|_| (0..aig.nb_inputs()).map(|_| rng.gen()).collect())
+                .collect();
+            Workload::simulate(aig, inputs)
+        })
+        .collect();
+
+    // One task per gate where an upset is injected
+    let nb_workers = nb_threads_or_available(nb_threads)
+        .min(aig.nb_nodes())
+        .max(1);
+    let mut sims: Vec<SimpleSimulator> = (0..nb_workers)
+        .map(|_| SimpleSimulator::from_aig(aig))
+        .collect();
+    let nb_failures = run_task_pool(&mut sims, aig.nb_nodes(), |sim, gate| {
+        let mut nb_failures = 0;
+        for w in &workloads {
+            for cycle in 0..nb_cycles {
+                nb_failures += w.failures(sim, gate, cycle, window).count_ones() as usize;
+            }
+        }
+        nb_failures
+    });
+    VulnerabilityReport {
+        window,
+        nb_injections: 64 * nb_words * nb_cycles,
+        nb_failures,
+        dffs: stats::dff_gates(aig),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::analyze_seu;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_comb_seu() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let x = aig.xor(i0, i1);
+        let a = aig.and(x, i2);
+        aig.add_output(a);
+        let report = analyze_seu(&aig, 4, 0, 256, 1, 1);
+        assert_eq!(report.nb_injections, 1024);
+        // The output is always observed; the Xor is masked when i2 is 0
+        assert_eq!(report.avf(1), 1.0);
+        assert!(report.avf(0) > 0.4 && report.avf(0) < 0.6);
+        assert_eq!(report.ranked_nodes()[0].0, 1);
+        assert!(report.ranked_dffs().is_empty());
+        // The result does not depend on the number of threads
+        assert_eq!(
+            analyze_seu(&aig, 4, 0, 256, 1, 2).nb_failures,
+            report.nb_failures
+        );
+    }
+
+    #[test]
+    fn test_seq_seu() {
+        // Shift register with two stages, and a flip-flop that is never observed
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let d0 = aig.add(Gate::dff(i0, Signal::one(), Signal::zero()));
+        let d1 = aig.add(Gate::dff(d0, Signal::one(), Signal::zero()));
+        aig.add(Gate::dff(i0, Signal::one(), Signal::zero()));
+        aig.add_output(d1);
+
+        // The first stage needs one cycle to reach the output
+        let report = analyze_seu(&aig, 8, 0, 64, 1, 1);
+        assert_eq!(report.avf(0), 0.0);
+        assert_eq!(report.avf(1), 1.0);
+        let report = analyze_seu(&aig, 8, 1, 64, 1, 1);
+        assert_eq!(report.avf(0), 1.0);
+        assert_eq!(report.avf(1), 1.0);
+        assert_eq!(report.avf(2), 0.0);
+        assert_eq!(report.ranked_dffs(), vec![(0, 1.0), (1, 1.0), (2, 0.0)]);
+    }
+}
@@ -1,9 +1,99 @@
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
 use volute::Lut;
 
-use crate::network::{BinaryType, NaryType, TernaryType};
-use crate::{Network, Signal};
+use crate::network::{stats, BinaryType, NaryType, TernaryType};
+use crate::{Gate, Network, Signal};
+
+use super::{BridgeType, Fault};
+
+/// 64 values of 3-valued logic (0/1/X), with a dual-rail encoding
+///
+/// Each value is represented by two bits: whether it may be 0 and whether it may be 1.
+/// Known values have exactly one of them set, and unknown values have both.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XWord {
+    /// Values that may be 0
+    pub zero: u64,
+    /// Values that may be 1
+    pub one: u64,
+}
+
+impl XWord {
+    /// Known values
+    pub fn known(value: u64) -> XWord {
+        XWord {
+            zero: !value,
+            one: value,
+        }
+    }
+
+    /// Unknown values
+    pub fn unknown() -> XWord {
+        XWord { zero: !0, one: !0 }
+    }
+
+    /// Mask of the known values
+    pub fn is_known(&self) -> u64 {
+        self.zero ^ self.one
+    }
+
+    /// Multiplexer: a where s is 1, b where s is 0, without pessimism when a and b agree
+    fn mux(s: XWord, a: XWord, b: XWord) -> XWord {
+        XWord {
+            zero: (s.one & a.zero) | (s.zero & b.zero),
+            one: (s.one & a.one) | (s.zero & b.one),
+        }
+    }
+
+    /// Majority function
+    fn maj(a: XWord, b: XWord, c: XWord) -> XWord {
+        XWord {
+            zero: maj(a.zero, b.zero, c.zero),
+            one: maj(a.one, b.one, c.one),
+        }
+    }
+}
+
+impl Not for XWord {
+    type Output = XWord;
+    fn not(self) -> XWord {
+        XWord {
+            zero: self.one,
+            one: self.zero,
+        }
+    }
+}
+
+impl BitAnd for XWord {
+    type Output = XWord;
+    fn bitand(self, rhs: XWord) -> XWord {
+        XWord {
+            zero: self.zero | rhs.zero,
+            one: self.one & rhs.one,
+        }
+    }
+}
+
+impl BitOr for XWord {
+    type Output = XWord;
+    fn bitor(self, rhs: XWord) -> XWord {
+        XWord {
+            zero: self.zero & rhs.zero,
+            one: self.one | rhs.one,
+        }
+    }
+}
 
-use super::Fault;
+impl BitXor for XWord {
+    type Output = XWord;
+    fn bitxor(self, rhs: XWord) -> XWord {
+        XWord {
+            zero: (self.zero & rhs.zero) | (self.one & rhs.one),
+            one: (self.zero & rhs.one) | (self.one & rhs.zero),
+        }
+    }
+}
 
 /// Structure for simulation based directly on the network representation
 ///
@@ -14,6 +104,12 @@ pub struct SimpleSimulator<'a> {
     aig: &'a Network,
     pub input_values: Vec<u64>,
     pub node_values: Vec<u64>,
+    /// Values of the nodes at the previous timestep, used for transition faults
+    pub prev_node_values: Option<Vec<u64>>,
+    /// Values of the inputs for 3-valued simulation
+    pub x_input_values: Vec<XWord>,
+    /// Values of the nodes for 3-valued simulation
+    pub x_node_values: Vec<XWord>,
 }
 
 /// Convert the inversion to a word for bitwise operations
@@ -40,6 +136,9 @@ impl<'a> SimpleSimulator<'a> {
             aig,
             input_values: vec![0; aig.nb_inputs()],
             node_values: vec![0; aig.nb_nodes()],
+            prev_node_values: None,
+            x_input_values: Vec::new(),
+            x_node_values: Vec::new(),
         }
     }
 
@@ -47,6 +146,21 @@ impl<'a> SimpleSimulator<'a> {
     pub fn run(&mut self, input_values: &Vec<Vec<u64>>) -> Vec<Vec<u64>> {
         self.check();
         self.reset();
+        self.run_steps(input_values)
+    }
+
+    /// Run the simulation, starting from the given flip-flop values
+    ///
+    /// The values are given in the order of the flip-flops in the network.
+    pub fn run_from(&mut self, dff_values: &[u64], input_values: &Vec<Vec<u64>>) -> Vec<Vec<u64>> {
+        self.check();
+        self.reset();
+        self.set_dff_values(dff_values);
+        self.run_steps(input_values)
+    }
+
+    /// Run the simulation from the current state
+    fn run_steps(&mut self, input_values: &Vec<Vec<u64>>) -> Vec<Vec<u64>> {
         let mut ret = Vec::new();
         for (i, v) in input_values.iter().enumerate() {
             if i != 0 {
@@ -59,7 +173,10 @@ impl<'a> SimpleSimulator<'a> {
         ret
     }
 
-    /// Run the simulation with a list of stuck-at-fault errors
+    /// Run the simulation with a list of faults
+    ///
+    /// Transition faults use the values of the previous timestep, so they have no effect on the first one.
+    /// With two timesteps, this is the two-frame simulation used for delay testing.
     pub fn run_with_faults(
         &mut self,
         input_values: &Vec<Vec<u64>>,
@@ -70,6 +187,7 @@ impl<'a> SimpleSimulator<'a> {
         let mut ret = Vec::new();
         for (i, v) in input_values.iter().enumerate() {
             if i != 0 {
+                self.prev_node_values = Some(self.node_values.clone());
                 self.run_dff();
             }
             self.copy_inputs(v.as_slice());
@@ -82,6 +200,16 @@ impl<'a> SimpleSimulator<'a> {
     pub fn reset(&mut self) {
         self.input_values = vec![0; self.aig.nb_inputs()];
         self.node_values = vec![0; self.aig.nb_nodes()];
+        self.prev_node_values = None;
+    }
+
+    /// Set the values of the flip-flops, in the order of the flip-flops in the network
+    pub fn set_dff_values(&mut self, dff_values: &[u64]) {
+        let dffs = stats::dff_gates(self.aig);
+        assert_eq!(dffs.len(), dff_values.len());
+        for (i, v) in dffs.into_iter().zip(dff_values) {
+            self.node_values[i] = *v;
+        }
     }
 
     fn check(&self) {
@@ -213,9 +341,32 @@ impl<'a> SimpleSimulator<'a> {
         }
     }
 
-    /// Run the combinatorial part of the design with a list of stuck-at-fault errors
+    /// Return the result of a single gate, with its function replaced by a truth table over its inputs
+    pub fn run_gate_with_function(&self, i: usize, lut: &Lut) -> u64 {
+        let signals = self.aig.gate(i).dependencies();
+        assert_eq!(lut.num_vars(), signals.len());
+        self.compute_lut(lut, signals)
+    }
+
+    /// Run the combinatorial part of the design with a list of faults
+    ///
+    /// Bridged nets take their values from a first simulation without the bridges.
     pub fn run_comb_with_faults(&mut self, faults: &Vec<Fault>) {
         assert!(!Fault::has_duplicate_gate(faults));
+        let has_bridges = faults
+            .iter()
+            .any(|f| matches!(f, Fault::BridgingFault { .. }));
+        let drivers = if has_bridges {
+            let other_faults = faults
+                .iter()
+                .filter(|f| !matches!(f, Fault::BridgingFault { .. }))
+                .copied()
+                .collect();
+            self.run_comb_with_faults(&other_faults);
+            self.node_values.clone()
+        } else {
+            Vec::new()
+        };
         for i in 0..self.aig.nb_nodes() {
             self.node_values[i] = self.run_gate(i);
             for f in faults {
@@ -231,6 +382,33 @@ impl<'a> SimpleSimulator<'a> {
                                 self.run_gate_with_input_stuck(*gate, *input, *value);
                         }
                     }
+                    Fault::TransitionFault { gate, value } => {
+                        if *gate == i {
+                            if let Some(prev) = &self.prev_node_values {
+                                // The gate only switches if it already had the value before
+                                self.node_values[i] = if *value {
+                                    self.node_values[i] & prev[i]
+                                } else {
+                                    self.node_values[i] | prev[i]
+                                };
+                            }
+                        }
+                    }
+                    Fault::BridgingFault { a, b, kind } => {
+                        let (va, vb) = kind.apply(drivers[*a], drivers[*b]);
+                        if *a == i {
+                            self.node_values[i] = va;
+                        }
+                        if *b == i {
+                            self.node_values[i] = vb;
+                        }
+                    }
+                    Fault::LutBitFlipFault { gate, .. } | Fault::DefectFault { gate, .. } => {
+                        if *gate == i {
+                            let lut = f.faulty_function(self.aig).unwrap();
+                            self.node_values[i] = self.run_gate_with_function(i, &lut);
+                        }
+                    }
                 }
             }
         }
@@ -310,7 +488,7 @@ impl<'a> SimpleSimulator<'a> {
         }
     }
 
-    fn get_output_values(&self) -> Vec<u64> {
+    pub(crate) fn get_output_values(&self) -> Vec<u64> {
         let mut ret = Vec::new();
         for o in 0..self.aig.nb_outputs() {
             ret.push(self.get_value(self.aig.output(o)));
@@ -339,6 +517,216 @@ impl<'a> SimpleSimulator<'a> {
 
         compute_lut(lut, &signals)
     }
+
+    /// Run the 3-valued simulation, with the flip-flops starting in an unknown state
+    pub fn run_x(&mut self, input_values: &[Vec<XWord>]) -> Vec<Vec<XWord>> {
+        self.check();
+        self.reset_x();
+        let mut ret = Vec::new();
+        for (i, v) in input_values.iter().enumerate() {
+            if i != 0 {
+                self.run_dff_x();
+            }
+            self.copy_inputs_x(v.as_slice());
+            self.run_comb_x();
+            ret.push(self.get_output_values_x());
+        }
+        ret
+    }
+
+    /// Reset the state for 3-valued simulation, with all values unknown
+    pub fn reset_x(&mut self) {
+        self.x_input_values = vec![XWord::unknown(); self.aig.nb_inputs()];
+        self.x_node_values = vec![XWord::unknown(); self.aig.nb_nodes()];
+    }
+
+    // Get the value of a signal in the current state of the 3-valued simulation
+    fn get_value_x(&self, s: Signal) -> XWord {
+        let v = if s.without_inversion() == Signal::zero() {
+            XWord::known(0)
+        } else if s.is_input() {
+            self.x_input_values[s.input() as usize]
+        } else {
+            debug_assert!(s.is_var());
+            self.x_node_values[s.var() as usize]
+        };
+        if s.is_inverted() {
+            !v
+        } else {
+            v
+        }
+    }
+
+    // Copy the values of the inputs to the internal state of the 3-valued simulation
+    pub fn copy_inputs_x(&mut self, inputs: &[XWord]) {
+        assert_eq!(inputs.len(), self.x_input_values.len());
+        self.x_input_values.copy_from_slice(inputs);
+    }
+
+    // Copy the values of the flip-flops for the next cycle of the 3-valued simulation
+    pub fn run_dff_x(&mut self) {
+        let mut next_values = self.x_node_values.clone();
+        for (i, next) in next_values.iter_mut().enumerate() {
+            if let Gate::Dff([d, en, res]) = self.aig.gate(i) {
+                let prev = self.x_node_values[i];
+                let v = XWord::mux(self.get_value_x(*en), self.get_value_x(*d), prev);
+                *next = !self.get_value_x(*res) & v;
+            }
+        }
+        self.x_node_values = next_values;
+    }
+
+    /// Return the result of a single gate in 3-valued simulation
+    pub fn run_gate_x(&self, i: usize) -> XWord {
+        self.run_gate_x_with_input(i, None)
+    }
+
+    /// Return the result of a single gate in 3-valued simulation, with an optional value forced on an input
+    fn run_gate_x_with_input(&self, i: usize, forced: Option<(usize, XWord)>) -> XWord {
+        let g = self.aig.gate(i);
+        if let Gate::Dff(_) = g {
+            return self.x_node_values[i];
+        }
+        let mut v: Vec<XWord> = g
+            .dependencies()
+            .iter()
+            .map(|s| self.get_value_x(*s))
+            .collect();
+        if let Some((input, value)) = forced {
+            v[input] = value;
+        }
+        compute_gate_x(g, &v)
+    }
+
+    /// Run the combinatorial part of the design in 3-valued simulation
+    pub fn run_comb_x(&mut self) {
+        for i in 0..self.aig.nb_nodes() {
+            self.x_node_values[i] = self.run_gate_x(i);
+        }
+    }
+
+    /// Run the combinatorial part of the design in 3-valued simulation with a list of faults
+    ///
+    /// Only the gates from `start` are simulated: the values of the previous gates are kept, and
+    /// must not depend on the faults. Bridged nets take their values from a first simulation
+    /// without the bridges. Transition faults are not supported.
+    pub fn run_comb_x_with_faults(&mut self, faults: &[Fault], start: usize) {
+        assert!(!Fault::has_duplicate_gate(faults));
+        let has_bridges = faults
+            .iter()
+            .any(|f| matches!(f, Fault::BridgingFault { .. }));
+        let drivers = if has_bridges {
+            let other_faults: Vec<Fault> = faults
+                .iter()
+                .filter(|f| !matches!(f, Fault::BridgingFault { .. }))
+                .copied()
+                .collect();
+            self.run_comb_x_with_faults(&other_faults, start);
+            self.x_node_values.clone()
+        } else {
+            Vec::new()
+        };
+        for i in start..self.aig.nb_nodes() {
+            self.x_node_values[i] = self.run_gate_x(i);
+            for f in faults {
+                match f {
+                    Fault::OutputStuckAtFault { gate, value } => {
+                        if *gate == i {
+                            self.x_node_values[i] = XWord::known(if *value { !0 } else { 0 });
+                        }
+                    }
+                    Fault::InputStuckAtFault { gate, input, value } => {
+                        if *gate == i {
+                            let v = XWord::known(if *value { !0 } else { 0 });
+                            self.x_node_values[i] =
+                                self.run_gate_x_with_input(i, Some((*input, v)));
+                        }
+                    }
+                    Fault::TransitionFault { .. } => {
+                        panic!("Transition faults are not supported in 3-valued simulation")
+                    }
+                    Fault::BridgingFault { a, b, kind } => {
+                        let (va, vb) = (drivers[*a], drivers[*b]);
+                        let (va, vb) = match kind {
+                            BridgeType::WiredAnd => (va & vb, va & vb),
+                            BridgeType::WiredOr => (va | vb, va | vb),
+                            BridgeType::DominantA => (va, va),
+                            BridgeType::DominantB => (vb, vb),
+                        };
+                        if *a == i {
+                            self.x_node_values[i] = va;
+                        }
+                        if *b == i {
+                            self.x_node_values[i] = vb;
+                        }
+                    }
+                    Fault::LutBitFlipFault { gate, .. } | Fault::DefectFault { gate, .. } => {
+                        if *gate == i {
+                            let lut = f.faulty_function(self.aig).unwrap();
+                            let v: Vec<XWord> = self
+                                .aig
+                                .gate(i)
+                                .dependencies()
+                                .iter()
+                                .map(|s| self.get_value_x(*s))
+                                .collect();
+                            self.x_node_values[i] = compute_lut_x(&lut, &v);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Values of the outputs in 3-valued simulation
+    pub fn get_output_values_x(&self) -> Vec<XWord> {
+        (0..self.aig.nb_outputs())
+            .map(|o| self.get_value_x(self.aig.output(o)))
+            .collect()
+    }
+}
+
+/// Compute a combinatorial gate in 3-valued logic from the values of its inputs
+fn compute_gate_x(g: &Gate, v: &[XWord]) -> XWord {
+    use crate::Gate::*;
+    let and_all = |v: &[XWord]| v.iter().fold(XWord::known(!0), |a, b| a & *b);
+    let or_all = |v: &[XWord]| v.iter().fold(XWord::known(0), |a, b| a | *b);
+    let xor_all = |v: &[XWord]| v.iter().fold(XWord::known(0), |a, b| a ^ *b);
+    match g {
+        Binary(_, BinaryType::And) | Ternary(_, TernaryType::And) => and_all(v),
+        Binary(_, BinaryType::Xor) | Ternary(_, TernaryType::Xor) => xor_all(v),
+        Ternary(_, TernaryType::Maj) => XWord::maj(v[0], v[1], v[2]),
+        Ternary(_, TernaryType::Mux) => XWord::mux(v[0], v[1], v[2]),
+        Nary(_, tp) => match tp {
+            NaryType::And => and_all(v),
+            NaryType::Or => or_all(v),
+            NaryType::Nand => !and_all(v),
+            NaryType::Nor => !or_all(v),
+            NaryType::Xor => xor_all(v),
+            NaryType::Xnor => !xor_all(v),
+        },
+        Buf(_) => v[0],
+        Lut(lut) => compute_lut_x(&lut.lut, v),
+        Dff(_) => panic!("Flip-flops are not combinatorial gates"),
+    }
+}
+
+/// Compute a Lut in 3-valued logic: the output is known if all values of the unknown inputs agree
+fn compute_lut_x(lut: &Lut, signals: &[XWord]) -> XWord {
+    let mut ret = XWord { zero: 0, one: 0 };
+    for mask in 0..lut.num_bits() {
+        // Patterns where this row of the truth table may be selected
+        let mut selected = !0;
+        for (i, s) in signals.iter().enumerate() {
+            selected &= if (mask >> i) & 1 != 0 { s.one } else { s.zero };
+        }
+        if lut.value(mask) {
+            ret.one |= selected;
+        } else {
+            ret.zero |= selected;
+        }
+    }
+    ret
 }
 
 #[inline]
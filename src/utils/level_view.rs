@@ -46,7 +46,10 @@ impl<'a> LevelViewBuilder<'a> {
         let mut levels = vec![0; self.ntk.nb_nodes()];
 
         for po in 0..self.ntk.nb_outputs() {
-            self.update(self.ntk.output(po).var(), &mut levels);
+            let o = self.ntk.output(po);
+            if o.is_var() {
+                self.update(o.var(), &mut levels);
+            }
         }
 
         levels